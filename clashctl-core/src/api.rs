@@ -1,27 +1,42 @@
 use std::{
-    io::{BufRead, BufReader, Read},
+    collections::HashMap,
+    io::{BufRead, BufReader, ErrorKind as IoErrorKind, Read},
     marker::PhantomData,
-    time::Duration,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use log::{debug, trace};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use log::{debug, trace, warn};
 use serde::de::DeserializeOwned;
 use serde_json::{from_str, json};
-use ureq::{Agent, Request};
+use ureq::{Agent, AgentBuilder, Request, Response};
 use url::Url;
 
 use crate::{
-    model::{Config, Connections, Delay, Log, Proxies, Proxy, Rules, Traffic, Version},
-    Error, Result,
+    model::{
+        Config, Connections, Delay, Level, Log, Mode, Provider, Providers, Proxies, Proxy, Rules,
+        Traffic, Version,
+    },
+    Error, Result, Tracer,
 };
 
+/// Compressions asked for in requests, all decoded by [`decode_body`]
+const ACCEPT_ENCODING: &str = "gzip, deflate";
+
 trait Convert<T: DeserializeOwned> {
-    fn convert(self) -> Result<T>;
+    /// Deserialize response of `endpoint`, which is kept in error for context
+    fn convert(self, endpoint: &str) -> Result<T>;
 }
 
 impl<T: DeserializeOwned> Convert<T> for String {
-    fn convert(self) -> Result<T> {
-        from_str(&self).map_err(Into::into)
+    fn convert(self, endpoint: &str) -> Result<T> {
+        from_str(&self).map_err(|e| Error::bad_response_format(endpoint, e))
     }
 }
 
@@ -30,20 +45,28 @@ pub struct ClashBuilder {
     url: Url,
     secret: Option<String>,
     timeout: Option<Duration>,
+    retry: usize,
+    retry_delay: Duration,
+    proxy: Option<Url>,
+    root_cert: Option<PathBuf>,
+    accept_invalid_certs: bool,
+    tracer: Option<Tracer>,
 }
 
 impl ClashBuilder {
+    /// `url` of controller is normalized with [`parse_controller_url`]
     pub fn new<S: Into<String>>(url: S) -> Result<Self> {
-        let mut url_str = url.into();
-        // Handle trailling slash
-        if !url_str.ends_with('/') {
-            url_str += "/";
-        };
-        let url = Url::parse(&url_str).map_err(|_| Error::url_parse())?;
+        let url = parse_controller_url(&url.into())?;
         Ok(Self {
             url,
             secret: None,
             timeout: None,
+            retry: 0,
+            retry_delay: Clash::DEFAULT_RETRY_DELAY,
+            proxy: None,
+            root_cert: None,
+            accept_invalid_certs: false,
+            tracer: None,
         })
     }
 
@@ -57,11 +80,107 @@ impl ClashBuilder {
         self
     }
 
-    pub fn build(self) -> Clash {
+    /// Retry idempotent `GET` requests up to `retry` times on transient
+    /// errors, see [`ErrorKind::is_transient`]. Default to 0
+    ///
+    /// [`ErrorKind::is_transient`]: crate::ErrorKind::is_transient
+    pub fn retry(mut self, retry: usize) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Delay before the first retry, doubled after each one
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Send requests through HTTP proxy, like `http://127.0.0.1:7890`
+    pub fn proxy(mut self, proxy: Option<Url>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Trust the PEM encoded certificate at `root_cert` besides system ones,
+    /// for servers behind HTTPS with self-signed certificates. Requires
+    /// feature `tls`
+    pub fn root_cert(mut self, root_cert: Option<PathBuf>) -> Self {
+        self.root_cert = root_cert;
+        self
+    }
+
+    /// Accept any certificate of the server, including expired and
+    /// self-signed ones. Dangerous, use [`ClashBuilder::root_cert`] if
+    /// possible. Requires feature `tls`
+    pub fn accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Write method, url, status and time taken of each request to `tracer`
+    pub fn trace(mut self, tracer: Option<Tracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Build the client, fails if the proxy is not an HTTP one or the
+    /// certificates can't be loaded
+    pub fn build(self) -> Result<Clash> {
+        let agent = self.agent()?.build();
+        let stream_agent = self
+            .agent()?
+            .timeout_read(Clash::STREAM_READ_TIMEOUT)
+            .build();
+
         let mut clash = Clash::new(self.url);
         clash.secret = self.secret;
         clash.timeout = self.timeout;
-        clash
+        clash.retry = self.retry;
+        clash.retry_delay = self.retry_delay;
+        clash.tracer = self.tracer;
+        clash.agent = agent;
+        clash.stream_agent = stream_agent;
+        Ok(clash)
+    }
+
+    fn agent(&self) -> Result<AgentBuilder> {
+        let mut agent = AgentBuilder::new();
+        if let Some(ref proxy) = self.proxy {
+            if proxy.scheme() != "http" {
+                return Err(Error::other(format!("Unsupported proxy {}", proxy)));
+            }
+            let proxy = ureq::Proxy::new(proxy.as_str())
+                .map_err(|e| Error::other(format!("Bad proxy {} ({})", proxy, e)))?;
+            agent = agent.proxy(proxy);
+        }
+        self.tls(agent)
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls(&self, agent: AgentBuilder) -> Result<AgentBuilder> {
+        use native_tls::{Certificate, TlsConnector};
+
+        let mut connector = TlsConnector::builder();
+        if let Some(ref path) = self.root_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| Error::tls(format!("Cannot read {} ({})", path.display(), e)))?;
+            let cert = Certificate::from_pem(&pem)
+                .map_err(|e| Error::tls(format!("Bad certificate {} ({})", path.display(), e)))?;
+            connector.add_root_certificate(cert);
+        }
+        let connector = connector
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .build()
+            .map_err(|e| Error::tls(e.to_string()))?;
+        Ok(agent.tls_connector(Arc::new(connector)))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn tls(&self, agent: AgentBuilder) -> Result<AgentBuilder> {
+        if self.root_cert.is_some() || self.accept_invalid_certs {
+            return Err(Error::tls("Built without feature `tls`".to_owned()));
+        }
+        Ok(agent)
     }
 }
 
@@ -75,10 +194,23 @@ pub struct Clash {
     url: Url,
     secret: Option<String>,
     timeout: Option<Duration>,
+    retry: usize,
+    retry_delay: Duration,
     agent: Agent,
+    // Agent of streams, reads time out so they can be stopped, see
+    // [`Clash::stop_on`]
+    stream_agent: Agent,
+    tracer: Option<Tracer>,
+    // Streams end once it's set, see [`Clash::stop_on`]
+    stop: Option<Arc<AtomicBool>>,
 }
 
 impl Clash {
+    pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+    /// How long a stream waits for data before checking if it's stopped
+    pub const STREAM_READ_TIMEOUT: Duration = Duration::from_millis(300);
+
     pub fn builder<S: Into<String>>(url: S) -> Result<ClashBuilder> {
         ClashBuilder::new(url)
     }
@@ -89,13 +221,42 @@ impl Clash {
             url,
             secret: None,
             timeout: None,
+            retry: 0,
+            retry_delay: Self::DEFAULT_RETRY_DELAY,
             agent: Agent::new(),
+            stream_agent: AgentBuilder::new()
+                .timeout_read(Self::STREAM_READ_TIMEOUT)
+                .build(),
+            tracer: None,
+            stop: None,
         }
     }
 
+    /// Same client with requests timing out after `timeout` instead, like
+    /// for delay tests that take longer than usual
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Same client with streams ending once `stop` is set, within
+    /// [`Clash::STREAM_READ_TIMEOUT`] even if server sends nothing. Otherwise
+    /// streams wait for data as long as the connection is open
+    pub fn stop_on(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    fn is_stopped(&self) -> bool {
+        is_set(&self.stop)
+    }
+
     fn build_request(&self, endpoint: &str, method: &str) -> Result<Request> {
         let url = self.url.join(endpoint).map_err(|_| Error::url_parse())?;
-        let mut req = self.agent.request_url(method, &url);
+        let mut req = self
+            .agent
+            .request_url(method, &url)
+            .set("Accept-Encoding", ACCEPT_ENCODING);
 
         if let Some(timeout) = self.timeout {
             req = req.timeout(timeout)
@@ -110,7 +271,10 @@ impl Clash {
 
     fn build_request_without_timeout(&self, endpoint: &str, method: &str) -> Result<Request> {
         let url = self.url.join(endpoint).map_err(|_| Error::url_parse())?;
-        let mut req = self.agent.request_url(method, &url);
+        let mut req = self
+            .stream_agent
+            .request_url(method, &url)
+            .set("Accept-Encoding", ACCEPT_ENCODING);
 
         if let Some(ref secret) = self.secret {
             req = req.set("Authorization", &format!("Bearer {}", secret))
@@ -119,6 +283,41 @@ impl Clash {
         Ok(req)
     }
 
+    /// Send `req` with `body` if any, traced if there's a [`Tracer`]
+    fn send(&self, req: Request, body: Option<&str>) -> Result<Response> {
+        // ureq's error is boxed to keep the closure's result small
+        let call = |req: Request| {
+            match body {
+                Some(body) => req.send_string(body),
+                None => req.call(),
+            }
+            .map_err(Box::new)
+        };
+        let tracer = match self.tracer {
+            Some(ref tracer) => tracer,
+            None => return call(req).map_err(|e| Error::from(*e)),
+        };
+        let (method, url) = (req.method().to_owned(), req.url().to_owned());
+        let start = Instant::now();
+        let res = call(req);
+        let outcome = match res {
+            Ok(ref resp) => resp.status().to_string(),
+            Err(ref e) => match **e {
+                ureq::Error::Status(status, _) => status.to_string(),
+                ureq::Error::Transport(ref e) => format!("failed ({})", e.kind()),
+            },
+        };
+        tracer.record(&method, &url, &outcome, start.elapsed());
+        res.map_err(|e| Error::from(*e))
+    }
+
+    /// Body of `resp`, decoded if compressed by server or a reverse proxy in
+    /// front of it
+    fn body(resp: Response) -> Box<dyn Read + Send> {
+        let encoding = resp.header("Content-Encoding").map(str::to_ascii_lowercase);
+        decode_body(encoding.as_deref(), resp.into_reader())
+    }
+
     /// Send a oneshot request to the specific endpoint with method, with body
     pub fn oneshot_req_with_body(
         &self,
@@ -127,18 +326,15 @@ impl Clash {
         body: Option<String>,
     ) -> Result<String> {
         trace!("Body: {:#?}", body);
-        let resp = if let Some(body) = body {
-            self.build_request(endpoint, method)?.send_string(&body)?
-        } else {
-            self.build_request(endpoint, method)?.call()?
-        };
+        let resp = self.send(self.build_request(endpoint, method)?, body.as_deref())?;
 
         if resp.status() >= 400 {
             return Err(Error::failed_response(resp.status()));
         }
 
-        let text = resp
-            .into_string()
+        let mut text = String::new();
+        Self::body(resp)
+            .read_to_string(&mut text)
             .map_err(|_| Error::bad_response_encoding())?;
         trace!("Received response: {}", text);
 
@@ -162,7 +358,7 @@ impl Clash {
     /// ```rust
     /// # use clashctl_core::{ Clash, model::Traffic }; use std::env;
     /// # fn main() {
-    /// # let clash = Clash::builder(env::var("PROXY_ADDR").unwrap()).unwrap().build();
+    /// # let clash = Clash::builder(env::var("PROXY_ADDR").unwrap()).unwrap().build().unwrap();
     /// let traffics = clash
     ///     .longhaul_req::<Traffic>("traffic", "GET")
     ///     .expect("connect failed");
@@ -178,20 +374,54 @@ impl Clash {
         endpoint: &str,
         method: &str,
     ) -> Result<LongHaul<T>> {
-        let resp = self
-            .build_request_without_timeout(endpoint, method)?
-            .call()?;
+        let resp = loop {
+            match self.send(self.build_request_without_timeout(endpoint, method)?, None) {
+                // Some streams, like idle logs, send no headers until the
+                // first item. Reading them times out as well
+                Err(e) if matches!(e.kind(), crate::ErrorKind::Timeout) && !self.is_stopped() => {
+                    trace!("No response from {} yet, request again", endpoint);
+                }
+                res => break res?,
+            }
+        };
 
         if resp.status() >= 400 {
             return Err(Error::failed_response(resp.status()));
         }
 
-        Ok(LongHaul::new(Box::new(resp.into_reader())))
+        let mut stream = LongHaul::new(Self::body(resp)).endpoint(endpoint);
+        stream.stop = self.stop.clone();
+        Ok(stream)
     }
 
-    /// Helper function for method `GET`
+    /// Helper function for method `GET`. Transient failures are retried with
+    /// exponential backoff, see [`ClashBuilder::retry`]
     pub fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
-        self.oneshot_req(endpoint, "GET").and_then(Convert::convert)
+        let mut delay = self.retry_delay;
+        let mut retried = 0;
+        loop {
+            match self.oneshot_req(endpoint, "GET") {
+                Err(e) if retried < self.retry && e.kind().is_transient() => {
+                    retried += 1;
+                    warn!(
+                        "GET {} failed ({}), retry {}/{} in {:?}",
+                        endpoint, e, retried, self.retry, delay
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                res => return res.and_then(|x| x.convert(endpoint)),
+            }
+        }
+    }
+
+    /// Like [`get`], but never retried. For requests that are expensive or
+    /// time sensitive, like delay tests
+    ///
+    /// [`get`]: Clash::get
+    fn get_once<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        self.oneshot_req(endpoint, "GET")
+            .and_then(|x| x.convert(endpoint))
     }
 
     /// Helper function for method `DELETE`
@@ -202,7 +432,7 @@ impl Clash {
     /// Helper function for method `PUT`
     pub fn put<T: DeserializeOwned>(&self, endpoint: &str, body: Option<String>) -> Result<T> {
         self.oneshot_req_with_body(endpoint, "PUT", body)
-            .and_then(Convert::convert)
+            .and_then(|x| x.convert(endpoint))
     }
 
     /// Get clash version
@@ -210,6 +440,16 @@ impl Clash {
         self.get("version")
     }
 
+    /// Like [`Clash::get_version`], but `None` if the server is too old to
+    /// have the endpoint, which should be treated as having baseline
+    /// capabilities only
+    pub fn try_get_version(&self) -> Result<Option<Version>> {
+        match self.get_version() {
+            Err(e) if matches!(e.kind(), crate::ErrorKind::FailedResponse(404)) => Ok(None),
+            res => res.map(Some),
+        }
+    }
+
     /// Get base configs
     pub fn get_configs(&self) -> Result<Config> {
         self.get("configs")
@@ -221,10 +461,19 @@ impl Clash {
     /// - `path`: the absolute path to config file
     ///
     /// This will **NOT** affect `external-controller` & `secret`
+    /// An empty `path` reloads the config file Clash was started with
     pub fn reload_configs(&self, force: bool, path: &str) -> Result<()> {
         let body = json!({ "path": path }).to_string();
         debug!("{}", body);
-        self.put::<String>(if force { "configs?force" } else { "configs" }, Some(body))
+        let endpoint = if force { "configs?force=true" } else { "configs" };
+        self.oneshot_req_with_body(endpoint, "PUT", Some(body))
+            .map(|_| ())
+    }
+
+    /// Switch proxy mode, other configs are left untouched
+    pub fn set_mode(&self, mode: Mode) -> Result<()> {
+        let body = json!({ "mode": mode }).to_string();
+        self.oneshot_req_with_body("configs", "PATCH", Some(body))
             .map(|_| ())
     }
 
@@ -282,26 +531,135 @@ impl Clash {
         self.longhaul_req("logs", "GET")
     }
 
+    /// Get real-time logs with level at least `level`
+    ///
+    /// See [`get_log`] for more information
+    ///
+    /// [`get_log`]: Clash::get_log
+    pub fn get_log_with_level(&self, level: &Level) -> Result<LongHaul<Log>> {
+        self.longhaul_req(&format!("logs?level={}", level.as_query()), "GET")
+    }
+
     /// Get specific proxy delay test information
     pub fn get_proxy_delay(&self, proxy: &str, test_url: &str, timeout: u64) -> Result<Delay> {
         use urlencoding::encode as e;
         let (proxy, test_url) = (e(proxy), e(test_url));
-        self.get(&format!(
+        self.get_once(&format!(
             "proxies/{}/delay?url={}&timeout={}",
             proxy, test_url, timeout
         ))
     }
 
+    /// Test delay of all proxies in a group at once (Clash Premium only)
+    ///
+    /// Returns delays keyed by proxy name. Proxies failed the test are absent
+    /// in the map.
+    pub fn get_group_delay(
+        &self,
+        group: &str,
+        test_url: &str,
+        timeout: u64,
+    ) -> Result<HashMap<String, u64>> {
+        use urlencoding::encode as e;
+        let (group, test_url) = (e(group), e(test_url));
+        self.get_once(&format!(
+            "group/{}/delay?url={}&timeout={}",
+            group, test_url, timeout
+        ))
+    }
+
+    /// Get proxy providers information
+    pub fn get_providers(&self) -> Result<Providers> {
+        self.get("providers/proxies")
+    }
+
+    /// Get specific proxy provider information
+    pub fn get_provider(&self, provider: &str) -> Result<Provider> {
+        self.get(&format!("providers/proxies/{}", urlencoding::encode(provider)))
+    }
+
+    /// Pull proxies of specific provider from its source again
+    pub fn update_provider(&self, provider: &str) -> Result<()> {
+        let provider = urlencoding::encode(provider);
+        self.oneshot_req(&format!("providers/proxies/{}", provider), "PUT")
+            .map(|_| ())
+    }
+
+    /// Trigger health check of all proxies in specific provider
+    pub fn healthcheck_provider(&self, provider: &str) -> Result<()> {
+        let provider = urlencoding::encode(provider);
+        self.oneshot_req(&format!("providers/proxies/{}/healthcheck", provider), "GET")
+            .map(|_| ())
+    }
+
     /// Select specific proxy
     pub fn set_proxygroup_selected(&self, group: &str, proxy: &str) -> Result<()> {
-        let body = format!("{{\"name\":\"{}\"}}", proxy);
+        let body = json!({ "name": proxy }).to_string();
+        let group = urlencoding::encode(group);
         self.oneshot_req_with_body(&format!("proxies/{}", group), "PUT", Some(body))?;
         Ok(())
     }
 }
 
+/// Url of controller from what users typed, like `127.0.0.1:9090`,
+/// `[::1]:9090` or `https://clash.lan/api`. Scheme defaults to `http://` if
+/// missing and path ends with `/` so endpoints are joined under it. Anything
+/// other than http(s) with a host is an
+/// [`ErrorKind::UrlParseError`](crate::ErrorKind::UrlParseError)
+pub fn parse_controller_url(url: &str) -> Result<Url> {
+    let url = url.trim();
+    // `localhost:9090` parses as scheme `localhost`, so look for `://` instead
+    let mut url = if url.contains("://") {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("http://{}", url))
+    }
+    .map_err(|_| Error::url_parse())?;
+
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(Error::url_parse());
+    }
+    // Handle trailling slash
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url.join("version").map_err(|_| Error::url_parse())?;
+    Ok(url)
+}
+
+fn is_set(flag: &Option<Arc<AtomicBool>>) -> bool {
+    flag.as_ref().is_some_and(|flag| flag.load(Ordering::Acquire))
+}
+
+/// Url for testing delay of `group`, which is the one in `overrides` (keyed by
+/// group name) if any, or `default_url`. Invalid url is an
+/// [`ErrorKind::UrlParseError`](crate::ErrorKind::UrlParseError)
+pub fn resolve_test_url<'a>(
+    group: &str,
+    default_url: &'a str,
+    overrides: &'a HashMap<String, String>,
+) -> Result<&'a str> {
+    let url = overrides.get(group).map_or(default_url, String::as_str);
+    Url::parse(url).map_err(|_| Error::url_parse())?;
+    Ok(url)
+}
+
+/// Reader of `body` decoded by lowercased `Content-Encoding`. `deflate` in
+/// HTTP is zlib wrapped. Others are read as is, since only those in
+/// [`ACCEPT_ENCODING`] are asked for
+fn decode_body(encoding: Option<&str>, body: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+    match encoding {
+        Some("gzip" | "x-gzip") => Box::new(GzDecoder::new(body)),
+        Some("deflate") => Box::new(ZlibDecoder::new(body)),
+        _ => body,
+    }
+}
+
 pub struct LongHaul<T: DeserializeOwned> {
     reader: BufReader<Box<dyn Read + Send>>,
+    endpoint: String,
+    stop: Option<Arc<AtomicBool>>,
     ty: PhantomData<T>,
 }
 
@@ -309,20 +667,36 @@ impl<T: DeserializeOwned> LongHaul<T> {
     pub fn new(reader: Box<dyn Read + Send>) -> Self {
         Self {
             reader: BufReader::new(reader),
+            endpoint: String::new(),
+            stop: None,
             ty: PhantomData,
         }
     }
 
+    /// Endpoint the stream comes from, shown in errors of broken items
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_owned();
+        self
+    }
+
     pub fn next_item(&mut self) -> Option<Result<T>> {
-        Some(self.next_raw()?.and_then(Convert::convert))
+        Some(self.next_raw()?.and_then(|x| x.convert(&self.endpoint)))
     }
 
     pub fn next_raw(&mut self) -> Option<Result<String>> {
         let mut buf = String::with_capacity(30);
-        match self.reader.read_line(&mut buf) {
-            Ok(0) => None,
-            Ok(_) => Some(Ok(buf)),
-            Err(e) => Some(Err(Error::other(format!("{:}", e)))),
+        loop {
+            match self.reader.read_line(&mut buf) {
+                Ok(0) if buf.is_empty() => return None,
+                Ok(_) => return Some(Ok(buf)),
+                // Read timed out, what's read so far stays in `buf`
+                Err(e) if matches!(e.kind(), IoErrorKind::TimedOut | IoErrorKind::WouldBlock) => {
+                    if is_set(&self.stop) {
+                        return None;
+                    }
+                }
+                Err(e) => return Some(Err(Error::other(format!("{:}", e)))),
+            }
         }
     }
 }
@@ -334,3 +708,193 @@ impl<T: DeserializeOwned> Iterator for LongHaul<T> {
         self.next_item()
     }
 }
+
+#[test]
+fn test_resolve_test_url() {
+    let default = "http://www.gstatic.com/generate_204";
+    let overrides = HashMap::from([
+        ("Direct".to_owned(), "http://connect.rom.miui.com/generate_204".to_owned()),
+        ("Broken".to_owned(), "not a url".to_owned()),
+    ]);
+    assert_eq!(resolve_test_url("Proxy", default, &overrides).unwrap(), default);
+    assert_eq!(
+        resolve_test_url("Direct", default, &overrides).unwrap(),
+        "http://connect.rom.miui.com/generate_204"
+    );
+    assert!(matches!(
+        resolve_test_url("Broken", default, &overrides)
+            .unwrap_err()
+            .kind(),
+        crate::ErrorKind::UrlParseError
+    ));
+}
+
+#[test]
+fn test_decode_body() {
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    let gzipped = include_bytes!("test/fixtures/proxies.json.gz");
+    let read = |encoding, body: Vec<u8>| {
+        let mut text = String::new();
+        decode_body(encoding, Box::new(std::io::Cursor::new(body)))
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+
+    let text = read(Some("gzip"), gzipped.to_vec());
+    let proxies: Proxies = text.clone().convert("proxies").unwrap();
+    assert_eq!(proxies.len(), 4);
+    assert_eq!(proxies["Proxy"].now.as_deref(), Some("Tokyo"));
+    assert_eq!(proxies["Tokyo"].history[0].delay, 87);
+
+    let mut deflated = ZlibEncoder::new(Vec::new(), Compression::default());
+    deflated.write_all(text.as_bytes()).unwrap();
+    assert_eq!(read(Some("deflate"), deflated.finish().unwrap()), text);
+    assert_eq!(read(None, text.clone().into_bytes()), text);
+}
+
+#[test]
+fn test_bad_response_format() {
+    let err = r#"{"up": "1", "down": 2}"#
+        .to_owned()
+        .convert("traffic")
+        .map(|x: Traffic| x)
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        crate::ErrorKind::BadResponseFormat { endpoint, detail }
+            if endpoint == "traffic" && detail.contains("invalid type")
+    ));
+    assert!(err.to_string().contains("`traffic`"));
+
+    let mut stream = LongHaul::<Traffic>::new(Box::new(&b"{}\n"[..])).endpoint("traffic");
+    assert!(matches!(
+        stream.next().unwrap().unwrap_err().kind(),
+        crate::ErrorKind::BadResponseFormat { endpoint, .. } if endpoint == "traffic"
+    ));
+}
+
+#[test]
+fn test_retry() {
+    use std::{io::Write, net::TcpListener};
+
+    // Serve 503 for the first `fails` requests, then 200
+    let serve = |fails: usize| {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf);
+                let resp = if i < fails {
+                    "HTTP/1.1 503 Service Unavailable\r\n\
+                     Content-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}"
+                };
+                stream.write_all(resp.as_bytes()).unwrap();
+            }
+        });
+        url
+    };
+    let clash = |url, retry| {
+        Clash::builder(url)
+            .unwrap()
+            .retry(retry)
+            .retry_delay(Duration::from_millis(1))
+            .build()
+            .unwrap()
+    };
+
+    assert!(clash(serve(2), 2).get::<HashMap<String, u64>>("x").is_ok());
+    assert!(matches!(
+        clash(serve(2), 1).get::<HashMap<String, u64>>("x").unwrap_err().kind(),
+        crate::ErrorKind::FailedResponse(503)
+    ));
+    // Selecting proxy is not idempotent and never retried
+    assert!(clash(serve(1), 2).set_proxygroup_selected("a", "b").is_err());
+}
+
+#[test]
+fn test_stream_stop() {
+    use std::{io::Write, net::TcpListener};
+
+    // Leave the first request unanswered like an idle log stream. Answer the
+    // next one with a line, and another after longer than a read timeout
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut idle = listener.accept().unwrap().0;
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1024];
+        let _ = idle.read(&mut buf);
+        let _ = stream.read(&mut buf);
+        let chunk = |line: &str| format!("{:x}\r\n{}\r\n", line.len(), line);
+        let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        write!(stream, "{}{}", head, chunk("1\n")).unwrap();
+        thread::sleep(Clash::STREAM_READ_TIMEOUT * 2);
+        write!(stream, "{}", chunk("2\n")).unwrap();
+        thread::sleep(Duration::from_secs(10));
+    });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut stream = Clash::builder(url)
+        .unwrap()
+        .build()
+        .unwrap()
+        .stop_on(stop.clone())
+        .longhaul_req::<u64>("x", "GET")
+        .unwrap();
+    assert_eq!(stream.next().unwrap().unwrap(), 1);
+    assert_eq!(stream.next().unwrap().unwrap(), 2);
+
+    stop.store(true, Ordering::Release);
+    let start = Instant::now();
+    assert!(stream.next().is_none());
+    assert!(start.elapsed() < Clash::STREAM_READ_TIMEOUT * 2);
+}
+
+#[test]
+fn test_build_proxy() {
+    let builder = Clash::builder("http://127.0.0.1:9090").unwrap();
+    let proxy = |url| Some(Url::parse(url).unwrap());
+
+    assert!(builder.clone().proxy(proxy("http://127.0.0.1:7890")).build().is_ok());
+    // ureq is built without SOCKS support
+    assert!(builder.clone().proxy(proxy("socks5://127.0.0.1:7891")).build().is_err());
+    assert!(builder.proxy(proxy("ftp://127.0.0.1:21")).build().is_err());
+}
+
+#[test]
+fn test_parse_controller_url() {
+    let parse = |url| parse_controller_url(url).map(String::from);
+
+    assert_eq!(parse("127.0.0.1:9090").unwrap(), "http://127.0.0.1:9090/");
+    assert_eq!(parse("localhost:9090").unwrap(), "http://localhost:9090/");
+    assert_eq!(parse(" [::1]:9090 ").unwrap(), "http://[::1]:9090/");
+    assert_eq!(
+        parse("https://clash.lan/api").unwrap(),
+        "https://clash.lan/api/"
+    );
+    assert_eq!(
+        parse("http://[fe80::1]:9090/").unwrap(),
+        "http://[fe80::1]:9090/"
+    );
+    assert_eq!(
+        parse_controller_url("[::1]:9090")
+            .unwrap()
+            .join("proxies")
+            .unwrap()
+            .as_str(),
+        "http://[::1]:9090/proxies"
+    );
+    for url in ["", "ftp://127.0.0.1:21", "::1:9090", "http://[::1"] {
+        assert!(matches!(
+            parse_controller_url(url).unwrap_err().kind(),
+            crate::ErrorKind::UrlParseError
+        ));
+    }
+}
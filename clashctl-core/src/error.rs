@@ -4,13 +4,28 @@ pub enum ErrorKind {
     UrlParseError,
 
     #[error("Error while requesting API ({0})")]
-    RequestError(#[from] ureq::Error),
+    RequestError(ureq::Error),
+
+    #[error("Unauthorized, please check the secret of the server")]
+    Unauthorized,
+
+    #[error("Request timed out, server may be busy")]
+    Timeout,
+
+    #[error("Unable to connect to server, is Clash running?")]
+    ConnectionRefused,
+
+    #[error("Unable to resolve server address")]
+    DnsFailure,
+
+    #[error("TLS error ({0}), check certificate of the server")]
+    Tls(String),
 
     #[error("Broken response from server")]
     BadResponseEncoding,
 
-    #[error("Broken response from server ({0})")]
-    BadResponseFormat(#[from] serde_json::Error),
+    #[error("Broken response from server for `{endpoint}` ({detail})")]
+    BadResponseFormat { endpoint: String, detail: String },
 
     #[error("Failed response from server (Code {0})")]
     FailedResponse(u16),
@@ -19,23 +34,81 @@ pub enum ErrorKind {
     Other(String),
 }
 
+impl ErrorKind {
+    /// Whether the error may go away by simply retrying the same request
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ErrorKind::Timeout | ErrorKind::ConnectionRefused | ErrorKind::RequestError(_) => true,
+            ErrorKind::FailedResponse(code) => *code >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<ureq::Error> for ErrorKind {
+    fn from(err: ureq::Error) -> Self {
+        match err {
+            ureq::Error::Status(401, _) => ErrorKind::Unauthorized,
+            ureq::Error::Status(code, _) => ErrorKind::FailedResponse(code),
+            ureq::Error::Transport(ref transport) => match transport.kind() {
+                ureq::ErrorKind::Dns => ErrorKind::DnsFailure,
+                _ if is_timeout(transport) => ErrorKind::Timeout,
+                #[cfg(feature = "tls")]
+                _ if is_tls_error(transport) => ErrorKind::Tls(transport.to_string()),
+                ureq::ErrorKind::ConnectionFailed => ErrorKind::ConnectionRefused,
+                _ => ErrorKind::RequestError(err),
+            },
+        }
+    }
+}
+
+fn is_timeout(transport: &ureq::Transport) -> bool {
+    std::error::Error::source(transport)
+        .and_then(|err| err.downcast_ref::<std::io::Error>())
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::TimedOut)
+}
+
+#[cfg(feature = "tls")]
+fn is_tls_error(transport: &ureq::Transport) -> bool {
+    std::error::Error::source(transport).is_some_and(|err| err.is::<native_tls::Error>())
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 pub struct Error(Box<ErrorKind>);
 
 impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0
+    }
+
     pub fn url_parse() -> Self {
         Error(Box::new(ErrorKind::UrlParseError))
     }
 
     pub fn failed_response(status: u16) -> Self {
-        Error(Box::new(ErrorKind::FailedResponse(status)))
+        match status {
+            401 => Error(Box::new(ErrorKind::Unauthorized)),
+            status => Error(Box::new(ErrorKind::FailedResponse(status))),
+        }
     }
 
     pub fn bad_response_encoding() -> Self {
         Error(Box::new(ErrorKind::BadResponseEncoding))
     }
 
+    /// Response of `endpoint` doesn't match the model, `detail` tells where
+    pub fn bad_response_format(endpoint: &str, detail: impl ToString) -> Self {
+        Error(Box::new(ErrorKind::BadResponseFormat {
+            endpoint: endpoint.to_owned(),
+            detail: detail.to_string(),
+        }))
+    }
+
+    pub fn tls(msg: String) -> Self {
+        Error(Box::new(ErrorKind::Tls(msg)))
+    }
+
     pub fn other(msg: String) -> Self {
         Error(Box::new(ErrorKind::Other(msg)))
     }
@@ -51,3 +124,22 @@ where
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[test]
+fn test_status_error_kind() {
+    let status = |code| ureq::Error::Status(code, ureq::Response::new(code, "", "").unwrap());
+
+    assert!(matches!(ErrorKind::from(status(401)), ErrorKind::Unauthorized));
+    assert!(matches!(
+        ErrorKind::from(status(404)),
+        ErrorKind::FailedResponse(404)
+    ));
+}
+
+#[test]
+fn test_connection_refused() {
+    // Nothing should be listening on port 1
+    let err = ureq::get("http://127.0.0.1:1").call().unwrap_err();
+
+    assert!(matches!(ErrorKind::from(err), ErrorKind::ConnectionRefused));
+}
@@ -15,6 +15,28 @@ pub enum Level {
     Debug,
 }
 
+impl Level {
+    /// Severity of level, where `Error` > `Warning` > `Info` > `Debug`
+    pub fn rank(&self) -> u8 {
+        match self {
+            Level::Error => 3,
+            Level::Warning => 2,
+            Level::Info => 1,
+            Level::Debug => 0,
+        }
+    }
+
+    /// Value used as `level` query of `/logs`
+    pub fn as_query(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
 // TODO Parse log
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Log {
@@ -22,3 +44,10 @@ pub struct Log {
     pub log_type: Level,
     pub payload: String,
 }
+
+#[test]
+fn test_level_rank() {
+    assert!(Level::Error.rank() > Level::Warning.rank());
+    assert!(Level::Warning.rank() > Level::Info.rank());
+    assert!(Level::Info.rank() > Level::Debug.rank());
+}
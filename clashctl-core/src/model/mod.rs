@@ -1,4 +1,4 @@
-mod_use::mod_use![config, connection, proxy, rule, traffic];
+mod_use::mod_use![config, connection, provider, proxy, rule, traffic];
 
 mod log;
 use cfg_if::cfg_if;
@@ -29,9 +29,30 @@ pub struct Delay {
 pub struct Version {
     // Clash Premium only
     pub premium: Option<bool>,
+    // Clash Meta only
+    pub meta: Option<bool>,
     pub version: VersionPayload,
 }
 
+impl Version {
+    /// `Premium` or `Meta`, `None` for the open source core
+    pub fn edition(&self) -> Option<&'static str> {
+        if self.premium == Some(true) {
+            Some("Premium")
+        } else if self.meta == Some(true) {
+            Some("Meta")
+        } else {
+            None
+        }
+    }
+
+    /// Whether delay of a whole group can be tested at once, see
+    /// [`Clash::get_group_delay`](crate::Clash::get_group_delay)
+    pub fn has_group_delay(&self) -> bool {
+        self.edition().is_some()
+    }
+}
+
 cfg_if! {
     if #[cfg(feature = "deserialize")] {
         use chrono::{Utc, DateTime};
@@ -58,3 +79,21 @@ impl ToString for VersionPayload {
         }
     }
 }
+
+#[test]
+fn test_version() {
+    let version = |json| serde_json::from_str::<Version>(json).unwrap();
+
+    let open = version(r#"{"version":"1.11.0"}"#);
+    assert_eq!(open.edition(), None);
+    assert!(!open.has_group_delay());
+    assert_eq!(open.version.to_string(), "1.11.0");
+
+    let premium = version(r#"{"premium":true,"version":"2022.08.26"}"#);
+    assert_eq!(premium.edition(), Some("Premium"));
+    assert!(premium.has_group_delay());
+
+    let meta = version(r#"{"meta":true,"version":"v1.14.1"}"#);
+    assert_eq!(meta.edition(), Some("Meta"));
+    assert_eq!(meta.version.to_string(), "v1.14.1");
+}
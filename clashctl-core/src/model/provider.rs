@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Proxy, TimeType};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Providers {
+    pub providers: HashMap<String, Provider>,
+}
+
+impl Deref for Providers {
+    type Target = HashMap<String, Provider>;
+    fn deref(&self) -> &Self::Target {
+        &self.providers
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Provider {
+    pub name: String,
+    pub vehicle_type: VehicleType,
+    #[serde(default)]
+    pub proxies: Vec<ProviderProxy>,
+    pub updated_at: Option<TimeType>,
+}
+
+impl Provider {
+    /// Whether the provider is loaded from outside (file or http) thus can be
+    /// updated. `Compatible` ones are created by clash for proxies listed in
+    /// groups directly
+    pub fn is_updatable(&self) -> bool {
+        matches!(self.vehicle_type, VehicleType::HTTP | VehicleType::File)
+    }
+
+    pub fn contains(&self, proxy: &str) -> bool {
+        self.proxies.iter().any(|x| x.name == proxy)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProviderProxy {
+    pub name: String,
+    #[serde(flatten)]
+    pub proxy: Proxy,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(
+    feature = "enum_ext",
+    derive(strum::EnumString, strum::Display, strum::EnumVariantNames),
+    strum(ascii_case_insensitive)
+)]
+pub enum VehicleType {
+    HTTP,
+    File,
+    Compatible,
+    #[serde(other)]
+    Unknown,
+}
+
+#[test]
+fn test_provider_deserialize() {
+    let raw = r#"{
+        "providers": {
+            "sub": {
+                "name": "sub",
+                "type": "Proxy",
+                "vehicleType": "HTTP",
+                "updatedAt": "2022-09-01T12:00:00.000000000+08:00",
+                "proxies": [
+                    { "name": "HK 01", "type": "Shadowsocks", "history": [], "udp": true }
+                ]
+            },
+            "default": {
+                "name": "default",
+                "type": "Proxy",
+                "vehicleType": "Compatible",
+                "proxies": []
+            }
+        }
+    }"#;
+    let providers = serde_json::from_str::<Providers>(raw).unwrap();
+    assert!(providers["sub"].is_updatable());
+    assert!(providers["sub"].contains("HK 01"));
+    assert!(!providers["default"].is_updatable());
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::ops::Deref;
 
 use serde::{Deserialize, Serialize};
@@ -35,7 +36,7 @@ impl Deref for Proxies {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Proxy {
     #[serde(rename = "type")]
     pub proxy_type: ProxyType,
@@ -47,16 +48,16 @@ pub struct Proxy {
     pub now: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct History {
     pub time: TimeType,
     pub delay: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy)]
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Hash)]
 #[cfg_attr(
     feature = "enum_ext",
-    derive(strum::EnumString, strum::Display, strum::EnumVariantNames),
+    derive(strum::EnumString, strum::EnumVariantNames),
     strum(ascii_case_insensitive)
 )]
 pub enum ProxyType {
@@ -78,12 +79,72 @@ pub enum ProxyType {
     Socks5,
     // Relay
     Relay,
-    // Unknown
-    #[serde(other)]
-    Unknown,
+    // Types unknown to us, with name reported by Clash
+    #[cfg_attr(feature = "enum_ext", strum(default))]
+    Unknown(String),
+}
+
+impl Serialize for ProxyType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProxyType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from_name)
+    }
+}
+
+impl Display for ProxyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
 }
 
 impl ProxyType {
+    /// Name of the type as Clash reports it
+    pub fn name(&self) -> &str {
+        match self {
+            ProxyType::Direct => "Direct",
+            ProxyType::Reject => "Reject",
+            ProxyType::Selector => "Selector",
+            ProxyType::URLTest => "URLTest",
+            ProxyType::Fallback => "Fallback",
+            ProxyType::LoadBalance => "LoadBalance",
+            ProxyType::Shadowsocks => "Shadowsocks",
+            ProxyType::Vmess => "Vmess",
+            ProxyType::ShadowsocksR => "ShadowsocksR",
+            ProxyType::Http => "Http",
+            ProxyType::Snell => "Snell",
+            ProxyType::Trojan => "Trojan",
+            ProxyType::Socks5 => "Socks5",
+            ProxyType::Relay => "Relay",
+            ProxyType::Unknown(name) => name,
+        }
+    }
+
+    /// Parse `name` reported by Clash, or spelled as in Clash config files
+    pub fn from_name(name: String) -> Self {
+        match name.as_str() {
+            "Direct" => ProxyType::Direct,
+            "Reject" => ProxyType::Reject,
+            "Selector" | "select" => ProxyType::Selector,
+            "URLTest" | "url-test" => ProxyType::URLTest,
+            "Fallback" | "fallback" => ProxyType::Fallback,
+            "LoadBalance" | "load-balance" => ProxyType::LoadBalance,
+            "Shadowsocks" | "ss" => ProxyType::Shadowsocks,
+            "Vmess" | "vmess" => ProxyType::Vmess,
+            "ShadowsocksR" | "ssr" => ProxyType::ShadowsocksR,
+            "Http" | "http" => ProxyType::Http,
+            "Snell" | "snell" => ProxyType::Snell,
+            "Trojan" | "trojan" => ProxyType::Trojan,
+            "Socks5" | "socks5" => ProxyType::Socks5,
+            "Relay" | "relay" => ProxyType::Relay,
+            _ => ProxyType::Unknown(name),
+        }
+    }
+
     pub fn is_selector(&self) -> bool {
         matches!(self, ProxyType::Selector)
     }
@@ -103,6 +164,8 @@ impl ProxyType {
         matches!(self, ProxyType::Direct | ProxyType::Reject)
     }
 
+    /// Whether it's a proxy node, whose delay can be tested. Unknown types
+    /// are assumed to be
     pub fn is_normal(&self) -> bool {
         matches!(
             self,
@@ -113,8 +176,30 @@ impl ProxyType {
                 | ProxyType::Snell
                 | ProxyType::Trojan
                 | ProxyType::Socks5
+                | ProxyType::Unknown(_)
         )
     }
+
+    /// Short code of the type, at most 3 chars, for narrow displays
+    pub fn short(&self) -> &'static str {
+        match self {
+            ProxyType::Direct => "DIR",
+            ProxyType::Reject => "REJ",
+            ProxyType::Selector => "SEL",
+            ProxyType::URLTest => "URL",
+            ProxyType::Fallback => "FB",
+            ProxyType::LoadBalance => "LB",
+            ProxyType::Shadowsocks => "SS",
+            ProxyType::Vmess => "VM",
+            ProxyType::ShadowsocksR => "SSR",
+            ProxyType::Http => "HT",
+            ProxyType::Snell => "SN",
+            ProxyType::Trojan => "TR",
+            ProxyType::Socks5 => "S5",
+            ProxyType::Relay => "RLY",
+            ProxyType::Unknown(_) => "?",
+        }
+    }
 }
 
 #[test]
@@ -181,3 +266,70 @@ fn test_proxies() {
         vec!["test_c"]
     );
 }
+
+/// Known types with their names reported by Clash
+#[cfg(test)]
+const PROXY_TYPE_NAMES: [(ProxyType, &str); 14] = [
+    (ProxyType::Direct, "Direct"),
+    (ProxyType::Reject, "Reject"),
+    (ProxyType::Selector, "Selector"),
+    (ProxyType::URLTest, "URLTest"),
+    (ProxyType::Fallback, "Fallback"),
+    (ProxyType::LoadBalance, "LoadBalance"),
+    (ProxyType::Shadowsocks, "Shadowsocks"),
+    (ProxyType::Vmess, "Vmess"),
+    (ProxyType::ShadowsocksR, "ShadowsocksR"),
+    (ProxyType::Http, "Http"),
+    (ProxyType::Snell, "Snell"),
+    (ProxyType::Trojan, "Trojan"),
+    (ProxyType::Socks5, "Socks5"),
+    (ProxyType::Relay, "Relay"),
+];
+
+#[test]
+fn test_proxy_type_serde() {
+    for (ty, name) in PROXY_TYPE_NAMES {
+        let json = format!("\"{}\"", name);
+        assert_eq!(serde_json::to_string(&ty).unwrap(), json);
+        assert_eq!(serde_json::from_str::<ProxyType>(&json).unwrap(), ty);
+    }
+
+    let parse = |name: &str| serde_json::from_str::<ProxyType>(&format!("\"{}\"", name)).unwrap();
+    assert_eq!(parse("url-test"), ProxyType::URLTest);
+    assert_eq!(parse("load-balance"), ProxyType::LoadBalance);
+    assert_eq!(parse("select"), ProxyType::Selector);
+    assert_eq!(parse("ss"), ProxyType::Shadowsocks);
+    assert_eq!(parse("Urltest"), ProxyType::Unknown("Urltest".into()));
+}
+
+#[test]
+fn test_unknown_proxy_type() {
+    let json = r#"{"proxies":{
+        "a":{"type":"Vless","history":[],"udp":true},
+        "b":{"type":"Direct","history":[]}
+    }}"#;
+    let proxies: Proxies = serde_json::from_str(json).unwrap();
+    let ty = &proxies["a"].proxy_type;
+    assert_eq!(ty, &ProxyType::Unknown("Vless".into()));
+    assert_eq!(ty.to_string(), "Vless");
+    assert!(ty.is_normal());
+    assert_eq!(serde_json::to_string(ty).unwrap(), r#""Vless""#);
+    assert_eq!(proxies.normal().map(|x| x.0).collect::<Vec<_>>(), vec!["a"]);
+}
+
+#[cfg(feature = "enum_ext")]
+#[test]
+fn test_proxy_type_display() {
+    use strum::VariantNames;
+
+    // All but `Unknown`
+    assert_eq!(PROXY_TYPE_NAMES.len(), ProxyType::VARIANTS.len() - 1);
+    for (ty, name) in PROXY_TYPE_NAMES {
+        assert_eq!(ty.to_string(), name);
+        assert_eq!(name.parse::<ProxyType>().unwrap(), ty);
+    }
+    assert_eq!(
+        "Vless".parse::<ProxyType>().unwrap(),
+        ProxyType::Unknown("Vless".into())
+    );
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -40,12 +43,118 @@ pub struct Rule {
     pub proxy: String,
 }
 
+impl Rule {
+    /// Whether the rule matches requests to `host`, a domain or an IP. `None`
+    /// if it can't be told by host alone, like `GEOIP`, or `IPCIDR` for
+    /// domains which Clash may resolve
+    pub fn matches_host(&self, host: &str) -> Option<bool> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let payload = self.payload.to_ascii_lowercase();
+        match self.rule_type {
+            RuleType::Domain => Some(host == payload),
+            RuleType::DomainSuffix => Some(
+                host == payload
+                    || host
+                        .strip_suffix(&payload)
+                        .is_some_and(|x| x.ends_with('.')),
+            ),
+            RuleType::DomainKeyword => Some(host.contains(&payload)),
+            RuleType::IPCIDR => {
+                let ip = host.parse::<IpAddr>().ok()?;
+                cidr_contains(&payload, ip)
+            }
+            RuleType::Match => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `cidr` like `10.0.0.0/8` contains `ip`, `None` if `cidr` is broken
+fn cidr_contains(cidr: &str, ip: IpAddr) -> Option<bool> {
+    let (net, len) = cidr.split_once('/')?;
+    let (net, len) = (net.parse::<IpAddr>().ok()?, len.parse::<u32>().ok()?);
+    match (net, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) if len <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - len).unwrap_or_default();
+            Some(u32::from(net) & mask == u32::from(ip) & mask)
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) if len <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - len).unwrap_or_default();
+            Some(u128::from(net) & mask == u128::from(ip) & mask)
+        }
+        (IpAddr::V4(_), IpAddr::V6(_)) | (IpAddr::V6(_), IpAddr::V4(_)) => Some(false),
+        _ => None,
+    }
+}
+
+/// First rule matching a host, see [`Rules::match_host`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostMatch<'a> {
+    pub rule: Option<&'a Rule>,
+    /// Rules before the matched one that can't be told by host alone
+    pub skipped: usize,
+}
+
+/// Number of rules in total and of each type, see [`Rules::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    pub total: usize,
+    pub by_type: BTreeMap<RuleType, usize>,
+}
+
+impl RuleStats {
+    /// Types with their counts, most common first
+    pub fn most_common(&self) -> Vec<(RuleType, usize)> {
+        let mut counts = self
+            .by_type
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        // Stable, so types of same count stay in order of declaration
+        counts.sort_by_key(|x| std::cmp::Reverse(x.1));
+        counts
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Rules {
     pub rules: Vec<Rule>,
 }
 
 impl Rules {
+    /// Approximate which rule Clash applies to requests to `host`, by
+    /// matching rules in order. Rules can't be told by host alone are skipped
+    pub fn match_host(&self, host: &str) -> HostMatch<'_> {
+        let mut skipped = 0;
+        for rule in self.rules.iter() {
+            match rule.matches_host(host) {
+                Some(true) => {
+                    return HostMatch {
+                        rule: Some(rule),
+                        skipped,
+                    }
+                }
+                Some(false) => {}
+                None => skipped += 1,
+            }
+        }
+        HostMatch {
+            rule: None,
+            skipped,
+        }
+    }
+
+    pub fn stats(&self) -> RuleStats {
+        let mut by_type = BTreeMap::new();
+        for rule in self.rules.iter() {
+            *by_type.entry(rule.rule_type).or_default() += 1;
+        }
+        RuleStats {
+            total: self.rules.len(),
+            by_type,
+        }
+    }
+
     pub fn most_frequent_proxy(&self) -> Option<&str> {
         self.frequency()
             .into_iter()
@@ -73,3 +182,75 @@ impl Rules {
         counts
     }
 }
+
+#[test]
+fn test_match_host() {
+    let rule = |rule_type, payload: &str, proxy: &str| Rule {
+        rule_type,
+        payload: payload.to_owned(),
+        proxy: proxy.to_owned(),
+    };
+    let rules = Rules {
+        rules: vec![
+            rule(RuleType::Domain, "example.com", "A"),
+            rule(RuleType::DomainSuffix, "Google.com", "B"),
+            rule(RuleType::DomainKeyword, "github", "C"),
+            rule(RuleType::GeoIP, "CN", "DIRECT"),
+            rule(RuleType::IPCIDR, "10.0.0.0/8", "D"),
+            rule(RuleType::IPCIDR, "fd00::/8", "E"),
+            rule(RuleType::Match, "", "F"),
+        ],
+    };
+    let proxy = |host| rules.match_host(host).rule.map(|x| x.proxy.as_str());
+
+    assert_eq!(proxy("example.com"), Some("A"));
+    assert_eq!(proxy("www.example.com"), Some("F"));
+    assert_eq!(proxy("google.com"), Some("B"));
+    assert_eq!(proxy("mail.GOOGLE.com."), Some("B"));
+    assert_eq!(proxy("notgoogle.com"), Some("F"));
+    assert_eq!(proxy("raw.githubusercontent.com"), Some("C"));
+    assert_eq!(proxy("10.1.2.3"), Some("D"));
+    assert_eq!(proxy("11.1.2.3"), Some("F"));
+    assert_eq!(proxy("fd12::1"), Some("E"));
+
+    assert_eq!(rules.match_host("example.com").skipped, 0);
+    assert_eq!(rules.match_host("10.1.2.3").skipped, 1);
+    // Domains are not resolved, so IP rules are skipped as well
+    assert_eq!(rules.match_host("example.org").skipped, 3);
+
+    let rules = Rules {
+        rules: vec![rule(RuleType::Domain, "example.com", "A")],
+    };
+    assert_eq!(rules.match_host("example.org").rule, None);
+}
+
+#[test]
+fn test_rule_stats() {
+    let rule = |rule_type| Rule {
+        rule_type,
+        payload: String::new(),
+        proxy: "DIRECT".to_owned(),
+    };
+    let rules = Rules {
+        rules: vec![
+            rule(RuleType::IPCIDR),
+            rule(RuleType::DomainSuffix),
+            rule(RuleType::GeoIP),
+            rule(RuleType::DomainSuffix),
+            rule(RuleType::Match),
+        ],
+    };
+    let stats = rules.stats();
+    assert_eq!(stats.total, 5);
+    assert_eq!(stats.by_type[&RuleType::DomainSuffix], 2);
+    assert_eq!(
+        stats.most_common(),
+        vec![
+            (RuleType::DomainSuffix, 2),
+            (RuleType::GeoIP, 1),
+            (RuleType::IPCIDR, 1),
+            (RuleType::Match, 1),
+        ]
+    );
+    assert_eq!(Rules::default().stats(), RuleStats::default());
+}
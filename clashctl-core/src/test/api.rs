@@ -18,6 +18,7 @@ fn init() -> Clash {
         .unwrap()
         .secret(env::var("PROXY_SECRET").ok())
         .build()
+        .unwrap()
 }
 
 #[test]
@@ -26,6 +27,12 @@ fn test_proxies() {
     clash.get_proxies().unwrap();
 }
 
+#[test]
+fn test_providers() {
+    let clash = init();
+    clash.get_providers().unwrap();
+}
+
 #[test]
 fn test_rules() {
     let clash = init();
@@ -72,7 +79,8 @@ fn test_configs() {
         .join(".config/clash/config.yaml");
     let _path = default_config_dir.to_str().unwrap();
 
-    clash.get_configs().unwrap();
+    let mode = clash.get_configs().unwrap().mode;
+    clash.set_mode(mode).unwrap();
     // clash.reload_configs(false, path).unwrap();
     // clash.reload_configs(true, path).unwrap();
 }
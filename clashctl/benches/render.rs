@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use clashctl::{
+    components::{MovableListManage, ProxyGroupFocusStatus, ProxyTree, ProxyTreeWidget},
+    LatencyThresholds, Theme,
+};
+use clashctl_core::model::{History, Proxies, Proxy, ProxyType};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+const SIZES: [usize; 3] = [10, 100, 1000];
+const MEMBERS: usize = 30;
+/// Nodes in the pool of [`bench_merge`], with as many groups as that takes
+const MERGE_NODES: usize = 2000;
+const AREA: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 120,
+    height: 40,
+};
+
+fn proxy(proxy_type: ProxyType, delay: Option<u64>) -> Proxy {
+    Proxy {
+        proxy_type,
+        history: delay
+            .map(|delay| History {
+                time: Utc::now(),
+                delay,
+            })
+            .into_iter()
+            .collect(),
+        udp: Some(true),
+        all: None,
+        now: None,
+    }
+}
+
+/// `groups` selectors, each picking `MEMBERS` out of a pool of nodes, some of
+/// which timed out or were never tested, like a real subscription
+fn fixture(groups: usize) -> Proxies {
+    let pool = groups * 2 + MEMBERS;
+    let mut proxies = HashMap::with_capacity(pool + groups + 1);
+    proxies.insert("DIRECT".to_owned(), proxy(ProxyType::Direct, None));
+    for i in 0..pool {
+        let delay = match i % 7 {
+            0 => None,
+            1 => Some(0),
+            x => Some(x as u64 * 97),
+        };
+        let proxy_type = if i % 2 == 0 {
+            ProxyType::Shadowsocks
+        } else {
+            ProxyType::Vmess
+        };
+        proxies.insert(format!("Node {:04}", i), proxy(proxy_type, delay));
+    }
+    for i in 0..groups {
+        let all = std::iter::once("DIRECT".to_owned())
+            .chain((0..MEMBERS).map(|x| format!("Node {:04}", (i * 2 + x) % pool)))
+            .collect::<Vec<_>>();
+        let group = Proxy {
+            now: Some(all[i % all.len()].clone()),
+            all: Some(all),
+            ..proxy(ProxyType::Selector, None)
+        };
+        proxies.insert(format!("Group {:04}", i), group);
+    }
+    Proxies { proxies }
+}
+
+fn render(tree: &ProxyTree) -> Buffer {
+    let mut buf = Buffer::empty(AREA);
+    ProxyTreeWidget::new(tree).render(AREA, &mut buf);
+    buf
+}
+
+fn bench_from_proxies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_proxies");
+    for size in SIZES {
+        let proxies = fixture(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &proxies, |b, proxies| {
+            b.iter(|| ProxyTree::from(proxies.clone()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    for size in SIZES {
+        let collapsed = ProxyTree::from(fixture(size));
+        group.bench_with_input(
+            BenchmarkId::new("collapsed", size),
+            &collapsed,
+            |b, tree| b.iter(|| render(tree)),
+        );
+
+        let mut expanded = collapsed.clone();
+        expanded.expand_all().toggle();
+        group.bench_with_input(BenchmarkId::new("expanded", size), &expanded, |b, tree| {
+            b.iter(|| render(tree))
+        });
+    }
+    group.finish();
+}
+
+fn bench_get_widget(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_widget");
+    let thresholds = LatencyThresholds::default();
+    let theme = Theme::default();
+    for size in SIZES {
+        let collapsed = ProxyTree::from(fixture(size));
+        let mut expanded = collapsed.clone();
+        expanded.expand_all();
+
+        for (name, tree, status) in [
+            ("collapsed", &collapsed, ProxyGroupFocusStatus::None),
+            ("expanded", &expanded, ProxyGroupFocusStatus::Expanded),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, size), tree, |b, tree| {
+                b.iter(|| {
+                    tree.groups()
+                        .iter()
+                        .map(|x| {
+                            x.get_widget(AREA, &thresholds, &theme, status, false, false)
+                                .len()
+                        })
+                        .sum::<usize>()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Polls merged into a tree of [`MERGE_NODES`] nodes, against rebuilding the
+/// tree from scratch as it was before merging in place
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge");
+    let freq = HashMap::new();
+    let proxies = fixture((MERGE_NODES - MEMBERS) / 2);
+    let mut tree = ProxyTree::default();
+    tree.merge(&proxies, &freq);
+
+    let mut changed = proxies.clone();
+    let node = changed.proxies.get_mut("Node 0002").unwrap();
+    node.history = proxy(ProxyType::Shadowsocks, Some(42)).history;
+
+    group.bench_function(BenchmarkId::new("unchanged", MERGE_NODES), |b| {
+        b.iter_batched_ref(
+            || tree.clone(),
+            |tree| {
+                tree.merge(&proxies, &freq);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function(BenchmarkId::new("one_changed", MERGE_NODES), |b| {
+        b.iter_batched_ref(
+            || tree.clone(),
+            |tree| {
+                tree.merge(&changed, &freq);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function(BenchmarkId::new("rebuild", MERGE_NODES), |b| {
+        b.iter(|| ProxyTree::from(changed.clone()))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_from_proxies,
+    bench_render,
+    bench_get_widget,
+    bench_merge
+);
+criterion_main!(benches);
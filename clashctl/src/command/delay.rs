@@ -0,0 +1,53 @@
+use clap::Parser;
+use clashctl_core::ErrorKind as ClashErrorKind;
+use url::Url;
+
+use crate::{interactive::Flags, ErrorKind, Result};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(after_help = "EXIT STATUS:
+    0    Delay printed
+    1    Other errors
+    3    Server is unreachable
+    4    Proxy not found
+    5    Delay test failed or timed out
+    6    Unauthorized, secret of the server is wrong")]
+pub struct DelayOpt {
+    #[clap(help = "Name of the proxy")]
+    pub proxy: String,
+
+    #[clap(long, help = "Url to test against, default to `--test-url`")]
+    pub url: Option<Url>,
+
+    #[clap(long, default_value = "5000", help = "Timeout of the test, in ms")]
+    pub timeout: u64,
+}
+
+impl DelayOpt {
+    pub fn handle(&self, flags: &Flags) -> Result<()> {
+        // Leave the server some time to respond after the test timed out
+        let flags = Flags {
+            timeout: flags.delay_request_timeout(self.timeout),
+            ..flags.clone()
+        };
+        let clash = flags.connect_server_from_config()?;
+        let url = self.url.as_ref().unwrap_or(&flags.test_url);
+
+        let failed = || ErrorKind::DelayTestFailed(self.proxy.clone());
+        let delay = match clash.get_proxy_delay(&self.proxy, url.as_str(), self.timeout) {
+            Ok(x) if x.delay > 0 => x.delay,
+            Ok(_) => return Err(failed().into()),
+            Err(e) => {
+                return Err(match e.kind() {
+                    ClashErrorKind::FailedResponse(404) => {
+                        ErrorKind::ProxyNotFound(self.proxy.clone()).into()
+                    }
+                    ClashErrorKind::FailedResponse(503 | 504) => failed().into(),
+                    _ => e.into(),
+                })
+            }
+        };
+        println!("{}", delay);
+        Ok(())
+    }
+}
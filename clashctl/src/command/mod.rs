@@ -3,7 +3,7 @@ use log::LevelFilter;
 
 use crate::{interactive::Flags, ui::TuiOpt, utils::init_logger};
 
-mod_use::mod_use!(completion, proxy, server);
+mod_use::mod_use!(completion, delay, proxy, server);
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -24,10 +24,12 @@ pub struct Opts {
 pub enum Cmd {
     #[clap(about = "Open TUI")]
     Tui(TuiOpt),
-    #[clap(subcommand)]
-    Proxy(ProxySubcommand),
+    #[clap(alias = "proxies")]
+    Proxy(ProxyOpt),
     #[clap(subcommand)]
     Server(ServerSubcommand),
+    #[clap(about = "Test delay of a proxy, in ms")]
+    Delay(DelayOpt),
     #[clap(alias = "comp")]
     Completion(CompletionArg),
 }
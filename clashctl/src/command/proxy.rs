@@ -1,13 +1,20 @@
 use std::time::Duration;
 
 use clap::{Parser, Subcommand};
-use clashctl_core::{model::ProxyType, strum::VariantNames};
-use log::{error, info, warn};
+use clashctl_core::{
+    model::{Proxies, ProxyType},
+    resolve_test_url,
+    strum::VariantNames,
+    Clash,
+};
+use log::{debug, error, info, warn};
 use owo_colors::OwoColorize;
+use rayon::prelude::*;
 use requestty::{prompt_one, Answer, ListItem, Question};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    interactive::{Flags, ProxySortBy, SortOrder},
+    interactive::{Config, Flags, ProxySortBy, SortOrder},
     RenderList, Result,
 };
 // use crate::{Result};
@@ -36,13 +43,69 @@ use crate::{
 //     }
 // }
 
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Interacting with proxies",
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true
+)]
+pub struct ProxyOpt {
+    #[clap(subcommand)]
+    pub cmd: Option<ProxySubcommand>,
+
+    #[clap(long, help = "Print raw proxies as JSON")]
+    pub json: bool,
+}
+
+impl ProxyOpt {
+    pub fn handle(&self, flags: &Flags) -> Result<()> {
+        let config = flags.get_config()?;
+        let server = match flags.using_server(&config) {
+            Ok(server) => server,
+            Err(_) => {
+                warn!("No server configured yet. Use `clashctl server add` first.");
+                return Ok(());
+            }
+        };
+        info!("Using {}", server);
+        let clash = flags.connect_server(server)?;
+
+        match self.cmd {
+            Some(ref cmd) => cmd.handle(flags, &config, clash),
+            None => {
+                let proxies = clash.get_proxies()?;
+                println!("{}", serde_json::to_string_pretty(&proxies)?);
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
-#[clap(about = "Interacting with proxies")]
 pub enum ProxySubcommand {
     #[clap(alias = "ls", about = "List proxies (alias ls)")]
     List(ProxyListOpt),
     #[clap(about = "Set active proxy")]
     Use,
+    #[clap(about = "Test delay of all proxies in a group")]
+    Delay(ProxyDelayOpt),
+    #[clap(alias = "now", about = "Show current proxy of each group (alias now)")]
+    Current(ProxyCurrentOpt),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ProxyCurrentOpt {
+    #[clap(long, help = "Print result as JSON")]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ProxyDelayOpt {
+    #[clap(help = "Name of the group")]
+    pub group: String,
+
+    #[clap(long, help = "Print result as JSON")]
+    pub json: bool,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -87,22 +150,91 @@ pub struct ProxyListOpt {
 }
 
 impl ProxySubcommand {
-    pub fn handle(&self, flags: &Flags) -> Result<()> {
-        let config = flags.get_config()?;
-        let server = match config.using_server() {
-            Some(server) => server.to_owned(),
-            None => {
-                warn!("No server configured yet. Use `clashctl server add` first.");
-                return Ok(());
+    pub fn handle(&self, flags: &Flags, config: &Config, clash: Clash) -> Result<()> {
+        match self {
+            ProxySubcommand::List(opt) => clash.get_proxies()?.render_list(opt),
+            ProxySubcommand::Delay(opt) => {
+                // Leave the server some time to respond after the test timed out
+                let timeout = Duration::from_millis(flags.delay_request_timeout(flags.timeout));
+                let clash = clash.with_timeout(Some(timeout));
+                let group = clash.get_proxy(&opt.group)?;
+                let members = match group.all {
+                    Some(members) => members,
+                    None => {
+                        warn!("{} is not a group", opt.group);
+                        return Ok(());
+                    }
+                };
+                let test_url =
+                    resolve_test_url(&opt.group, flags.test_url.as_str(), &config.test_urls)?;
+                let delays = match clash.get_group_delay(&opt.group, test_url, flags.timeout) {
+                    Ok(delays) => delays,
+                    Err(e) => {
+                        debug!("Group delay test unavailable ({}), test one by one", e);
+                        members
+                            .par_iter()
+                            .filter_map(|proxy| {
+                                clash
+                                    .get_proxy_delay(proxy, test_url, flags.timeout)
+                                    .ok()
+                                    .map(|x| (proxy.to_owned(), x.delay))
+                            })
+                            .collect()
+                    }
+                };
+
+                // Failed ones at the bottom
+                let mut result = members
+                    .into_iter()
+                    .map(|name| (delays.get(&name).copied().filter(|x| *x > 0), name))
+                    .collect::<Vec<_>>();
+                result.sort_by_key(|(delay, _)| delay.unwrap_or(u64::MAX));
+
+                if opt.json {
+                    let list = result
+                        .into_iter()
+                        .map(|(delay, name)| serde_json::json!({ "name": name, "delay": delay }))
+                        .collect::<Vec<_>>();
+                    println!("{}", serde_json::to_string_pretty(&list)?);
+                } else {
+                    println!("{:<8}NAME", "DELAY");
+                    for (delay, name) in result {
+                        match delay {
+                            Some(delay) => println!("{:<8}{}", delay, name),
+                            None => println!("{:<8}{}", "-".red(), name),
+                        }
+                    }
+                }
             }
-        };
-        info!("Using {}", server);
-        let clash = server.into_clash_with_timeout(Some(Duration::from_millis(flags.timeout)))?;
+            ProxySubcommand::Current(opt) => {
+                let selections = current_selections(&clash.get_proxies()?);
 
-        match self {
-            ProxySubcommand::List(opt) => {
-                let proxies = clash.get_proxies()?;
-                proxies.render_list(opt);
+                if opt.json {
+                    let list = selections
+                        .into_iter()
+                        .map(|(group, now, delay)| {
+                            serde_json::json!({ "group": group, "now": now, "delay": delay })
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{}", serde_json::to_string_pretty(&list)?);
+                } else {
+                    let width = |names: Vec<&str>| {
+                        names.iter().map(|x| x.width()).max().unwrap_or_default() + 2
+                    };
+                    let groups = selections.iter().map(|x| x.0.as_str());
+                    let nows = selections.iter().filter_map(|x| x.1.as_deref());
+                    let group_width = width(groups.chain(["GROUP"]).collect());
+                    let now_width = width(nows.chain(["NOW"]).collect());
+                    println!("{:<group_width$}{:<now_width$}DELAY", "GROUP", "NOW");
+                    for (group, now, delay) in selections {
+                        let now = now.unwrap_or_else(|| "-".to_owned());
+                        let delay = match delay {
+                            Some(delay) => delay.to_string(),
+                            None => "-".red().to_string(),
+                        };
+                        println!("{:<group_width$}{:<now_width$}{}", group, now, delay);
+                    }
+                }
             }
             ProxySubcommand::Use => {
                 let proxies = clash.get_proxies()?;
@@ -160,9 +292,50 @@ impl ProxySubcommand {
     }
 }
 
+/// Group, its selected member and latest delay of that member, if it passed.
+/// Groups ordered by name, as there's no rule to order them by
+fn current_selections(proxies: &Proxies) -> Vec<(String, Option<String>, Option<u64>)> {
+    let mut selections = proxies
+        .groups()
+        .map(|(name, group)| {
+            let delay = group
+                .now
+                .as_ref()
+                .and_then(|now| proxies.get(now))
+                .and_then(|now| now.history.iter().max_by_key(|x| x.time))
+                .map(|x| x.delay)
+                .filter(|delay| *delay > 0);
+            (name.to_owned(), group.now.to_owned(), delay)
+        })
+        .collect::<Vec<_>>();
+    selections.sort();
+    selections
+}
+
 #[test]
 fn test_proxy_type() {
     let string = "direct";
     let parsed = string.parse().unwrap();
     assert_eq!(ProxyType::Direct, parsed);
 }
+
+#[test]
+fn test_current_selections() {
+    let proxies: Proxies = serde_json::from_value(serde_json::json!({ "proxies": {
+        "b": { "type": "Selector", "history": [], "all": ["y"], "now": "y" },
+        "a": { "type": "Selector", "history": [], "all": ["x", "y"], "now": "x" },
+        "x": { "type": "Shadowsocks", "history": [
+            { "time": "2022-01-01T00:00:01Z", "delay": 120 },
+            { "time": "2022-01-01T00:00:00Z", "delay": 0 },
+        ] },
+        "y": { "type": "Shadowsocks", "history": [{ "time": "2022-01-01T00:00:00Z", "delay": 0 }] },
+    }}))
+    .unwrap();
+    assert_eq!(
+        current_selections(&proxies),
+        [
+            ("a".to_owned(), Some("x".to_owned()), Some(120)),
+            ("b".to_owned(), Some("y".to_owned()), None),
+        ]
+    );
+}
@@ -1,4 +1,5 @@
 use clap::Subcommand;
+use clashctl_core::parse_controller_url;
 use log::{debug, info, warn};
 use owo_colors::OwoColorize;
 use requestty::{prompt, prompt_one, Answers, Question};
@@ -33,8 +34,8 @@ impl ServerSubcommand {
             Self::Add => {
                 let questions = [
                     Question::input("url")
-                        .message("URL of Clash API")
-                        .validate(|input, _| match Url::parse(input) {
+                        .message("URL of Clash API, like 127.0.0.1:9090")
+                        .validate(|input, _| match parse_controller_url(input) {
                             Ok(_) => Ok(()),
                             Err(e) => Err(format!("Invalid URL: {}", e)),
                         })
@@ -42,6 +43,9 @@ impl ServerSubcommand {
                     Question::password("secret")
                         .message("Secret of Clash API, default to None:")
                         .build(),
+                    Question::input("name")
+                        .message("Name of the server, default to None:")
+                        .build(),
                 ];
                 let mut res = prompt(questions).expect("Error during prompt");
                 debug!("{:#?}", res);
@@ -50,12 +54,21 @@ impl ServerSubcommand {
                     secret => Some(secret),
                 };
 
+                let name = match res.remove("name").unwrap().try_into_string().unwrap() {
+                    string if string.is_empty() => None,
+                    name => Some(name),
+                };
+
                 let url_str = res.remove("url").unwrap().try_into_string().unwrap();
-                let url = Url::parse(&url_str).unwrap();
+                let url = parse_controller_url(&url_str)?;
 
                 let server = Server {
                     secret,
                     url: url.clone(),
+                    name,
+                    proxy: None,
+                    root_cert: None,
+                    accept_invalid_certs: false,
                 };
 
                 info!("Adding {}", server);
@@ -89,7 +102,7 @@ impl ServerSubcommand {
                 let (Width(terminal_width), _) = terminal_size().unwrap_or((Width(70), Height(0)));
                 let active = config.using_server();
                 println!("\n{:-<1$}", "", terminal_width as usize);
-                println!("{:<8}{:<50}", "ACTIVE".green(), "URL");
+                println!("{:<8}{:<16}{:<50}", "ACTIVE".green(), "NAME", "URL");
                 println!("{:-<1$}", "", terminal_width as usize);
                 for server in &config.servers {
                     let is_active = match active {
@@ -97,8 +110,9 @@ impl ServerSubcommand {
                         _ => false,
                     };
                     println!(
-                        "{:^8}{:<50}",
+                        "{:^8}{:<16}{:<50}",
                         if is_active { "→".green() } else { "".green() },
+                        server.name.as_deref().unwrap_or("-"),
                         server.url.as_str(),
                     )
                 }
@@ -11,6 +11,15 @@ pub enum ErrorKind {
 
     #[error("Requestty error")]
     RequesttyError(#[from] requestty::ErrorKind),
+
+    #[error("Cannot serialize output ({0})")]
+    SerializeError(#[from] serde_json::Error),
+
+    #[error("Cannot find proxy {0}")]
+    ProxyNotFound(String),
+
+    #[error("Delay test of {0} failed or timed out")]
+    DelayTestFailed(String),
 }
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
@@ -25,4 +34,26 @@ where
     }
 }
 
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0
+    }
+
+    /// Exit status of the process failed with this error, see `clashctl delay -h`
+    pub fn exit_code(&self) -> i32 {
+        use clashctl_core::ErrorKind as Clash;
+
+        match self.kind() {
+            ErrorKind::ClashCtl(e) => match e.kind() {
+                Clash::ConnectionRefused | Clash::DnsFailure | Clash::Timeout => 3,
+                Clash::Unauthorized => 6,
+                _ => 1,
+            },
+            ErrorKind::ProxyNotFound(_) => 4,
+            ErrorKind::DelayTestFailed(_) => 5,
+            _ => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
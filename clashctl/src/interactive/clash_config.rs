@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use clashctl_core::parse_controller_url;
+use home::home_dir;
+
+use super::{InteractiveError, InteractiveResult, Server};
+
+/// Default path of Clash's own config file
+pub fn clash_config_path() -> Option<PathBuf> {
+    home_dir().map(|dir| dir.join(".config/clash/config.yaml"))
+}
+
+/// Server described by `external-controller` and `secret` of Clash config
+/// file at `path`
+pub fn read_clash_server(path: &Path) -> InteractiveResult<Server> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        InteractiveError::ClashConfigError(format!("unable to read {} ({})", path.display(), e))
+    })?;
+    parse_clash_server(&content).map_err(|e| match e {
+        InteractiveError::ClashConfigError(msg) => {
+            InteractiveError::ClashConfigError(format!("{} in {}", msg, path.display()))
+        }
+        e => e,
+    })
+}
+
+/// Only top level `external-controller` and `secret` are read, the rest of
+/// the YAML is ignored
+fn parse_clash_server(content: &str) -> InteractiveResult<Server> {
+    let controller = top_level_value(content, "external-controller").ok_or_else(|| {
+        InteractiveError::ClashConfigError("`external-controller` is missing".to_owned())
+    })?;
+    let secret = top_level_value(content, "secret").filter(|x| !x.is_empty());
+
+    let invalid = || {
+        InteractiveError::ClashConfigError(format!(
+            "`external-controller` {} is not a valid address",
+            controller
+        ))
+    };
+    let (host, port) = controller.rsplit_once(':').ok_or_else(invalid)?;
+    // Listening on all interfaces, reach it locally
+    let host = match host {
+        "" | "0.0.0.0" => "127.0.0.1",
+        "[::]" => "[::1]",
+        host => host,
+    };
+    let url = parse_controller_url(&format!("{}:{}", host, port)).map_err(|_| invalid())?;
+
+    Ok(Server {
+        url,
+        secret,
+        name: None,
+        proxy: None,
+        root_cert: None,
+        accept_invalid_certs: false,
+    })
+}
+
+/// Value of a top level `key: value` line, unquoted and without comments
+fn top_level_value(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.trim_start().strip_prefix(':')?.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next()?,
+            _ => value.split(" #").next()?.trim_end(),
+        };
+        Some(value.to_owned())
+    })
+}
+
+#[test]
+fn test_parse_clash_server() {
+    let server = parse_clash_server(
+        r#"
+port: 7890
+# external-controller: 127.0.0.1:1234
+external-controller: :9090 # all interfaces
+secret: "s3cret # not a comment"
+proxies:
+  - name: a
+    secret: nested
+"#,
+    )
+    .unwrap();
+    assert_eq!(server.url.as_str(), "http://127.0.0.1:9090/");
+    assert_eq!(server.secret.as_deref(), Some("s3cret # not a comment"));
+
+    let server = parse_clash_server("external-controller: '192.168.1.2:9090'\nsecret: ''").unwrap();
+    assert_eq!(server.url.as_str(), "http://192.168.1.2:9090/");
+    assert_eq!(server.secret, None);
+
+    let server = parse_clash_server("external-controller: '[::]:9090'").unwrap();
+    assert_eq!(server.url.as_str(), "http://[::1]:9090/");
+
+    assert!(parse_clash_server("port: 7890").is_err());
+    assert!(parse_clash_server("external-controller: 9090").is_err());
+}
@@ -0,0 +1,164 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+use tui::style::Color;
+
+/// Colors a terminal is able to show. Theme colors beyond it are mapped to
+/// the nearest ones it has, see [`ColorLevel::downgrade`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorLevel {
+    /// Named colors only
+    Ansi16,
+    /// xterm 256 color palette
+    Ansi256,
+    /// 24 bit RGB
+    TrueColor,
+}
+
+/// The 16 named colors, as xterm shows them by default
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Difference between the largest and smallest channels of a color for it
+/// to be considered colored rather than gray
+const MIN_CHROMA: u8 = 48;
+
+/// Channel values of the 6x6x6 color cube in xterm 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorLevel {
+    /// Guess from `$COLORTERM` and `$TERM` of the environment
+    pub fn detect() -> Self {
+        Self::detect_from(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    /// `colorterm` of `truecolor` or `24bit` means true color. Otherwise
+    /// terminfo names like `xterm-256color` tell 256 colors
+    pub fn detect_from(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        match (colorterm, term) {
+            (Some("truecolor" | "24bit"), _) => ColorLevel::TrueColor,
+            (_, Some(term)) if term.contains("truecolor") || term.contains("direct") => {
+                ColorLevel::TrueColor
+            }
+            (_, Some(term)) if term.contains("256") => ColorLevel::Ansi256,
+            _ => ColorLevel::Ansi16,
+        }
+    }
+
+    /// Nearest color of `color` that can be shown at this level
+    pub fn downgrade(self, color: Color) -> Color {
+        let rgb = match color {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Indexed(index) if self == ColorLevel::Ansi16 => indexed_rgb(index),
+            color => return color,
+        };
+        match self {
+            ColorLevel::TrueColor => color,
+            ColorLevel::Ansi256 => Color::Indexed(nearest_indexed(rgb)),
+            ColorLevel::Ansi16 => nearest_ansi16(rgb),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Colored ones are never mapped to grays, which can be closer by distance
+/// but lose what the color is for
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    let (r, g, b) = rgb;
+    let colored = r.max(g).max(b) - r.min(g).min(b) >= MIN_CHROMA;
+    ANSI16
+        .iter()
+        .filter(|(_, (r, g, b))| !colored || r != g || g != b)
+        .min_by_key(|(_, x)| distance(rgb, *x))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+/// Nearest of the color cube and the gray ramp in 256 color palette, the 16
+/// named ones are left out as terminals often redefine them
+fn nearest_indexed((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |x: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - x as i32).abs())
+            .unwrap_or_default()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = (average.saturating_sub(8) / 10).min(23) as u8;
+    let gray_value = 8 + gray_index * 10;
+    let gray = (gray_value, gray_value, gray_value);
+
+    if distance((r, g, b), gray) < distance((r, g, b), cube) {
+        232 + gray_index
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+/// RGB of a color in 256 color palette
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let value = 8 + (index - 232) * 10;
+            (value, value, value)
+        }
+    }
+}
+
+#[test]
+fn test_color_level() {
+    use ColorLevel::*;
+
+    assert_eq!(ColorLevel::detect_from(Some("truecolor"), None), TrueColor);
+    assert_eq!(ColorLevel::detect_from(Some("24bit"), Some("xterm")), TrueColor);
+    assert_eq!(ColorLevel::detect_from(None, Some("xterm-256color")), Ansi256);
+    assert_eq!(ColorLevel::detect_from(None, Some("xterm-direct")), TrueColor);
+    assert_eq!(ColorLevel::detect_from(None, Some("linux")), Ansi16);
+    assert_eq!(ColorLevel::detect_from(None, None), Ansi16);
+
+    let orange = Color::Rgb(0xaf, 0x87, 0x00);
+    assert_eq!(TrueColor.downgrade(orange), orange);
+    assert_eq!(Ansi256.downgrade(orange), Color::Indexed(136));
+    assert_eq!(Ansi16.downgrade(orange), Color::Yellow);
+    assert_eq!(Ansi256.downgrade(Color::Rgb(0x80, 0x80, 0x80)), Color::Indexed(244));
+    assert_eq!(Ansi16.downgrade(Color::Indexed(196)), Color::LightRed);
+    assert_eq!(Ansi16.downgrade(Color::Rgb(0x6c, 0x71, 0xc4)), Color::LightBlue);
+    assert_eq!(Ansi16.downgrade(Color::Rgb(0x58, 0x6e, 0x75)), Color::DarkGray);
+    // Named colors are shown by all terminals
+    assert_eq!(Ansi16.downgrade(Color::LightGreen), Color::LightGreen);
+}
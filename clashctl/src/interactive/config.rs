@@ -3,7 +3,7 @@ use std::{
     fs::{File, OpenOptions},
     io::{Read, Seek, SeekFrom, Write},
     ops::{Deref, DerefMut},
-    path::Path,
+    path::{Path, PathBuf},
     time::Duration,
 };
 
@@ -19,11 +19,34 @@ use super::{ConfigData, InteractiveError, InteractiveResult};
 pub struct Server {
     pub url: url::Url,
     pub secret: Option<String>,
+    /// Name to tell servers apart, like `home` or `vps`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Proxy to reach the server through, like `http://127.0.0.1:7890`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<Url>,
+    /// PEM certificate to trust, for servers behind HTTPS with self-signed
+    /// certificates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_cert: Option<PathBuf>,
+    /// Skip verifying certificate of the server, only if you know what you're
+    /// doing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accept_invalid_certs: bool,
 }
 
 impl Server {
+    /// Name of the server, or host of its url if it's unnamed
+    pub fn display_name(&self) -> &str {
+        match (&self.name, self.url.host_str()) {
+            (Some(name), _) => name,
+            (None, Some(host)) => host,
+            (None, None) => self.url.as_str(),
+        }
+    }
+
     pub fn into_clash_with_timeout(self, timeout: Option<Duration>) -> InteractiveResult<Clash> {
-        Ok(self.into_clash_builder()?.timeout(timeout).build())
+        Ok(self.into_clash_builder()?.timeout(timeout).build()?)
     }
 
     pub fn into_clash(self) -> InteractiveResult<Clash> {
@@ -31,13 +54,20 @@ impl Server {
     }
 
     pub fn into_clash_builder(self) -> InteractiveResult<ClashBuilder> {
-        Ok(ClashBuilder::new(self.url)?.secret(self.secret))
+        Ok(ClashBuilder::new(self.url)?
+            .secret(self.secret)
+            .proxy(self.proxy)
+            .root_cert(self.root_cert)
+            .accept_invalid_certs(self.accept_invalid_certs))
     }
 }
 
 impl Display for Server {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Server ({})", self.url)
+        match self.name {
+            Some(ref name) => write!(f, "Server {} ({})", name, self.url),
+            None => write!(f, "Server ({})", self.url),
+        }
     }
 }
 
@@ -179,6 +209,22 @@ fn test_config() {
     config.servers.push(Server {
         url: url::Url::parse(&env::var("PROXY_ADDR").unwrap()).unwrap(),
         secret: None,
+        name: None,
+        proxy: None,
+        root_cert: None,
+        accept_invalid_certs: false,
     });
     config.write().unwrap();
 }
+
+#[test]
+fn test_server_name() {
+    let server: Server = from_str(r#"(url: "http://127.0.0.1:9090", secret: None)"#).unwrap();
+    assert_eq!(server.name, None);
+    assert_eq!(server.display_name(), "127.0.0.1");
+
+    let server: Server =
+        from_str(r#"(url: "http://10.0.0.1:9090", secret: None, name: Some("vps"))"#).unwrap();
+    assert_eq!(server.display_name(), "vps");
+    assert_eq!(server.to_string(), "Server vps (http://10.0.0.1:9090/)");
+}
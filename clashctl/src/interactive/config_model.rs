@@ -1,9 +1,15 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
+use clashctl_core::{model::Level, resolve_test_url};
 use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use tui::layout::{Constraint, Direction, Layout, Rect};
 use url::Url;
 
-use crate::{ConSort, ProxySort, RuleSort, Server};
+use crate::{
+    ColorLevel, ConSort, InteractiveError, InteractiveResult, KeyMap, ProxySort, RuleSort, Server,
+    ThemeConfig,
+};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct ConfigData {
@@ -13,11 +19,265 @@ pub struct ConfigData {
     pub tui: TuiConfig,
     #[serde(default)]
     pub sort: SortsConfig,
+    /// Urls for testing delay of specific groups, keyed by group name,
+    /// overriding `--test-url`
+    #[serde(default)]
+    pub test_urls: HashMap<String, String>,
+}
+
+impl ConfigData {
+    pub fn validate_test_urls(&self) -> InteractiveResult<()> {
+        for group in self.test_urls.keys() {
+            resolve_test_url(group, "", &self.test_urls)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TuiConfig {
     pub log_file: Option<PathBuf>,
+    /// Minimum level of Clash logs to receive, default to info
+    #[serde(default)]
+    pub log_level: Option<Level>,
+    #[serde(default)]
+    pub latency: LatencyThresholds,
+    #[serde(default)]
+    pub keymap: KeyMap,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// Colors the terminal supports, like `Some(ansi16)`, `Some(ansi256)` or
+    /// `Some(true_color)`. Detected from `$COLORTERM` & `$TERM` by default
+    #[serde(default)]
+    pub color_level: Option<ColorLevel>,
+    /// Show proxy types as short codes like `SS` or `VM` in proxies page
+    #[serde(default)]
+    pub compact: bool,
+    /// Show collapsed groups in proxies page as one line each, without
+    /// latency signs of members. Toggled with `V`
+    #[serde(default)]
+    pub single_line_groups: bool,
+    /// Arrangement of panes in status page, default to info beside traffic
+    #[serde(default)]
+    pub layout: Option<LayoutConfig>,
+    /// Base url of web dashboard, like `http://127.0.0.1:9090/ui`, opened
+    /// with pointed group & proxy as `group` & `proxy` queries
+    #[serde(default)]
+    pub dashboard: Option<Url>,
+    /// Names of groups pinned to top of proxies page
+    #[serde(default)]
+    pub pinned_groups: Vec<String>,
+    /// Seconds between refreshing proxies, rules, version and config,
+    /// default to 5
+    #[serde(default)]
+    pub poll_interval: Option<f32>,
+    /// Shell command run after selecting a proxy in TUI, with `{group}` and
+    /// `{proxy}` replaced by quoted names, like `notify-send {group} {proxy}`
+    #[serde(default)]
+    pub on_select: Option<String>,
+    /// Milliseconds to wait for each latency test, default to `--timeout`.
+    /// Adjusted with `+` & `-` in proxies page
+    #[serde(default)]
+    pub delay_timeout: Option<u64>,
+    /// File proxies are exported to with `e`, time stamped before extension.
+    /// `.yaml` or `.yml` for YAML, JSON otherwise. Default to
+    /// `clashctl-proxies.json` in working directory
+    #[serde(default)]
+    pub export_path: Option<PathBuf>,
+    /// Panels not shown in TUI, like `[logs, connections]`. Their pages and
+    /// panes are removed and their states are never requested
+    #[serde(default)]
+    pub hidden_panels: Vec<Panel>,
+    /// Aliases of proxies shown in place of their names in proxies page,
+    /// keyed by proxy names. Set with `a` on pointed proxy
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// Where `GLOBAL` group is shown in proxies page, one of `normal`,
+    /// `bottom`, `collapsed` or `hidden`. Default to `normal`
+    #[serde(default)]
+    pub global_group: GlobalGroup,
+    /// Move from the last group or proxy to the first one and back in
+    /// proxies page, skipping hidden ones. Default to `false`
+    #[serde(default)]
+    pub wrap_navigation: bool,
+    /// Max number of logs kept in logs page, oldest ones are dropped first.
+    /// Default to 2000
+    #[serde(default)]
+    pub max_logs: Option<usize>,
+    /// File logs are appended to after pressing `L`, as JSON lines for
+    /// `.json` or `.jsonl`, plain text otherwise. Default to
+    /// `clashctl-logs.log` in working directory
+    #[serde(default)]
+    pub log_record_path: Option<PathBuf>,
+}
+
+impl TuiConfig {
+    /// Whether `panel` is compiled in and not hidden
+    pub fn shows(&self, panel: Panel) -> bool {
+        panel.compiled() && !self.hidden_panels.contains(&panel)
+    }
+}
+
+/// Optional panels of TUI, which can be hidden with `hidden_panels` in config,
+/// or compiled out by disabling cargo features of the same names
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Panel {
+    /// Traffic pane of status page and the traffic stream
+    Traffic,
+    /// Connections page and polling connections
+    Connections,
+    /// Logs page & pane and the log stream
+    Logs,
+}
+
+impl Panel {
+    pub const fn compiled(self) -> bool {
+        match self {
+            Panel::Traffic => cfg!(feature = "traffic"),
+            Panel::Connections => cfg!(feature = "connections"),
+            Panel::Logs => cfg!(feature = "logs"),
+        }
+    }
+}
+
+/// Placement of `GLOBAL` group, which has every proxy and is rarely the one
+/// to look at
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GlobalGroup {
+    /// Sorted like other groups
+    #[default]
+    Normal,
+    /// Below all other groups, unless it's pinned
+    Bottom,
+    /// Sorted like other groups, but never expanded by `zR` or on restart
+    Collapsed,
+    /// Not shown, though `g` still flips it
+    Hidden,
+}
+
+/// Panes of status page, like `(direction: vertical, ratios: [0, 1, 2, 2])`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, SmartDefault)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub direction: LayoutDirection,
+    /// Relative sizes of info, traffic, proxies and logs panes. Missing or
+    /// `0` ones are hidden
+    #[default(_code = "vec![1, 3]")]
+    pub ratios: Vec<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutDirection {
+    /// Side by side
+    #[default]
+    Horizontal,
+    /// Stacked
+    Vertical,
+}
+
+impl From<LayoutDirection> for Direction {
+    fn from(value: LayoutDirection) -> Self {
+        match value {
+            LayoutDirection::Horizontal => Direction::Horizontal,
+            LayoutDirection::Vertical => Direction::Vertical,
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Info, traffic, proxies and logs
+    pub const PANES: usize = 4;
+    pub const TRAFFIC_PANE: usize = 1;
+    pub const PROXIES_PANE: usize = 2;
+    pub const LOGS_PANE: usize = 3;
+
+    pub fn validate(&self) -> InteractiveResult<()> {
+        if self.ratios.len() > Self::PANES {
+            return Err(InteractiveError::ConfigValueError(format!(
+                "layout.ratios has {} values, but there are only {} panes (info, traffic, \
+                 proxies, logs)",
+                self.ratios.len(),
+                Self::PANES
+            )));
+        }
+        if self.ratios.iter().all(|x| *x == 0) {
+            return Err(InteractiveError::ConfigValueError(
+                "layout.ratios should show at least one pane".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Same layout with panes of `hidden` indices removed, their space is
+    /// given to proxies pane
+    pub fn hiding(&self, hidden: &[usize]) -> Self {
+        let mut ratios = self.ratios.clone();
+        ratios.resize(Self::PANES, 0);
+        for &i in hidden {
+            if i < Self::PANES && i != Self::PROXIES_PANE {
+                ratios[Self::PROXIES_PANE] += std::mem::take(&mut ratios[i]);
+            }
+        }
+        Self {
+            direction: self.direction,
+            ratios,
+        }
+    }
+
+    /// Areas of info, traffic, proxies and logs panes, `None` for hidden ones
+    pub fn split(&self, area: Rect) -> [Option<Rect>; Self::PANES] {
+        let shown = self
+            .ratios
+            .iter()
+            .take(Self::PANES)
+            .enumerate()
+            .filter(|(_, x)| **x > 0)
+            .map(|(i, x)| (i, *x as u32))
+            .collect::<Vec<_>>();
+        let sum = shown.iter().map(|(_, x)| x).sum();
+        let chunks = Layout::default()
+            .direction(self.direction.into())
+            .constraints(
+                shown
+                    .iter()
+                    .map(|(_, x)| Constraint::Ratio(*x, sum))
+                    .collect::<Vec<_>>(),
+            )
+            .split(area);
+
+        let mut ret = [None; Self::PANES];
+        for ((i, _), chunk) in shown.into_iter().zip(chunks) {
+            ret[i] = Some(chunk);
+        }
+        ret
+    }
+}
+
+/// Upper bounds (inclusive, in ms) of low and mid latency, used to color
+/// delays green, yellow or red.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+#[serde(default)]
+pub struct LatencyThresholds {
+    #[default = 200]
+    pub low: u64,
+    #[default = 400]
+    pub mid: u64,
+}
+
+impl LatencyThresholds {
+    pub fn validate(&self) -> InteractiveResult<()> {
+        if self.low < self.mid {
+            Ok(())
+        } else {
+            Err(InteractiveError::ConfigValueError(format!(
+                "latency.low ({}) should be less than latency.mid ({})",
+                self.low, self.mid
+            )))
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -26,3 +286,52 @@ pub struct SortsConfig {
     pub rules: RuleSort,
     pub proxies: ProxySort,
 }
+
+#[test]
+fn test_latency_thresholds() {
+    let default = ron::from_str::<LatencyThresholds>("()").unwrap();
+    assert_eq!(default, LatencyThresholds { low: 200, mid: 400 });
+    assert!(default.validate().is_ok());
+
+    let partial = ron::from_str::<LatencyThresholds>("(mid: 800)").unwrap();
+    assert_eq!(partial, LatencyThresholds { low: 200, mid: 800 });
+
+    let invalid = ron::from_str::<LatencyThresholds>("(low: 500)").unwrap();
+    assert!(invalid.validate().is_err());
+}
+
+#[test]
+fn test_layout() {
+    let default = ron::from_str::<LayoutConfig>("()").unwrap();
+    assert_eq!(default, LayoutConfig::default());
+    assert!(default.validate().is_ok());
+
+    let area = Rect::new(0, 0, 200, 60);
+    let raw = "(direction: vertical, ratios: [0, 1, 2, 2])";
+    let config = ron::from_str::<LayoutConfig>(raw).unwrap();
+    assert!(config.validate().is_ok());
+    let [info, traffic, proxies, logs] = config.split(area);
+    assert_eq!(info, None);
+    assert_eq!(traffic, Some(Rect::new(0, 0, 200, 12)));
+    assert_eq!(proxies, Some(Rect::new(0, 12, 200, 24)));
+    assert_eq!(logs, Some(Rect::new(0, 36, 200, 24)));
+
+    let [_, traffic, proxies, logs] = LayoutConfig { ratios: vec![0, 1, 1], ..default }.split(area);
+    assert_eq!(traffic, Some(Rect::new(0, 0, 100, 60)));
+    assert_eq!(proxies, Some(Rect::new(100, 0, 100, 60)));
+    assert_eq!(logs, None);
+
+    let hidden = [LayoutConfig::TRAFFIC_PANE, LayoutConfig::LOGS_PANE];
+    let [_, traffic, proxies, logs] = config.hiding(&hidden).split(area);
+    assert_eq!(traffic, None);
+    assert_eq!(proxies, Some(Rect::new(0, 0, 200, 60)));
+    assert_eq!(logs, None);
+    let [info, traffic, proxies, _] = LayoutConfig::default().hiding(&hidden).split(area);
+    assert_eq!(info, Some(Rect::new(0, 0, 50, 60)));
+    assert_eq!(traffic, None);
+    assert_eq!(proxies, Some(Rect::new(50, 0, 150, 60)));
+
+    for ratios in [vec![], vec![0, 0], vec![1, 1, 1, 1, 1]] {
+        assert!(LayoutConfig { ratios, ..Default::default() }.validate().is_err());
+    }
+}
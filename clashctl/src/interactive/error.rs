@@ -20,8 +20,17 @@ pub enum InteractiveError {
     #[error("Config file cannot be parsed ({0})")]
     ConfigFileFormatError(#[from] ron::error::SpannedError),
 
+    #[error("Invalid config value: {0}")]
+    ConfigValueError(String),
+
+    #[error("Clash config file cannot be used ({0})")]
+    ClashConfigError(String),
+
     #[error("Config file cannot be generated ({0})")]
     ConfigFileGenerateError(#[from] ron::Error),
+
+    #[error("Trace file {} cannot be opened ({1})", .0.display())]
+    TraceFileError(PathBuf, std::io::Error),
 }
 
 pub type InteractiveResult<T> = std::result::Result<T, InteractiveError>;
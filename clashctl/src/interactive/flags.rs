@@ -1,15 +1,21 @@
-use std::{path::PathBuf, time::Duration};
+use std::{fs::OpenOptions, io, path::PathBuf, time::Duration};
 
 use clap::Parser;
-use clashctl_core::Clash;
+use clashctl_core::{Clash, Tracer};
 use home::home_dir;
-use log::debug;
+use log::{debug, warn};
 use url::Url;
 
-use super::{Config, InteractiveError, InteractiveResult};
+use super::{
+    clash_config_path, read_clash_server, Config, InteractiveError, InteractiveResult, Server,
+};
 
 const DEFAULT_TEST_URL: &str = "http://www.gstatic.com/generate_204";
 
+/// Delay test requests wait this much longer (in ms) than the test timeout
+/// given to Clash
+const DELAY_TIMEOUT_MARGIN: u64 = 1000;
+
 #[derive(Clone, Debug, Parser)]
 pub struct Flags {
     #[clap(short, long, parse(from_occurrences))]
@@ -20,6 +26,14 @@ pub struct Flags {
     /// Timeout of requests, in ms
     pub timeout: u64,
 
+    #[clap(long, default_value = "2")]
+    /// Times to retry failed requests for fetching data, like proxies or rules
+    pub retry: usize,
+
+    #[clap(long, default_value = "200")]
+    /// Delay before the first retry, doubled after each one, in ms
+    pub retry_delay: u64,
+
     #[clap(long, conflicts_with = "config-path")]
     /// Path of config directory. Default to ~/.config/clashctl
     pub config_dir: Option<PathBuf>,
@@ -35,6 +49,22 @@ pub struct Flags {
     ]
     /// Url for testing proxy endpointes
     pub test_url: Url,
+
+    #[clap(long)]
+    /// Use `external-controller` and `secret` in ~/.config/clash/config.yaml
+    /// instead of the configured server
+    pub from_clash_config: bool,
+
+    #[clap(long)]
+    /// Print method, url, status and time taken of each request sent to
+    /// Clash to stderr, with secrets hidden. In TUI they're written to
+    /// trace.log beside config file instead
+    pub trace: bool,
+
+    #[clap(long)]
+    /// Append traced requests to this file instead of stderr, implies
+    /// `--trace`
+    pub trace_file: Option<PathBuf>,
 }
 
 impl Default for Flags {
@@ -42,22 +72,71 @@ impl Default for Flags {
         Self {
             verbose: 0,
             timeout: 2000,
+            retry: 2,
+            retry_delay: 200,
             config_dir: None,
             config_path: None,
             test_url: Url::parse(DEFAULT_TEST_URL).unwrap(),
+            from_clash_config: false,
+            trace: false,
+            trace_file: None,
         }
     }
 }
 
 impl Flags {
+    fn config_dir(&self) -> Option<PathBuf> {
+        self.config_dir
+            .to_owned()
+            .or_else(|| home_dir().map(|dir| dir.join(".config/clashctl/")))
+    }
+
+    /// Timeout of requests for delay tests of `test_timeout` ms, in ms.
+    /// Requests wait a bit longer than Clash does, so slow proxies come back
+    /// as failed instead of request errors
+    pub fn delay_request_timeout(&self, test_timeout: u64) -> u64 {
+        self.timeout.max(test_timeout + DELAY_TIMEOUT_MARGIN)
+    }
+
+    /// Path of file to keep TUI state across restarts, next to config file
+    pub fn state_path(&self) -> Option<PathBuf> {
+        self.beside_config("state.ron")
+    }
+
+    /// Path of file for `--trace` in TUI, where stderr is covered
+    pub fn trace_path(&self) -> Option<PathBuf> {
+        self.beside_config("trace.log")
+    }
+
+    fn beside_config(&self, name: &str) -> Option<PathBuf> {
+        match self.config_path {
+            Some(ref path) => path.parent().map(|dir| dir.join(name)),
+            None => self.config_dir().map(|dir| dir.join(name)),
+        }
+    }
+
+    /// Where requests are traced, `None` without `--trace` or `--trace-file`
+    fn tracer(&self) -> InteractiveResult<Option<Tracer>> {
+        match self.trace_file {
+            Some(ref path) => {
+                let file = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(path)
+                    .map_err(|e| InteractiveError::TraceFileError(path.to_owned(), e))?;
+                Ok(Some(Tracer::new(file)))
+            }
+            None if self.trace => Ok(Some(Tracer::new(io::stderr()))),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_config(&self) -> InteractiveResult<Config> {
         if let Some(ref dir) = self.config_path {
             return Config::from_dir(dir);
         }
         let conf_dir = self
-            .config_dir
-            .to_owned()
-            .or_else(|| home_dir().map(|dir| dir.join(".config/clashctl/")))
+            .config_dir()
             .ok_or(InteractiveError::ConfigFileOpenError)?;
 
         if !conf_dir.exists() {
@@ -73,12 +152,39 @@ impl Flags {
         }
     }
 
+    /// Server to use, read from Clash config file with `--from-clash-config`,
+    /// falling back to the one in use in `config`
+    pub fn using_server(&self, config: &Config) -> InteractiveResult<Server> {
+        if self.from_clash_config {
+            let server = clash_config_path()
+                .ok_or_else(|| {
+                    InteractiveError::ClashConfigError("home directory is unknown".to_owned())
+                })
+                .and_then(|path| read_clash_server(&path));
+            match server {
+                Ok(server) => return Ok(server),
+                Err(e) => warn!("{}, falling back to configured server", e),
+            }
+        }
+        config
+            .using_server()
+            .cloned()
+            .ok_or(InteractiveError::ServerNotFound)
+    }
+
     pub fn connect_server_from_config(&self) -> InteractiveResult<Clash> {
         let config = self.get_config()?;
-        let server = config
-            .using_server()
-            .ok_or(InteractiveError::ServerNotFound)?
-            .to_owned();
-        server.into_clash_with_timeout(Some(Duration::from_millis(self.timeout)))
+        self.connect_server(self.using_server(&config)?)
+    }
+
+    /// Build client of `server` with request options from flags
+    pub fn connect_server(&self, server: Server) -> InteractiveResult<Clash> {
+        Ok(server
+            .into_clash_builder()?
+            .timeout(Some(Duration::from_millis(self.timeout)))
+            .retry(self.retry)
+            .retry_delay(Duration::from_millis(self.retry_delay))
+            .trace(self.tracer()?)
+            .build()?)
     }
 }
@@ -0,0 +1,466 @@
+use std::{fmt::Display, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::{InteractiveError, InteractiveResult};
+
+/// Actions that can be bound to keys in [`KeyMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum KeyAction {
+    MoveUp,
+    MoveDown,
+    Expand,
+    Select,
+    TestDelay,
+    TestMember,
+    Search,
+    Quit,
+    TestAllLatency,
+    TestAndSelectFastest,
+    OlderSample,
+    NewerSample,
+    IncreaseTestTimeout,
+    DecreaseTestTimeout,
+    SearchAllGroups,
+    NextMatch,
+    JumpToCurrent,
+    ToggleDelaySort,
+    ToggleHideDead,
+    ToggleCompact,
+    ToggleSingleLineGroups,
+    TogglePin,
+    NextGroupFilter,
+    NextGroupSort,
+    EditAlias,
+    ShowGroupStats,
+    ShowSelections,
+    FlipGlobalDirect,
+    SelectNextMember,
+    SelectPreviousMember,
+    UpdateProvider,
+    CopyName,
+    OpenDashboard,
+    ExportProxies,
+    ToggleLogRecording,
+    MatchRule,
+    NextLogLevel,
+    NextMode,
+    ReloadConfig,
+    PickServer,
+    Reconnect,
+    TogglePause,
+    OpenPalette,
+}
+
+/// Keys bound to each [`KeyAction`]. Keys are written like `"k"`, `"up"`,
+/// `"ctrl-d"` or `"shift-enter"` in config file, and shifted chars as typed,
+/// like `"T"` or `"*"`. Keys in [`KeyBind::is_fixed`] can't be bound
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, SmartDefault)]
+#[serde(default)]
+pub struct KeyMap {
+    #[default(_code = "vec![KeyBind::code(KeyCode::Up)]")]
+    pub move_up: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::code(KeyCode::Down)]")]
+    pub move_down: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char(' ')]")]
+    pub expand: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::code(KeyCode::Enter)]")]
+    pub select: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('t')]")]
+    pub test_delay: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::ctrl('t')]")]
+    pub test_member: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('/')]")]
+    pub search: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('q'), KeyBind::char('x')]")]
+    pub quit: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('T')]")]
+    pub test_all_latency: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('B')]")]
+    pub test_and_select_fastest: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('[')]")]
+    pub older_sample: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char(']')]")]
+    pub newer_sample: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('+')]")]
+    pub increase_test_timeout: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('-')]")]
+    pub decrease_test_timeout: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('f')]")]
+    pub search_all_groups: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('n')]")]
+    pub next_match: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('c')]")]
+    pub jump_to_current: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('d')]")]
+    pub toggle_delay_sort: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('h')]")]
+    pub toggle_hide_dead: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('v')]")]
+    pub toggle_compact: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('V')]")]
+    pub toggle_single_line_groups: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('*')]")]
+    pub toggle_pin: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('F')]")]
+    pub next_group_filter: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('S')]")]
+    pub next_group_sort: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('a')]")]
+    pub edit_alias: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('i')]")]
+    pub show_group_stats: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('Y')]")]
+    pub show_selections: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('g')]")]
+    pub flip_global_direct: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('>')]")]
+    pub select_next_member: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('<')]")]
+    pub select_previous_member: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('u')]")]
+    pub update_provider: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('y')]")]
+    pub copy_name: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('o')]")]
+    pub open_dashboard: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('e')]")]
+    pub export_proxies: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('L')]")]
+    pub toggle_log_recording: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('w')]")]
+    pub match_rule: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('l')]")]
+    pub next_log_level: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('m')]")]
+    pub next_mode: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('r')]")]
+    pub reload_config: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('p')]")]
+    pub pick_server: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('R')]")]
+    pub reconnect: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char('P')]")]
+    pub toggle_pause: Vec<KeyBind>,
+    #[default(_code = "vec![KeyBind::char(':'), KeyBind::ctrl('p')]")]
+    pub open_palette: Vec<KeyBind>,
+}
+
+impl KeyMap {
+    pub fn binds(&self, action: KeyAction) -> &[KeyBind] {
+        match action {
+            KeyAction::MoveUp => &self.move_up,
+            KeyAction::MoveDown => &self.move_down,
+            KeyAction::Expand => &self.expand,
+            KeyAction::Select => &self.select,
+            KeyAction::TestDelay => &self.test_delay,
+            KeyAction::TestMember => &self.test_member,
+            KeyAction::Search => &self.search,
+            KeyAction::Quit => &self.quit,
+            KeyAction::TestAllLatency => &self.test_all_latency,
+            KeyAction::TestAndSelectFastest => &self.test_and_select_fastest,
+            KeyAction::OlderSample => &self.older_sample,
+            KeyAction::NewerSample => &self.newer_sample,
+            KeyAction::IncreaseTestTimeout => &self.increase_test_timeout,
+            KeyAction::DecreaseTestTimeout => &self.decrease_test_timeout,
+            KeyAction::SearchAllGroups => &self.search_all_groups,
+            KeyAction::NextMatch => &self.next_match,
+            KeyAction::JumpToCurrent => &self.jump_to_current,
+            KeyAction::ToggleDelaySort => &self.toggle_delay_sort,
+            KeyAction::ToggleHideDead => &self.toggle_hide_dead,
+            KeyAction::ToggleCompact => &self.toggle_compact,
+            KeyAction::ToggleSingleLineGroups => &self.toggle_single_line_groups,
+            KeyAction::TogglePin => &self.toggle_pin,
+            KeyAction::NextGroupFilter => &self.next_group_filter,
+            KeyAction::NextGroupSort => &self.next_group_sort,
+            KeyAction::EditAlias => &self.edit_alias,
+            KeyAction::ShowGroupStats => &self.show_group_stats,
+            KeyAction::ShowSelections => &self.show_selections,
+            KeyAction::FlipGlobalDirect => &self.flip_global_direct,
+            KeyAction::SelectNextMember => &self.select_next_member,
+            KeyAction::SelectPreviousMember => &self.select_previous_member,
+            KeyAction::UpdateProvider => &self.update_provider,
+            KeyAction::CopyName => &self.copy_name,
+            KeyAction::OpenDashboard => &self.open_dashboard,
+            KeyAction::ExportProxies => &self.export_proxies,
+            KeyAction::ToggleLogRecording => &self.toggle_log_recording,
+            KeyAction::MatchRule => &self.match_rule,
+            KeyAction::NextLogLevel => &self.next_log_level,
+            KeyAction::NextMode => &self.next_mode,
+            KeyAction::ReloadConfig => &self.reload_config,
+            KeyAction::PickServer => &self.pick_server,
+            KeyAction::Reconnect => &self.reconnect,
+            KeyAction::TogglePause => &self.toggle_pause,
+            KeyAction::OpenPalette => &self.open_palette,
+        }
+    }
+
+    /// Find the action bound to `key`. Moving actions also accept `ctrl` or
+    /// `shift` for moving fast
+    pub fn action_of(&self, key: &KeyEvent) -> Option<KeyAction> {
+        // Some terminals report shift with shifted chars and some don't
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        KeyAction::iter().find(|action| {
+            let fast = matches!(action, KeyAction::MoveUp | KeyAction::MoveDown)
+                && matches!(modifiers, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+            self.binds(*action).iter().any(|bind| {
+                bind.code == key.code
+                    && (bind.modifiers == modifiers
+                        || (fast && bind.modifiers == KeyModifiers::NONE))
+            })
+        })
+    }
+
+    /// Make sure no key is bound to more than one action, or is fixed
+    pub fn validate(&self) -> InteractiveResult<()> {
+        let all = KeyAction::iter()
+            .flat_map(|action| self.binds(action).iter().map(move |bind| (action, bind)))
+            .collect::<Vec<_>>();
+        for (i, (action, bind)) in all.iter().enumerate() {
+            if bind.is_fixed() {
+                return Err(InteractiveError::ConfigValueError(format!(
+                    "key `{}` of {} is fixed and can't be bound",
+                    bind, action
+                )));
+            }
+            if let Some((other, _)) = all[i + 1..].iter().find(|(_, x)| x == bind) {
+                if other != action {
+                    return Err(InteractiveError::ConfigValueError(format!(
+                        "key `{}` is bound to both {} and {}",
+                        bind, action, other
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBind {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBind {
+    pub const fn code(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub const fn char(c: char) -> Self {
+        Self::code(KeyCode::Char(c))
+    }
+
+    pub const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    /// Whether it's handled before [`KeyMap`] is looked up: tab numbers,
+    /// `esc`, `left` & `right`, sorting (`s` & `alt-s`), `z` starting chords
+    /// like `za`, `ctrl-d` for debug and `ctrl-c`
+    pub fn is_fixed(&self) -> bool {
+        match (self.modifiers, self.code) {
+            (KeyModifiers::NONE, KeyCode::Char(c)) => c == 's' || c == 'z' || c.is_ascii_digit(),
+            (KeyModifiers::NONE, KeyCode::Esc) => true,
+            (KeyModifiers::ALT, KeyCode::Char('s')) => true,
+            (KeyModifiers::CONTROL, KeyCode::Char('c' | 'd')) => true,
+            (_, KeyCode::Left | KeyCode::Right) => true,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for KeyBind {
+    type Err = InteractiveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InteractiveError::ConfigValueError(format!("`{}` is not a valid key", s));
+
+        // Split off modifiers, but keep a lone `-` as the key itself
+        let (mods, key) = match s.rfind('-') {
+            Some(pos) if pos + 1 < s.len() => (&s[..pos], &s[pos + 1..]),
+            _ => ("", s),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in mods.split('-').filter(|x| !x.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(err()),
+            }
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f if f.len() > 1 && f.starts_with('f') => {
+                KeyCode::F(f[1..].parse().map_err(|_| err())?)
+            }
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(err()),
+                }
+            }
+        };
+
+        // Shifted chars are reported as typed, see [`KeyMap::action_of`]
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                if !c.is_alphabetic() {
+                    return Err(InteractiveError::ConfigValueError(format!(
+                        "`{}` is not a valid key, write shifted char as typed instead",
+                        s
+                    )));
+                }
+                Ok(Self {
+                    code: KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                })
+            }
+            _ => Ok(Self { code, modifiers }),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyBind {
+    type Error = InteractiveError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyBind> for String {
+    fn from(val: KeyBind) -> Self {
+        val.to_string()
+    }
+}
+
+impl Display for KeyBind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?
+            }
+        }
+        match self.code {
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_keymap() {
+    let bind = |s: &str| s.parse::<KeyBind>().unwrap();
+    assert_eq!(bind("k"), KeyBind::char('k'));
+    assert_eq!(bind("-"), KeyBind::char('-'));
+    assert_eq!(
+        bind("ctrl-alt-up"),
+        KeyBind {
+            code: KeyCode::Up,
+            modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT
+        }
+    );
+    assert_eq!(bind("ctrl-alt-up").to_string(), "ctrl-alt-up");
+    assert_eq!(bind("f5").code, KeyCode::F(5));
+    assert!("hyper-k".parse::<KeyBind>().is_err());
+    assert!("kk".parse::<KeyBind>().is_err());
+    assert_eq!(bind("shift-t"), KeyBind::char('T'));
+    assert_eq!(bind("ctrl-shift-t").to_string(), "ctrl-T");
+    assert!("shift-1".parse::<KeyBind>().is_err());
+
+    let keymap = ron::from_str::<KeyMap>(r#"(move_up: ["up", "k"])"#).unwrap();
+    assert!(keymap.validate().is_ok());
+    assert_eq!(keymap.quit, KeyMap::default().quit);
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Char('k'), KeyModifiers::NONE)),
+        Some(KeyAction::MoveUp)
+    );
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Up, KeyModifiers::SHIFT)),
+        Some(KeyAction::MoveUp)
+    );
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+        None
+    );
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Char('t'), KeyModifiers::CONTROL)),
+        Some(KeyAction::TestMember)
+    );
+
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Char('T'), KeyModifiers::SHIFT)),
+        Some(KeyAction::TestAllLatency)
+    );
+    assert_eq!(
+        keymap.action_of(&key(KeyCode::Char('T'), KeyModifiers::NONE)),
+        Some(KeyAction::TestAllLatency)
+    );
+    assert!(KeyMap::default().validate().is_ok());
+
+    let conflict = ron::from_str::<KeyMap>(r#"(search: ["t"])"#).unwrap();
+    assert!(conflict.validate().is_err());
+    let shifted = ron::from_str::<KeyMap>(r#"(search: ["shift-t"])"#).unwrap();
+    assert!(shifted.validate().is_err());
+    for fixed in ["s", "alt-s", "z", "ctrl-d", "3"] {
+        let keymap = ron::from_str::<KeyMap>(&format!(r#"(search: ["{}"])"#, fixed)).unwrap();
+        assert!(keymap.validate().is_err(), "{}", fixed);
+    }
+    let rebound = ron::from_str::<KeyMap>(r#"(pick_server: ["ctrl-s"], next_mode: ["p"])"#)
+        .unwrap();
+    assert!(rebound.validate().is_ok());
+    assert_eq!(
+        rebound.action_of(&key(KeyCode::Char('p'), KeyModifiers::NONE)),
+        Some(KeyAction::NextMode)
+    );
+}
@@ -1,3 +1,5 @@
 pub use clashctl_core as clashctl;
 
-mod_use::mod_use![flags, sort, error, config, config_model];
+mod_use::mod_use![
+    flags, sort, error, config, config_model, clash_config, keymap, theme, color_level
+];
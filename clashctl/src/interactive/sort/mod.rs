@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::VecDeque};
 
 use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
@@ -106,6 +106,18 @@ where
     }
 }
 
+impl<'a, T, M> Sortable<'a, M> for VecDeque<T>
+where
+    M: SortMethod<T>,
+{
+    type Item<'b> = T;
+
+    #[inline]
+    fn sort_with(&mut self, method: &M) {
+        self.make_contiguous().sort_by(|a, b| method.sort_fn(a, b))
+    }
+}
+
 // #[macro_export]
 // macro_rules! endless {
 //     ( $ty:path = $from:ident => $( $to:ident $(=>)? )+ ) => {
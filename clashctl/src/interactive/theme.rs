@@ -0,0 +1,368 @@
+use std::{fmt::Display, str::FromStr};
+
+use clashctl_core::model::Level;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use tui::style::{Color, Modifier, Style};
+
+use crate::{ColorLevel, InteractiveError, InteractiveResult, LatencyThresholds};
+
+/// Built-in color sets of [`Theme`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    Solarized,
+}
+
+/// Theme in config file, a preset with some of its colors optionally
+/// overridden, like `(preset: light, colors: (low_latency: Some("#859900")))`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: ThemePreset,
+    pub colors: ThemeColors,
+}
+
+impl ThemeConfig {
+    pub fn theme(&self) -> Theme {
+        let mut theme = Theme::preset(self.preset);
+        theme.apply(&self.colors);
+        theme
+    }
+}
+
+/// Overrides of [`Theme`] colors, `None` for keeping the preset one
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ThemeColors {
+    pub group_name: Option<HexColor>,
+    pub member_name: Option<HexColor>,
+    pub current_member: Option<HexColor>,
+    pub pointed_member: Option<HexColor>,
+    pub proxy_type: Option<HexColor>,
+    pub no_latency: Option<HexColor>,
+    pub low_latency: Option<HexColor>,
+    pub mid_latency: Option<HexColor>,
+    pub high_latency: Option<HexColor>,
+    pub timeout: Option<HexColor>,
+    pub udp: Option<HexColor>,
+    pub search_match: Option<HexColor>,
+    pub testing: Option<HexColor>,
+    pub log_error: Option<HexColor>,
+    pub log_warning: Option<HexColor>,
+    pub log_info: Option<HexColor>,
+    pub log_debug: Option<HexColor>,
+}
+
+/// Colors used to render proxies and logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub group_name: Color,
+    pub member_name: Color,
+    pub current_member: Color,
+    pub pointed_member: Color,
+    pub proxy_type: Color,
+    pub no_latency: Color,
+    pub low_latency: Color,
+    pub mid_latency: Color,
+    pub high_latency: Color,
+    pub timeout: Color,
+    pub udp: Color,
+    pub search_match: Color,
+    pub testing: Color,
+    pub log_error: Color,
+    pub log_warning: Color,
+    // `Reset` for default color of terminal
+    pub log_info: Color,
+    pub log_debug: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset(ThemePreset::Dark)
+    }
+}
+
+impl Theme {
+    pub const fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Dark => Self {
+                group_name: Color::White,
+                member_name: Color::White,
+                current_member: Color::Blue,
+                pointed_member: Color::LightBlue,
+                proxy_type: Color::DarkGray,
+                no_latency: Color::DarkGray,
+                low_latency: Color::LightGreen,
+                mid_latency: Color::LightYellow,
+                high_latency: Color::LightRed,
+                timeout: Color::Red,
+                udp: Color::Blue,
+                search_match: Color::LightYellow,
+                testing: Color::LightYellow,
+                log_error: Color::Red,
+                log_warning: Color::Yellow,
+                log_info: Color::Reset,
+                log_debug: Color::DarkGray,
+            },
+            ThemePreset::Light => Self {
+                group_name: Color::Black,
+                member_name: Color::Black,
+                current_member: Color::Blue,
+                pointed_member: Color::Cyan,
+                proxy_type: Color::Gray,
+                no_latency: Color::Gray,
+                low_latency: Color::Green,
+                mid_latency: Color::Rgb(0xaf, 0x87, 0x00),
+                high_latency: Color::Red,
+                timeout: Color::Red,
+                udp: Color::Blue,
+                search_match: Color::Magenta,
+                testing: Color::Rgb(0xaf, 0x87, 0x00),
+                log_error: Color::Red,
+                log_warning: Color::Rgb(0xaf, 0x87, 0x00),
+                log_info: Color::Reset,
+                log_debug: Color::Gray,
+            },
+            ThemePreset::Solarized => Self {
+                group_name: Color::Rgb(0x93, 0xa1, 0xa1),
+                member_name: Color::Rgb(0x83, 0x94, 0x96),
+                current_member: Color::Rgb(0x26, 0x8b, 0xd2),
+                pointed_member: Color::Rgb(0x2a, 0xa1, 0x98),
+                proxy_type: Color::Rgb(0x58, 0x6e, 0x75),
+                no_latency: Color::Rgb(0x58, 0x6e, 0x75),
+                low_latency: Color::Rgb(0x85, 0x99, 0x00),
+                mid_latency: Color::Rgb(0xb5, 0x89, 0x00),
+                high_latency: Color::Rgb(0xdc, 0x32, 0x2f),
+                timeout: Color::Rgb(0xdc, 0x32, 0x2f),
+                udp: Color::Rgb(0x6c, 0x71, 0xc4),
+                search_match: Color::Rgb(0xd3, 0x36, 0x82),
+                testing: Color::Rgb(0xb5, 0x89, 0x00),
+                log_error: Color::Rgb(0xdc, 0x32, 0x2f),
+                log_warning: Color::Rgb(0xb5, 0x89, 0x00),
+                log_info: Color::Rgb(0x83, 0x94, 0x96),
+                log_debug: Color::Rgb(0x58, 0x6e, 0x75),
+            },
+        }
+    }
+
+    pub fn apply(&mut self, colors: &ThemeColors) {
+        let overrides = [
+            (&mut self.group_name, colors.group_name),
+            (&mut self.member_name, colors.member_name),
+            (&mut self.current_member, colors.current_member),
+            (&mut self.pointed_member, colors.pointed_member),
+            (&mut self.proxy_type, colors.proxy_type),
+            (&mut self.no_latency, colors.no_latency),
+            (&mut self.low_latency, colors.low_latency),
+            (&mut self.mid_latency, colors.mid_latency),
+            (&mut self.high_latency, colors.high_latency),
+            (&mut self.timeout, colors.timeout),
+            (&mut self.udp, colors.udp),
+            (&mut self.search_match, colors.search_match),
+            (&mut self.testing, colors.testing),
+            (&mut self.log_error, colors.log_error),
+            (&mut self.log_warning, colors.log_warning),
+            (&mut self.log_info, colors.log_info),
+            (&mut self.log_debug, colors.log_debug),
+        ];
+        for (color, value) in overrides {
+            if let Some(HexColor(value)) = value {
+                *color = value;
+            }
+        }
+    }
+
+    /// Colors mapped to the nearest ones terminals of `level` can show.
+    /// Latency bands fall back to green, yellow and red if any two of them
+    /// end up the same
+    pub fn downgrade(mut self, level: ColorLevel) -> Self {
+        for color in [
+            &mut self.group_name,
+            &mut self.member_name,
+            &mut self.current_member,
+            &mut self.pointed_member,
+            &mut self.proxy_type,
+            &mut self.no_latency,
+            &mut self.low_latency,
+            &mut self.mid_latency,
+            &mut self.high_latency,
+            &mut self.timeout,
+            &mut self.udp,
+            &mut self.search_match,
+            &mut self.testing,
+            &mut self.log_error,
+            &mut self.log_warning,
+            &mut self.log_info,
+            &mut self.log_debug,
+        ] {
+            *color = level.downgrade(*color);
+        }
+        let (low, mid, high) = (self.low_latency, self.mid_latency, self.high_latency);
+        if low == mid || mid == high || low == high {
+            self.low_latency = Color::Green;
+            self.mid_latency = Color::Yellow;
+            self.high_latency = Color::Red;
+        }
+        self
+    }
+
+    pub fn group_name_style(&self) -> Style {
+        Style::default()
+            .fg(self.group_name)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn member_name_style(&self, current: bool, pointed: bool) -> Style {
+        if current {
+            Style::default()
+                .fg(self.current_member)
+                .add_modifier(Modifier::BOLD)
+        } else if pointed {
+            Style::default().fg(self.pointed_member)
+        } else {
+            Style::default().fg(self.member_name)
+        }
+    }
+
+    pub fn proxy_type_style(&self) -> Style {
+        Style::default().fg(self.proxy_type)
+    }
+
+    pub fn no_latency_style(&self) -> Style {
+        Style::default().fg(self.no_latency)
+    }
+
+    pub fn timeout_style(&self) -> Style {
+        Style::default().fg(self.timeout)
+    }
+
+    pub fn udp_style(&self) -> Style {
+        Style::default().fg(self.udp).add_modifier(Modifier::BOLD)
+    }
+
+    pub fn search_match_style(&self) -> Style {
+        Style::default()
+            .fg(self.search_match)
+            .add_modifier(Modifier::UNDERLINED)
+    }
+
+    /// Patched onto current member just changed elsewhere, keeping its colors
+    pub fn changed_style(&self) -> Style {
+        Style::default().add_modifier(Modifier::REVERSED)
+    }
+
+    pub fn testing_style(&self) -> Style {
+        Style::default().fg(self.testing)
+    }
+
+    /// Style of logs of `level`, both the tag and the payload
+    pub fn log_style(&self, level: &Level) -> Style {
+        Style::default().fg(match level {
+            Level::Error => self.log_error,
+            Level::Warning => self.log_warning,
+            Level::Info => self.log_info,
+            Level::Debug => self.log_debug,
+        })
+    }
+
+    /// Style of `delay` (in ms), `0` for failed tests
+    pub fn delay_style(&self, delay: u64, thresholds: &LatencyThresholds) -> Style {
+        Style::default().fg(match delay {
+            0 => self.no_latency,
+            x if x <= thresholds.low => self.low_latency,
+            x if x <= thresholds.mid => self.mid_latency,
+            _ => self.high_latency,
+        })
+    }
+}
+
+/// Color written as `"#rrggbb"` in config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexColor(pub Color);
+
+impl FromStr for HexColor {
+    type Err = InteractiveError;
+
+    fn from_str(s: &str) -> InteractiveResult<Self> {
+        let err = || {
+            InteractiveError::ConfigValueError(format!("`{}` is not a valid hex color", s))
+        };
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(err());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err());
+        Ok(Self(Color::Rgb(channel(0)?, channel(2)?, channel(4)?)))
+    }
+}
+
+impl TryFrom<String> for HexColor {
+    type Error = InteractiveError;
+
+    fn try_from(value: String) -> InteractiveResult<Self> {
+        value.parse()
+    }
+}
+
+impl Display for HexColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl From<HexColor> for String {
+    fn from(value: HexColor) -> Self {
+        value.to_string()
+    }
+}
+
+#[test]
+fn test_theme() {
+    assert_eq!(
+        "#859900".parse::<HexColor>().unwrap(),
+        HexColor(Color::Rgb(0x85, 0x99, 0x00))
+    );
+    assert_eq!(
+        "DC322F".parse::<HexColor>().unwrap(),
+        HexColor(Color::Rgb(0xdc, 0x32, 0x2f))
+    );
+    for invalid in ["", "#fff", "#12345g", "#1234567", "#ü1234"] {
+        assert!(invalid.parse::<HexColor>().is_err(), "{}", invalid);
+    }
+
+    let default = ron::from_str::<ThemeConfig>("()").unwrap();
+    assert_eq!(default.theme(), Theme::default());
+    assert_eq!(default.theme().low_latency, Color::LightGreen);
+
+    let config =
+        ron::from_str::<ThemeConfig>(r##"(preset: solarized, colors: (udp: Some("#ffffff")))"##)
+            .unwrap();
+    let theme = config.theme();
+    assert_eq!(config.preset, ThemePreset::Solarized);
+    assert_eq!(theme.udp, Color::Rgb(0xff, 0xff, 0xff));
+    assert_eq!(theme.low_latency, Color::Rgb(0x85, 0x99, 0x00));
+
+    assert!(ron::from_str::<ThemeConfig>(r#"(colors: (udp: Some("blue")))"#).is_err());
+
+    // Solarized green & yellow are both closest to yellow out of 16 colors
+    let solarized = Theme::preset(ThemePreset::Solarized);
+    assert_eq!(solarized.downgrade(ColorLevel::TrueColor), solarized);
+    let limited = solarized.downgrade(ColorLevel::Ansi16);
+    assert_eq!(
+        (limited.low_latency, limited.mid_latency, limited.high_latency),
+        (Color::Green, Color::Yellow, Color::Red)
+    );
+    assert_eq!(limited.udp, Color::LightBlue);
+
+    let theme = Theme::default();
+    assert_eq!(theme.log_style(&Level::Error).fg, Some(Color::Red));
+    assert_eq!(theme.log_style(&Level::Info).fg, Some(Color::Reset));
+}
@@ -74,8 +74,8 @@ impl RenderList for Proxies {
         let list = self
             .iter()
             .filter(|x| {
-                let proxy_type = x.1.proxy_type;
-                proxy_type.is_group() && !opt.exclude.contains(&proxy_type)
+                let proxy_type = &x.1.proxy_type;
+                proxy_type.is_group() && !opt.exclude.contains(proxy_type)
             })
             .collect::<Vec<_>>();
 
@@ -1,5 +1,61 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use clashctl_core::model::Mode;
+use url::Url;
+
 #[derive(Debug, Clone)]
 pub enum Action {
-    TestLatency { proxies: Vec<String> },
+    // Test `proxies` one by one rather than as a group, `url` overrides the
+    // global test url. Proxies slower than `timeout` ms fail
+    TestLatency {
+        proxies: Vec<String>,
+        url: Option<String>,
+        timeout: u64,
+    },
+    // `url` overrides the global test url for this group. With
+    // `select_fastest`, the fastest of `proxies` is selected afterwards
+    TestGroupLatency {
+        group: String,
+        proxies: Vec<String>,
+        url: Option<String>,
+        timeout: u64,
+        select_fastest: bool,
+    },
+    // Test groups one by one, stop before next group once `cancel` is set
+    TestAllGroupsLatency {
+        groups: Vec<(String, Vec<String>)>,
+        cancel: Arc<AtomicBool>,
+        timeout: u64,
+    },
     ApplySelection { group: String, proxy: String },
+    // Update all updatable providers that back any of `proxies`
+    UpdateProviders { group: String, proxies: Vec<String> },
+    // Reload config file Clash was started with
+    ReloadConfig,
+    SetMode(Mode),
+    // Switch to the configured server with this url
+    UseServer(Url),
+    // Stop or resume polling the server, refreshing at once when resumed
+    SetPaused(bool),
+    // Rebuild client of the server in use, reopen streams and refetch states
+    Reconnect,
+}
+
+impl Action {
+    /// Whether this action changes state of the server, disabled in
+    /// read-only mode. Latency tests only measure and are allowed, unless
+    /// they select the fastest proxy
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Action::ApplySelection { .. }
+                | Action::TestGroupLatency {
+                    select_fastest: true,
+                    ..
+                }
+                | Action::UpdateProviders { .. }
+                | Action::ReloadConfig
+                | Action::SetMode(_)
+        )
+    }
 }
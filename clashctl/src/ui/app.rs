@@ -2,20 +2,25 @@ use std::{
     cell::RefCell,
     fs::OpenOptions,
     io::{self, Stdout},
-    sync::{mpsc::channel, Arc, Mutex, RwLock},
-    thread::spawn,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex, RwLock,
+    },
+    thread::{sleep, spawn},
     time::{Duration, Instant},
 };
 
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use log::{info, warn};
+use log::{debug, info, warn};
 use owo_colors::OwoColorize;
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
     Frame, Terminal,
 };
 
@@ -24,8 +29,13 @@ use crate::{
     interactive::Flags,
     servo,
     ui::{
-        components::Tabs, get_config, init_config, pages::route, Interval, LoggerBuilder,
-        TicksCounter, TuiOpt, TuiResult, TuiStates,
+        components::{
+            CommandPalette, GroupStatsPopup, SelectionsPopup, ServerPicker, StatusLine, Tabs,
+        },
+        get_config, init_config,
+        pages::route,
+        bind_serve, clamp_delay_timeout, poll_interval, serve_job, Event, Interval, LoggerBuilder,
+        PersistState, PollHealth, TicksCounter, TuiOpt, TuiResult, TuiStates,
     },
 };
 
@@ -33,10 +43,14 @@ thread_local!(pub(crate) static TICK_COUNTER: RefCell<TicksCounter> = RefCell::n
 
 pub type Backend = CrosstermBackend<Stdout>;
 
+/// How long to wait for servo to close streams and finish requests on quit,
+/// it's abandoned after that
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
 fn setup() -> TuiResult<Terminal<Backend>> {
     let mut stdout = io::stdout();
 
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     enable_raw_mode()?;
 
     let backend = CrosstermBackend::new(stdout);
@@ -47,16 +61,20 @@ fn setup() -> TuiResult<Terminal<Backend>> {
 }
 
 fn wrap_up(mut terminal: Terminal<Backend>) -> TuiResult<()> {
-    execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
 
     disable_raw_mode()?;
 
     Ok(())
 }
 
-pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
+pub fn main_loop(opt: TuiOpt, mut flag: Flags) -> TuiResult<()> {
     let config = flag.get_config()?;
-    if config.using_server().is_none() {
+    if flag.using_server(&config).is_err() {
         println!(
             "{} No API server configured yet. Use this command to add a server:\n\n  $ {}",
             "WARN:".red(),
@@ -65,16 +83,51 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
         return Ok(());
     };
 
+    config.tui.latency.validate()?;
+    config.tui.keymap.validate()?;
+    config.validate_test_urls()?;
+    if let Some(ref layout) = config.tui.layout {
+        layout.validate()?;
+    }
+
+    // Traces on stderr would mess up the screen
+    if flag.trace && flag.trace_file.is_none() {
+        flag.trace_file = flag.trace_path();
+        flag.trace = flag.trace_file.is_some();
+    }
+
+    let delay_timeout = clamp_delay_timeout(config.tui.delay_timeout.unwrap_or(flag.timeout));
     init_config(config);
 
-    let state = Arc::new(RwLock::new(TuiStates::default()));
+    let state_path = flag.state_path();
+    let mut state = TuiStates {
+        read_only: opt.read_only,
+        delay_timeout,
+        poll_health: PollHealth::new(poll_interval(&opt)),
+        ..Default::default()
+    };
+    if let Some(ref path) = state_path {
+        let persisted = PersistState::load(path);
+        state.proxy_tree.restore_later(persisted.proxy_tree);
+    }
+    let state = Arc::new(RwLock::new(state));
     let error = Arc::new(Mutex::new(None));
 
+    // Bound before taking over the terminal so failures are printed plainly
+    if let Some(listener) = opt.serve.map(bind_serve).transpose()? {
+        let serve_state = state.clone();
+        spawn(move || serve_job(listener, serve_state));
+    }
+
     let (event_tx, event_rx) = channel();
     let (action_tx, action_rx) = channel();
+    // Wakes render loop up early to redraw
+    let (redraw_tx, redraw_rx) = channel();
 
     let servo_event_tx = event_tx.clone();
-    let servo = spawn(|| servo(servo_event_tx, action_rx, opt, flag));
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let servo_shutdown = shutdown.clone();
+    let servo = spawn(|| servo(servo_event_tx, action_rx, opt, flag, servo_shutdown));
 
     LoggerBuilder::new(event_tx)
         .file(get_config().tui.log_file.as_ref().map(|x| {
@@ -94,6 +147,7 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
         let mut should_quit;
         while let Ok(event) = event_rx.recv() {
             should_quit = event.is_quit();
+            let resized = matches!(event, Event::Resize(..));
             let mut state = event_handler_state.write().unwrap();
             match state.handle(event) {
                 Ok(Some(action)) => {
@@ -109,9 +163,14 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
                     should_quit = true;
                 }
             }
-            if should_quit {
+            if should_quit || state.should_quit {
                 break;
             }
+            drop(state);
+            // Render loop may have quit already
+            if resized {
+                let _ = redraw_tx.send(());
+            }
         }
         event_handler_state
             .write()
@@ -122,7 +181,8 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
     let mut terminal = setup()?;
 
     let mut interval = Interval::every(Duration::from_millis(33));
-    while let Ok(state) = state.read() {
+    let mut size = None;
+    while let Ok(mut state) = state.write() {
         if handle.is_finished() {
             info!("State handler quit");
             break;
@@ -130,15 +190,6 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
 
         if servo.is_finished() {
             info!("Servo quit");
-            match servo.join() {
-                Err(_) => {
-                    warn!("Servo panicked");
-                }
-                Ok(Err(e)) => {
-                    warn!("TUI error ({e})");
-                }
-                _ => {}
-            }
             break;
         }
 
@@ -147,19 +198,67 @@ pub fn main_loop(opt: TuiOpt, flag: Flags) -> TuiResult<()> {
             break;
         }
 
+        // Resize to what's reported by the event right away, lines of last
+        // size would otherwise be drawn over the new ones
+        if let Some((columns, rows)) = state.terminal_size.filter(|x| Some(*x) != size) {
+            size = state.terminal_size;
+            if let Err(e) = terminal.resize(Rect::new(0, 0, columns, rows)) {
+                error.lock().unwrap().replace(e.into());
+                break;
+            }
+        }
+
+        state.flush_updates();
+        state.flush_log_record();
         TICK_COUNTER.with(|t| t.borrow_mut().new_tick());
         if let Err(e) = terminal.draw(|f| render(&state, f)) {
             error.lock().unwrap().replace(e.into());
             break;
         }
         drop(state);
-        interval.tick();
+        // Sleep till next tick unless a redraw is requested
+        let _ = redraw_rx.recv_timeout(interval.next_tick());
     }
 
     drop(handle);
 
     wrap_up(terminal)?;
 
+    // Errors of servo quitting by itself are real, the rest are mostly
+    // channels closed during shutdown
+    let servo_quit = servo.is_finished();
+    // Streams are closed instead of being dropped with the process
+    shutdown.store(true, Ordering::Release);
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while !servo.is_finished() && Instant::now() < deadline {
+        sleep(Duration::from_millis(10));
+    }
+    if servo.is_finished() {
+        match servo.join() {
+            Err(_) => {
+                warn!("Servo panicked");
+            }
+            Ok(Err(e)) if servo_quit => {
+                warn!("TUI error ({e})");
+            }
+            Ok(Err(e)) => {
+                debug!("Servo stopped with error ({e})");
+            }
+            _ => {}
+        }
+    } else {
+        warn!("Servo didn't stop in {:?}, quit anyway", SHUTDOWN_TIMEOUT);
+    }
+
+    if let (Some(path), Ok(state)) = (state_path, state.read()) {
+        let persisted = PersistState {
+            proxy_tree: state.proxy_tree.snapshot(),
+        };
+        if let Err(e) = persisted.save(&path) {
+            warn!("Failed to save state @ {} ({})", path.display(), e);
+        }
+    }
+
     if let Some(error) = error.lock().unwrap().take() {
         return Err(error);
     }
@@ -175,7 +274,29 @@ fn render(state: &TuiStates, f: &mut Frame<Backend>) {
     let tabs = Tabs::new(state);
     f.render_widget(tabs, layout[0]);
 
-    let main = layout[1];
+    let mut main = layout[1];
+    if state.status.current(Instant::now()).is_some() && main.height > 1 {
+        main.height -= 1;
+        let line = Rect {
+            y: main.y + main.height,
+            height: 1,
+            ..main
+        };
+        f.render_widget(StatusLine::new(state), line);
+    }
 
     route(state, main, f);
+
+    if state.server_picker.is_some() {
+        f.render_widget(ServerPicker::new(state), main);
+    }
+    if state.show_group_stats {
+        f.render_widget(GroupStatsPopup::new(state), main);
+    }
+    if state.show_selections {
+        f.render_widget(SelectionsPopup::new(state), main);
+    }
+    if state.palette.is_some() {
+        f.render_widget(CommandPalette::new(state), main);
+    }
 }
@@ -14,6 +14,40 @@ impl Consts {
 
     pub const NO_LATENCY_SIGN: &'static str = "⊝";
 
+    pub const TIMEOUT_SIGN: &'static str = "✗";
+
+    pub const UDP_SIGN: &'static str = "U";
+
+    pub const PINNED_SIGN: &'static str = "★";
+
+    pub const SPINNER_FRAMES: [&'static str; 10] =
+        ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+    pub const HIDE_DEAD_SIGN: &'static str = "alive only";
+
+    pub const EMPTY_GROUP_SIGN: &'static str = "(empty)";
+
+    pub const TESTING_SIGN: &'static str = "testing…";
+
+    pub const LOADING_SIGN: &'static str = "Loading proxies…";
+
+    pub const LOG_ERROR_TAG: &'static str = "ERR";
+
+    pub const LOG_WARNING_TAG: &'static str = "WRN";
+
+    pub const LOG_INFO_TAG: &'static str = "INF";
+
+    pub const LOG_DEBUG_TAG: &'static str = "DBG";
+
+    pub const SCROLLBAR_TRACK: &'static str = "│";
+
+    pub const SCROLLBAR_THUMB: &'static str = "┃";
+
+    pub const DELAY_HISTORY_BARS: [&'static str; 8] =
+        ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+    pub const DELAY_HISTORY_FAILED: &'static str = "·";
+
     pub const FOCUSED_INDICATOR: &'static str = "🮇  ";
 
     pub const FOCUSED_EXPANDED_INDICATOR: &'static str = "🮇   ";
@@ -29,62 +63,16 @@ impl Consts {
         bg: None,
     };
 
-    pub const PROXY_TYPE_STYLE: Style = Style {
-        fg: Some(Color::DarkGray),
-        add_modifier: Modifier::empty(),
-        ..Self::DEFAULT_STYLE
-    };
-
-    pub const NO_LATENCY_STYLE: Style = Style {
+    pub const SCROLLBAR_STYLE: Style = Style {
         fg: Some(Color::DarkGray),
         ..Self::DEFAULT_STYLE
     };
 
-    pub const LOW_LATENCY_STYLE: Style = Style {
-        fg: Some(Color::LightGreen),
-        ..Self::DEFAULT_STYLE
-    };
-
-    pub const MID_LATENCY_STYLE: Style = Style {
-        fg: Some(Color::LightYellow),
-        ..Self::DEFAULT_STYLE
-    };
-
-    pub const HIGH_LATENCY_STYLE: Style = Style {
-        fg: Some(Color::LightRed),
-        ..Self::DEFAULT_STYLE
-    };
-
     pub const DELIMITER_SPAN: Span<'static> = Span {
         content: Cow::Borrowed(" "),
         style: Self::DEFAULT_STYLE,
     };
 
-    pub const NOT_PROXY_SPAN: Span<'static> = Span {
-        content: Cow::Borrowed(Self::NOT_PROXY_SIGN),
-        style: Self::NO_LATENCY_STYLE,
-    };
-
-    pub const NO_LATENCY_SPAN: Span<'static> = Span {
-        content: Cow::Borrowed(Self::PROXY_LATENCY_SIGN),
-        style: Self::NO_LATENCY_STYLE,
-    };
-
-    pub const LOW_LATENCY_SPAN: Span<'static> = Span {
-        content: Cow::Borrowed(Self::PROXY_LATENCY_SIGN),
-        style: Self::LOW_LATENCY_STYLE,
-    };
-
-    pub const MID_LATENCY_SPAN: Span<'static> = Span {
-        content: Cow::Borrowed(Self::PROXY_LATENCY_SIGN),
-        style: Self::MID_LATENCY_STYLE,
-    };
-
-    pub const HIGH_LATENCY_SPAN: Span<'static> = Span {
-        content: Cow::Borrowed(Self::PROXY_LATENCY_SIGN),
-        style: Self::HIGH_LATENCY_STYLE,
-    };
-
     pub const FOCUSED_INDICATOR_SPAN: Span<'static> = Span {
         content: Cow::Borrowed(Self::FOCUSED_INDICATOR),
         style: Style {
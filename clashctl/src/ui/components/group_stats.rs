@@ -0,0 +1,80 @@
+use tui::{
+    layout::Rect,
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph, Widget},
+};
+
+use crate::ui::{define_widget, utils::get_focused_block};
+
+/// Width of the longest bar, for the band with most members
+const BAR_WIDTH: usize = 20;
+
+define_widget!(GroupStatsPopup);
+
+/// Popup of latency bands of current group, drawn in the middle of `area`
+impl<'a> Widget for GroupStatsPopup<'a> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        let tree = &self.state.proxy_tree;
+        let (name, stats) = match tree.current_stats() {
+            Some(x) => x,
+            None => return,
+        };
+        let thresholds = tree.thresholds();
+        let theme = tree.theme();
+
+        let bands = [
+            (
+                format!("≤ {}ms", thresholds.low),
+                stats.low,
+                theme.delay_style(thresholds.low, thresholds),
+            ),
+            (
+                format!("≤ {}ms", thresholds.mid),
+                stats.mid,
+                theme.delay_style(thresholds.mid, thresholds),
+            ),
+            (
+                format!("> {}ms", thresholds.mid),
+                stats.high,
+                theme.delay_style(thresholds.mid + 1, thresholds),
+            ),
+            ("Timeout".to_owned(), stats.timeout, theme.timeout_style()),
+            ("Untested".to_owned(), stats.untested, theme.no_latency_style()),
+        ];
+        let max = bands.iter().map(|x| x.1).max().unwrap_or_default().max(1);
+        let label_width = bands.iter().map(|x| x.0.chars().count()).max().unwrap_or_default();
+
+        let mut lines = bands
+            .into_iter()
+            .map(|(label, count, style)| {
+                let bar = (count * BAR_WIDTH).div_ceil(max);
+                Spans::from(vec![
+                    Span::styled(format!("{:<width$} ", label, width = label_width), style),
+                    Span::styled("█".repeat(bar), style),
+                    Span::raw(format!(" {}", count)),
+                ])
+            })
+            .collect::<Vec<_>>();
+        lines.push(Spans::from(Span::styled(
+            format!("{} proxies", stats.total()),
+            Style::default().fg(theme.proxy_type),
+        )));
+
+        let title = format!("{} · Esc to close", name);
+        let width = (label_width + BAR_WIDTH + 8).max(title.chars().count() + 4) as u16;
+        let width = width.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(get_focused_block(&title))
+            .render(popup, buf);
+    }
+}
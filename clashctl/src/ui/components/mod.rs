@@ -1,9 +1,14 @@
 mod_use::mod_use![
     block_footer,
     constants,
+    group_stats,
     movable_list,
+    palette,
     proxy,
+    selections,
+    server_picker,
     sparkline,
+    status_line,
     tabs,
     traffic
 ];
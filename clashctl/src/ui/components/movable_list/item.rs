@@ -4,6 +4,12 @@ use tui::text::{Span, Spans};
 
 pub trait MovableListItem<'a> {
     fn to_spans(&self) -> Spans<'a>;
+
+    /// Columns to indent lines after the first one when the item is wrapped,
+    /// see [`crate::components::MovableListState::wrap_lines`]
+    fn wrap_indent(&self) -> usize {
+        0
+    }
 }
 
 impl<'a> MovableListItem<'a> for Spans<'a> {
@@ -1,11 +1,15 @@
 use std::{
     borrow::Cow,
+    collections::VecDeque,
     fmt::Debug,
     ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use crossterm::event::KeyCode;
-use match_any::match_any;
 use paste::paste;
 use smart_default::SmartDefault;
 
@@ -15,8 +19,12 @@ use crate::{
         components::{MovableListItem, ProxyTree},
         utils::Coord,
     },
-    Action, ConListState, DebugListState, ListEvent, LogListState, RuleListState,
+    Action, DebugListState, ListEvent, RuleListState,
 };
+#[cfg(feature = "connections")]
+use crate::ConListState;
+#[cfg(feature = "logs")]
+use crate::LogListState;
 
 macro_rules! impl_setter {
     ($prop:ident, $ty:ty) => {
@@ -54,16 +62,18 @@ where
 
     impl_setter!(dsc_index, reverse_index, true);
 
-    impl_setter!(items, Vec<T>);
+    impl_setter!(wrap_lines, true);
 
     impl_setter!(padding, u16);
 
+    impl_setter!(capacity, Option<usize>);
+
     pub fn new(items: Vec<T>) -> Self
     where
         T: MovableListItem<'a>,
     {
         Self {
-            items,
+            items: items.into(),
             ..Default::default()
         }
     }
@@ -75,7 +85,7 @@ where
         items.sort_by(|a, b| sort.sort_fn(a, b));
 
         Self {
-            items,
+            items: items.into(),
             sort,
             ..Default::default()
         }
@@ -87,15 +97,42 @@ where
     }
 
     pub fn sorted_merge(&mut self, other: Vec<T>) {
-        self.items = other;
+        self.items = other.into();
         self.sort();
     }
 
     pub fn push(&mut self, item: T) {
-        self.items.push(item);
+        self.items.push_back(item);
         if self.offset.hold {
             self.offset.y += 1;
         }
+        if let Some(capacity) = self.capacity {
+            self.drop_oldest(self.items.len().saturating_sub(capacity));
+        }
+    }
+
+    /// Remove `num` items pushed first. Offset counts from the last item, so
+    /// it only moves when what it points to is gone
+    pub fn drop_oldest(&mut self, num: usize) {
+        if num == 0 {
+            return;
+        }
+        for _ in 0..num {
+            if self.items.pop_front().is_none() {
+                break;
+            }
+        }
+        self.offset.y = self.offset.y.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Number of items left by filter of last render, see
+    /// [`super::MovableList::filter`]
+    pub fn num_displayed(&self) -> usize {
+        self.items.len().saturating_sub(self.hidden.get())
     }
 }
 
@@ -103,21 +140,57 @@ where
 #[derive(Debug, Clone, PartialEq, Eq, SmartDefault)]
 pub struct MovableListState<'a, T: MovableListItem<'a>, S: Default> {
     pub(super) offset: Coord,
-    pub(super) items: Vec<T>,
+    pub(super) items: VecDeque<T>,
     pub(super) placeholder: Option<Cow<'a, str>>,
     #[default = 1]
     pub(super) padding: u16,
     pub(super) sort: S,
     pub(super) with_index: bool,
     pub(super) reverse_index: bool,
+    // Whether items wider than the list are wrapped instead of scrolled
+    // horizontally
+    pub(super) wrap_lines: bool,
+    // Max number of items kept, oldest ones are dropped first on push
+    pub(super) capacity: Option<usize>,
+    // Items fully shown by last render, so paging through wrapped items
+    // doesn't skip what's never been on screen
+    pub(super) shown: RenderCount,
+    // Items hidden by filter of last render, so offset stays within what's
+    // displayed
+    pub(super) hidden: RenderCount,
 }
 
+/// Count written by [`super::MovableList`] on render, for handling keys
+/// according to what's on screen.
+///
+/// This is a render cache shared between clones, so it's not compared
+#[derive(Clone, Debug, Default)]
+pub struct RenderCount(Arc<AtomicUsize>);
+
+impl RenderCount {
+    pub(super) fn set(&self, num: usize) {
+        self.0.store(num, Ordering::Relaxed)
+    }
+
+    pub(super) fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for RenderCount {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RenderCount {}
+
 impl<'a, T, S> Deref for MovableListState<'a, T, S>
 where
     T: MovableListItem<'a>,
     S: Default,
 {
-    type Target = Vec<T>;
+    type Target = VecDeque<T>;
 
     fn deref(&self) -> &Self::Target {
         &self.items
@@ -152,7 +225,7 @@ where
     type Item<'b> = T;
 
     fn sort_with(&mut self, method: &S) {
-        self.items.sort_by(|a, b| method.sort_fn(a, b))
+        self.items.sort_with(method)
     }
 }
 
@@ -205,7 +278,7 @@ where
 
     fn current_pos(&self) -> Coord {
         let x = self.offset.x;
-        let y = self.len().saturating_sub(self.offset.y);
+        let y = self.num_displayed().saturating_sub(self.offset.y);
         Coord {
             x,
             y,
@@ -237,18 +310,26 @@ where
     }
 
     fn handle(&mut self, event: ListEvent) -> Option<Action> {
-        let len = self.len().saturating_sub(1);
+        let len = self.num_displayed().saturating_sub(1);
+        // Page by no more than what's on screen when items take many lines
+        let page = if self.wrap_lines {
+            self.shown.get().saturating_sub(1).clamp(1, 5)
+        } else {
+            5
+        };
         let offset = &mut self.offset;
 
         if !offset.hold {
             offset.hold = true;
         }
+        // Filter may have changed since last key
+        offset.y = offset.y.min(len);
 
         match (event.fast, event.code) {
             (true, KeyCode::Left) => offset.x = offset.x.saturating_sub(7),
             (true, KeyCode::Right) => offset.x = offset.x.saturating_add(7),
-            (true, KeyCode::Up) => offset.y = offset.y.saturating_sub(5),
-            (true, KeyCode::Down) => offset.y = offset.y.saturating_add(5).min(len),
+            (true, KeyCode::Up) => offset.y = offset.y.saturating_sub(page),
+            (true, KeyCode::Down) => offset.y = offset.y.saturating_add(page).min(len),
             (false, KeyCode::Left) => offset.x = offset.x.saturating_sub(1),
             (false, KeyCode::Right) => offset.x = offset.x.saturating_add(1),
             (false, KeyCode::Up) => offset.y = offset.y.saturating_sub(1),
@@ -263,8 +344,26 @@ where
     }
 }
 
+/// Run `$body` with `$inner` bound to list state of any variant of
+/// [`MovableListManager`]
+macro_rules! match_list {
+    ($self:expr, $inner:ident => $body:expr) => {
+        match $self {
+            #[cfg(feature = "logs")]
+            Self::Log($inner) => $body,
+            #[cfg(feature = "connections")]
+            Self::Connection($inner) => $body,
+            Self::Rule($inner) => $body,
+            Self::Event($inner) => $body,
+            Self::Proxy($inner) => $body,
+        }
+    };
+}
+
 pub enum MovableListManager<'a, 'own> {
+    #[cfg(feature = "logs")]
     Log(&'own mut LogListState<'a>),
+    #[cfg(feature = "connections")]
     Connection(&'own mut ConListState<'a>),
     Rule(&'own mut RuleListState<'a>),
     Event(&'own mut DebugListState<'a>),
@@ -273,151 +372,129 @@ pub enum MovableListManager<'a, 'own> {
 
 impl<'a, 'own> MovableListManage for MovableListManager<'a, 'own> {
     fn sort(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.sort();
-            }
-        );
+        match_list!(self, inner => {
+            inner.sort();
+        });
         self
     }
 
     fn next_sort(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.next_sort();
-            }
-        );
+        match_list!(self, inner => {
+            inner.next_sort();
+        });
         self
     }
 
     fn prev_sort(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.prev_sort();
-            }
-        );
+        match_list!(self, inner => {
+            inner.prev_sort();
+        });
         self
     }
 
     fn current_pos(&self) -> Coord {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.current_pos()
-            }
-        )
+        match_list!(self, inner => inner.current_pos())
     }
 
     fn len(&self) -> usize {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.len()
-            }
-        )
+        match_list!(self, inner => inner.len())
     }
 
     fn is_empty(&self) -> bool {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.is_empty()
-            }
-        )
+        match_list!(self, inner => inner.is_empty())
     }
 
     fn toggle(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.toggle();
-            }
-        );
+        match_list!(self, inner => {
+            inner.toggle();
+        });
         self
     }
 
     fn end(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.end();
-            }
-        );
+        match_list!(self, inner => {
+            inner.end();
+        });
         self
     }
 
     fn hold(&mut self) -> &mut Self {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.hold();
-            }
-        );
+        match_list!(self, inner => {
+            inner.hold();
+        });
         self
     }
 
     fn handle(&mut self, event: ListEvent) -> Option<Action> {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.handle(event)
-            }
-        )
+        match_list!(self, inner => inner.handle(event))
     }
 
     fn offset(&self) -> &Coord {
-        match_any!(
-            self,
-            Self::Log(inner) |
-            Self::Event(inner) |
-            Self::Rule(inner) |
-            Self::Connection(inner) |
-            Self::Proxy(inner) => {
-                inner.offset()
-            }
-        )
+        match_list!(self, inner => inner.offset())
+    }
+}
+
+#[test]
+fn test_capacity() {
+    use crate::interactive::Noop;
+
+    let mut state = MovableListState::<String, Noop>::default();
+    state.set_capacity(Some(3));
+    for i in 0..5 {
+        state.push(i.to_string());
+    }
+    assert_eq!(state.items, ["2", "3", "4"]);
+
+    // Held offset follows pushed items until what it points to is dropped
+    state.offset.hold = true;
+    state.offset.y = 1;
+    state.push("5".to_owned());
+    assert_eq!(state.offset.y, 2);
+    state.push("6".to_owned());
+    assert_eq!(state.items, ["4", "5", "6"]);
+    assert_eq!(state.offset.y, 2);
+
+    state.drop_oldest(5);
+    assert!(state.items.is_empty());
+    assert_eq!(state.offset.y, 0);
+}
+
+#[test]
+fn test_filtered_offset() {
+    use tui::widgets::Widget;
+
+    use crate::{interactive::Noop, ui::components::MovableList};
+
+    let mut state =
+        MovableListState::<String, Noop>::new((0..10).map(|x| x.to_string()).collect());
+    let down = ListEvent {
+        fast: false,
+        code: KeyCode::Down,
+    };
+    let render = |state: &MovableListState<String, Noop>| {
+        let area = tui::layout::Rect::new(0, 0, 20, 10);
+        let mut buf = tui::buffer::Buffer::empty(area);
+        MovableList::new("", state)
+            .filter(|x| x.parse::<u8>().unwrap() % 3 == 0)
+            .render(area, &mut buf);
+    };
+
+    // 4 matches, so Down stops on the last one
+    render(&state);
+    for _ in 0..10 {
+        state.handle(down.clone());
     }
+    assert_eq!(state.offset.y, 3);
+    state.handle(ListEvent { fast: true, ..down });
+    assert_eq!(state.offset.y, 3);
+    assert_eq!(state.num_displayed(), 4);
+    assert_eq!(state.current_pos().y, 1);
+
+    // Offset beyond a narrowed filter is pulled back before moving
+    state.offset.y = 8;
+    state.handle(ListEvent {
+        fast: false,
+        code: KeyCode::Up,
+    });
+    assert_eq!(state.offset.y, 2);
 }
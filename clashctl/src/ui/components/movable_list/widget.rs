@@ -12,7 +12,7 @@ use crate::{
         components::{
             Footer, FooterItem, FooterWidget, MovableListItem, MovableListManage, MovableListState,
         },
-        utils::{get_block, get_focused_block, get_text_style},
+        utils::{get_block, get_focused_block, get_text_style, wrap_spans},
     },
 };
 
@@ -27,6 +27,8 @@ where
 {
     pub(super) title: String,
     pub(super) state: &'a MovableListState<'a, T, S>,
+    // Items to be displayed if filtered, all items otherwise
+    pub(super) filtered: Option<Vec<&'a T>>,
 }
 
 impl<'a, T, S> MovableList<'a, T, S>
@@ -39,6 +41,26 @@ where
         Self {
             state,
             title: title.into(),
+            filtered: None,
+        }
+    }
+
+    /// Only display items that satisfy `predicate`
+    pub fn filter<F: Fn(&T) -> bool>(mut self, predicate: F) -> Self {
+        self.filtered = Some(self.state.items.iter().filter(|x| predicate(x)).collect());
+        self
+    }
+
+    pub fn title<TITLE: Into<String>>(mut self, title: TITLE) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Number of items to be displayed, after filtering
+    pub fn num_displayed(&self) -> usize {
+        match self.filtered {
+            Some(ref filtered) => filtered.len(),
+            None => self.state.items.len(),
         }
     }
 
@@ -92,7 +114,12 @@ where
     MovableListState<'a, T, S>: MovableListManage,
 {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let num = self.state.items.len();
+        let all_items = match self.filtered {
+            Some(ref filtered) => filtered.to_owned(),
+            None => self.state.items.iter().collect(),
+        };
+        let num = all_items.len();
+        self.state.hidden.set(self.state.items.len() - num);
 
         let offset = self.state.offset;
 
@@ -136,9 +163,42 @@ where
         let rev_index = self.state.reverse_index;
 
         // Get that portion of items
-        let items = if num != 0 {
-            self.state
-                .items
+        let items = if num != 0 && self.state.wrap_lines {
+            let gutter = if with_index { index_width + 1 } else { 0 };
+            let text_width = (inner.width as usize).saturating_sub(gutter);
+            let mut lines = Vec::with_capacity(height);
+            let mut shown = 0;
+            for (i, x) in all_items.iter().rev().skip(y_offset).enumerate() {
+                if lines.len() >= height {
+                    break;
+                }
+                let wrapped = wrap_spans(&x.to_spans(), text_width, x.wrap_indent());
+                if lines.len() + wrapped.len() <= height {
+                    shown += 1;
+                }
+                for (j, mut spans) in wrapped.into_iter().enumerate() {
+                    if with_index {
+                        let cur_index = if rev_index {
+                            num - i - y_offset
+                        } else {
+                            i + y_offset + 1
+                        };
+                        // Only first line of an item is numbered
+                        let gutter = if j == 0 {
+                            format!("{:>width$} ", cur_index, width = index_width)
+                        } else {
+                            " ".repeat(gutter)
+                        };
+                        spans.0.insert(0, Span::styled(gutter, index_style));
+                    }
+                    lines.push(ListItem::new(spans));
+                }
+            }
+            lines.truncate(height);
+            self.state.shown.set(shown);
+            lines
+        } else if num != 0 {
+            all_items
                 .iter()
                 .rev()
                 .skip(y_offset)
@@ -0,0 +1,214 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use strum::IntoEnumIterator;
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph, Widget},
+};
+
+use crate::{
+    interactive::{KeyAction, KeyMap},
+    ui::{
+        define_widget,
+        utils::{fuzzy_match, get_focused_block},
+    },
+    Event, InputEvent,
+};
+
+/// Commands with keys not configurable in [`KeyMap`], as `(name, key, event)`
+const FIXED_COMMANDS: &[(&str, &str, InputEvent)] = &[
+    ("toggle fold", "za", InputEvent::ToggleFold),
+    ("collapse all", "zM", InputEvent::CollapseAll),
+    ("expand all", "zR", InputEvent::ExpandAll),
+    ("next sort", "s", InputEvent::NextSort),
+    ("previous sort", "alt-s", InputEvent::PrevSort),
+    ("toggle debug", "ctrl-d", InputEvent::ToggleDebug),
+];
+
+/// Command listed in [`CommandPalette`], run by handling `event` as if its
+/// key was pressed
+#[derive(Debug, Clone)]
+pub struct PaletteCommand {
+    pub name: String,
+    // Keys bound to it, separated by commas
+    pub keys: String,
+    pub event: Event,
+}
+
+/// Actions of `keymap` with their bound keys, followed by the ones with fixed
+/// keys
+pub fn palette_commands(keymap: &KeyMap) -> Vec<PaletteCommand> {
+    // Run from palette, so there's no modifier for moving fast
+    let key = KeyEvent::new(KeyCode::Null, KeyModifiers::NONE);
+    let bound = KeyAction::iter().map(|action| PaletteCommand {
+        name: action.to_string().replace('_', " "),
+        keys: keymap
+            .binds(action)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        event: Event::from_action(action, key),
+    });
+    let fixed = FIXED_COMMANDS
+        .iter()
+        .map(|(name, keys, event)| PaletteCommand {
+            name: (*name).to_owned(),
+            keys: (*keys).to_owned(),
+            event: Event::Input(event.clone()),
+        });
+    bound.chain(fixed).collect()
+}
+
+/// Query typed into command palette and the highlighted one of commands
+/// matching it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaletteState {
+    pub query: String,
+    pub index: usize,
+}
+
+impl PaletteState {
+    /// Commands with names fuzzy matching the query, along with indices of
+    /// matched chars
+    pub fn filter<'c>(
+        &self,
+        commands: &'c [PaletteCommand],
+    ) -> Vec<(&'c PaletteCommand, Vec<usize>)> {
+        commands
+            .iter()
+            .filter_map(|x| Some((x, fuzzy_match(&self.query, &x.name)?)))
+            .collect()
+    }
+
+    /// Event of the highlighted command, `None` if nothing matches
+    pub fn picked(&self, commands: &[PaletteCommand]) -> Option<Event> {
+        self.filter(commands)
+            .get(self.index)
+            .map(|(x, _)| x.event.clone())
+    }
+
+    pub fn handle(&mut self, key: KeyEvent, num_matched: usize) {
+        match key.code {
+            KeyCode::Up => self.index = self.index.saturating_sub(1),
+            KeyCode::Down => self.index = (self.index + 1).min(num_matched.saturating_sub(1)),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.index = 0;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.index = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+define_widget!(CommandPalette);
+
+/// Popup of commands matching what's typed, drawn in the middle of `area`
+impl<'a> Widget for CommandPalette<'a> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        let state = match self.state.palette {
+            Some(ref state) => state,
+            None => return,
+        };
+        let commands = palette_commands(&crate::get_config().tui.keymap);
+        let matched = state.filter(&commands);
+
+        let width = 48.min(area.width);
+        let height = (matched.len() as u16 + 3).clamp(4, 20).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        // Rows left for commands after borders and query
+        let rows = height.saturating_sub(3) as usize;
+        let skip = (state.index + 1).saturating_sub(rows);
+        let name_width = (width as usize).saturating_sub(4);
+
+        let mut lines = vec![Spans::from(vec![
+            Span::styled(": ", Style::default().fg(Color::LightYellow)),
+            Span::raw(state.query.to_owned()),
+            Span::styled("█", Style::default().fg(Color::DarkGray)),
+        ])];
+        if matched.is_empty() {
+            lines.push(Spans::from(Span::styled(
+                "No matching commands",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        for (i, (command, indices)) in matched.iter().enumerate().skip(skip).take(rows) {
+            let picked = i == state.index;
+            let style = if picked {
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let mut line = vec![Span::styled(if picked { "➤ " } else { "  " }, style)];
+            line.extend(command.name.chars().enumerate().map(|(i, c)| {
+                if indices.contains(&i) {
+                    Span::styled(c.to_string(), style.fg(Color::LightYellow))
+                } else {
+                    Span::styled(c.to_string(), style)
+                }
+            }));
+            let padding = name_width.saturating_sub(command.name.len() + command.keys.len());
+            line.push(Span::raw(" ".repeat(padding)));
+            line.push(Span::styled(
+                command.keys.to_owned(),
+                Style::default().fg(Color::DarkGray),
+            ));
+            lines.push(Spans::from(line));
+        }
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(get_focused_block("Commands · Enter to run"))
+            .render(popup, buf);
+    }
+}
+
+#[test]
+fn test_palette() {
+    use crate::interactive::KeyBind;
+
+    let commands = palette_commands(&KeyMap::default());
+    assert_eq!(commands[0].name, "move up");
+    assert_eq!(commands[0].keys, "up");
+    assert!(commands.iter().any(|x| x.name == "quit" && x.keys == "q, x"));
+    assert!(commands
+        .iter()
+        .any(|x| x.name == "open palette" && x.keys == ":, ctrl-p"));
+
+    // Fixed commands can't be shadowed by keymap. First key of chords is what
+    // can't be bound
+    for (_, keys, _) in FIXED_COMMANDS {
+        let bind = keys
+            .parse::<KeyBind>()
+            .unwrap_or_else(|_| KeyBind::char(keys.chars().next().unwrap()));
+        assert!(bind.is_fixed(), "{}", keys);
+    }
+
+    let mut state = PaletteState::default();
+    assert_eq!(state.filter(&commands).len(), commands.len());
+    for c in "expall".chars() {
+        state.handle(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), 0);
+    }
+    let matched = state.filter(&commands);
+    assert_eq!(matched.len(), 1);
+    assert_eq!(matched[0].0.name, "expand all");
+    assert!(matches!(
+        state.picked(&commands),
+        Some(Event::Input(InputEvent::ExpandAll))
+    ));
+
+    state.query = "zzz".to_owned();
+    assert!(state.picked(&commands).is_none());
+}
@@ -1,16 +1,40 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    time::{Duration, Instant},
+};
 
-use clashctl_core::model::ProxyType;
+use chrono::Local;
+use clashctl_core::model::{History, Proxies, Proxy, ProxyType};
+use log::warn;
+use smart_default::SmartDefault;
 use tui::{
+    layout::Rect,
     style::{Color, Modifier, Style},
     text::{Span, Spans},
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::ui::{
-    components::{Consts, ProxyItem},
-    utils::get_text_style,
+use crate::{
+    interactive::{LatencyThresholds, Theme},
+    ui::{
+        components::{latest_history, Consts, ProxyItem, TestStatus},
+        utils::{fuzzy_match, skip_columns, IntoSpans},
+    },
 };
 
+/// Columns delays of members are right-aligned in, so rows don't shift
+/// between `9` and `1234`
+const DELAY_WIDTH: usize = 4;
+
+/// `text` padded on the left to [`DELAY_WIDTH`]
+fn align_delay(text: &str) -> String {
+    format!("{}{}", " ".repeat(DELAY_WIDTH.saturating_sub(text.width())), text)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ProxyGroup<'a> {
     pub(super) name: String,
@@ -18,9 +42,112 @@ pub struct ProxyGroup<'a> {
     pub(super) members: Vec<ProxyItem>,
     pub(super) current: Option<usize>,
     pub(super) cursor: usize,
+    pub(super) member_order: MemberOrder,
+    // Display order of members as indices, `None` if in original order. Members
+    // themselves are kept untouched so merging can still match by name
+    pub(super) order: Option<Vec<usize>>,
+    // Search query used to filter members, `None` if not searching
+    pub(super) filter: Option<String>,
+    // Name of the member pointed before search started
+    pub(super) saved_cursor: Option<String>,
+    // Whether members are listed, regardless of whether the group is focused
+    pub(super) expanded: bool,
+    // Hide members that failed or haven't been tested. Only affects display,
+    // members are kept to be tested later
+    pub(super) hide_dead: bool,
+    // Hash of data the group was last updated with, to skip unchanged ones
+    pub(super) source_hash: Option<u64>,
+    // Pinned to top of the tree, set by [`super::ProxyTree`]
+    pub(super) pinned: bool,
+    // When current member was last changed by an update, for highlighting
+    // changes made elsewhere
+    pub(super) current_changed: Option<Instant>,
+    // Columns member names are scrolled to the left by, to see the rest of
+    // long names. Only applied while cursor is inside the group
+    pub(super) member_hscroll: usize,
+    // Members tested alone, with when each test started, see
+    // [`super::ProxyTree::start_testing_member`]
+    pub(super) testing_members: BTreeMap<String, Instant>,
+    // Pointed member shown with an older delay sample, and how many samples
+    // it's before the latest one. Ignored once cursor moves to another member
+    pub(super) history_back: Option<(String, usize)>,
+    // Aliases of members shown in place of their names, keyed by names. Set
+    // by [`super::ProxyTree`]
+    pub(super) aliases: BTreeMap<String, String>,
     pub(super) _life: PhantomData<&'a ()>,
 }
 
+/// How long newly current member is highlighted after changed elsewhere
+const CURRENT_FLASH: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, SmartDefault)]
+pub enum MemberOrder {
+    #[default]
+    Original,
+    DelayAsc,
+}
+
+impl MemberOrder {
+    pub fn indicator(&self) -> &'static str {
+        match self {
+            MemberOrder::Original => "",
+            MemberOrder::DelayAsc => "↑delay",
+        }
+    }
+}
+
+/// Aggregated delays of a group, in ms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub min: u64,
+    pub median: u64,
+}
+
+/// Number of proxy members in each latency band by their latest tests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GroupStats {
+    pub low: usize,
+    pub mid: usize,
+    pub high: usize,
+    pub timeout: usize,
+    pub untested: usize,
+}
+
+impl GroupStats {
+    pub fn total(&self) -> usize {
+        self.low + self.mid + self.high + self.timeout + self.untested
+    }
+}
+
+/// Cache key of group `name`, equal as long as what's shown of the group is.
+///
+/// Only a canonical projection is hashed: name, type and current member of
+/// the group, then name, type, UDP support, current member and latest delay
+/// sample of each member, in member order. Older samples and the order Clash
+/// lists history in are left out, so they don't force a rebuild. Stable within
+/// one run only, as [`DefaultHasher`] is not guaranteed to be stable across
+/// releases
+pub fn hash_group(name: &str, group: &Proxy, proxies: &Proxies) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (name, &group.proxy_type, &group.now).hash(&mut hasher);
+    for member in group.all.iter().flatten() {
+        member.hash(&mut hasher);
+        proxies
+            .get(member)
+            .map(|x| {
+                (
+                    &x.proxy_type,
+                    x.udp,
+                    &x.now,
+                    latest_history(&x.history),
+                )
+            })
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProxyGroupFocusStatus {
     None,
     Focused,
@@ -28,134 +155,641 @@ pub enum ProxyGroupFocusStatus {
 }
 
 impl<'a> ProxyGroup<'a> {
-    pub fn proxy_type(&self) -> ProxyType {
-        self.proxy_type
+    /// Build group `name` out of `group`, with members looked up in `proxies`
+    pub fn new(name: &str, group: &Proxy, proxies: &Proxies) -> Self {
+        let mut ret = Self {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        ret.update(group, proxies);
+        ret.cursor = ret.current.unwrap_or_default();
+        ret
+    }
+
+    /// Update members with latest data of the group. Members still in the
+    /// group are updated in place and keep their allocations. Returns `false`
+    /// if nothing changed since last update
+    pub(super) fn update(&mut self, group: &Proxy, proxies: &Proxies) -> bool {
+        let hash = Some(hash_group(&self.name, group, proxies));
+        if self.source_hash == hash {
+            return false;
+        }
+        // Building a new group is not a change
+        let first = self.source_hash.is_none();
+        self.source_hash = hash;
+
+        let previous = self
+            .current
+            .and_then(|x| self.members.get(x))
+            .map(|x| x.name.clone());
+        let pointed = self.members.get(self.cursor).map(|x| x.name.clone());
+        // Names are moved into keys and back to avoid cloning them
+        let mut old = mem::take(&mut self.members)
+            .into_iter()
+            .map(|mut x| (mem::take(&mut x.name), x))
+            .collect::<HashMap<_, _>>();
+
+        // Groups backed by empty providers may have no member at all
+        let all = group.all.as_deref().unwrap_or_default();
+        self.members = all
+            .iter()
+            .filter_map(|x| match (proxies.get(x), old.remove_entry(x)) {
+                (Some(proxy), Some((name, mut item))) => {
+                    item.name = name;
+                    item.update(proxy);
+                    Some(item)
+                }
+                (Some(proxy), None) => Some((x.as_str(), proxy).into()),
+                (None, _) => {
+                    warn!("Member {} of group {} is not in proxies", x, self.name);
+                    None
+                }
+            })
+            .collect();
+
+        let position = |name: &str| self.members.iter().position(|x| x.name == name);
+        self.proxy_type = group.proxy_type.clone();
+        self.current = group.now.as_deref().and_then(position);
+        if !first && self.current.is_some() && group.now != previous {
+            self.current_changed = Some(Instant::now());
+        }
+        self.cursor = pointed
+            .as_deref()
+            .and_then(position)
+            .or(self.current)
+            .unwrap_or_default();
+        self.refresh_order();
+        self.clamp_cursor();
+        true
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn proxy_type(&self) -> &ProxyType {
+        &self.proxy_type
     }
 
     pub fn members(&self) -> &Vec<ProxyItem> {
         &self.members
     }
 
-    pub fn get_summary_widget(&self) -> impl Iterator<Item = Span> {
+    /// Members in the order Clash lists them, regardless of sorting, filter
+    /// and hidden dead ones
+    pub fn iter_members(&self) -> impl Iterator<Item = &ProxyItem> + '_ {
+        self.members.iter()
+    }
+
+    pub fn member(&self, name: &str) -> Option<&ProxyItem> {
+        self.members.iter().find(|x| x.name == name)
+    }
+
+    /// Member selected in Clash, if any
+    pub fn current(&self) -> Option<&ProxyItem> {
+        self.current.and_then(|x| self.members.get(x))
+    }
+
+    pub fn member_order(&self) -> MemberOrder {
+        self.member_order
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Whether it's the `GLOBAL` group, used in global mode
+    pub fn is_global(&self) -> bool {
+        self.name == "GLOBAL"
+    }
+
+    pub fn is_hiding_dead(&self) -> bool {
+        self.hide_dead
+    }
+
+    pub fn toggle_hide_dead(&mut self) -> &mut Self {
+        self.hide_dead = !self.hide_dead;
+        self.clamp_cursor();
+        self
+    }
+
+    /// Order members by latest delay, ascending. Members without latency or
+    /// with zero delay are pushed to the bottom
+    pub fn sort_by_delay(&mut self) -> &mut Self {
+        let mut order = (0..self.members.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| match self.members[*i].delay() {
+            None | Some(0) => u64::MAX,
+            Some(delay) => delay,
+        });
+        self.order = Some(order);
+        self.member_order = MemberOrder::DelayAsc;
+        self
+    }
+
+    pub fn toggle_delay_sort(&mut self) -> &mut Self {
+        match self.member_order {
+            MemberOrder::Original => self.sort_by_delay(),
+            MemberOrder::DelayAsc => {
+                self.order = None;
+                self.member_order = MemberOrder::Original;
+                self
+            }
+        }
+    }
+
+    /// Rebuild order index after members are changed
+    pub(super) fn refresh_order(&mut self) {
+        if matches!(self.member_order, MemberOrder::DelayAsc) {
+            self.sort_by_delay();
+        }
+    }
+
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Indices of members to be displayed, in display order, that match
+    /// current filter and are alive if dead ones are hidden
+    pub fn visible_members(&self) -> Vec<usize> {
+        let order = match self.order {
+            Some(ref order) => order.clone(),
+            None => (0..self.members.len()).collect(),
+        };
+        order
+            .into_iter()
+            .filter(|i| {
+                let matched = match self.filter {
+                    Some(ref filter) => self.matches(filter, *i),
+                    None => true,
+                };
+                self.is_shown(*i) && matched
+            })
+            .collect()
+    }
+
+    /// Indices of members matching `query`, in display order. Current filter
+    /// is ignored, dead members are skipped if they're hidden
+    pub(super) fn find(&self, query: &str) -> Vec<usize> {
+        let order = match self.order {
+            Some(ref order) => order.clone(),
+            None => (0..self.members.len()).collect(),
+        };
+        order
+            .into_iter()
+            .filter(|i| self.is_shown(*i) && self.matches(query, *i))
+            .collect()
+    }
+
+    /// Alias of member if it has one, otherwise its name
+    pub(super) fn label(&self, index: usize) -> &str {
+        let name = &self.members[index].name;
+        self.aliases.get(name).unwrap_or(name)
+    }
+
+    /// Whether name or alias of member matches `query`
+    fn matches(&self, query: &str, index: usize) -> bool {
+        fuzzy_match(query, &self.members[index].name).is_some()
+            || fuzzy_match(query, self.label(index)).is_some()
+    }
+
+    /// Whether member is not hidden as dead
+    fn is_shown(&self, index: usize) -> bool {
+        let member = &self.members[index];
+        !self.hide_dead
+            || !member.proxy_type.is_normal()
+            || !matches!(member.delay(), None | Some(0))
+    }
+
+    pub(super) fn start_search(&mut self) {
+        if self.filter.is_none() {
+            self.saved_cursor = self.members.get(self.cursor).map(|x| x.name.to_owned());
+            self.filter = Some(String::new());
+        }
+    }
+
+    pub(super) fn clear_search(&mut self) {
+        self.filter = None;
+        if let Some(name) = self.saved_cursor.take() {
+            if let Some(pos) = self.members.iter().position(|x| x.name == name) {
+                self.cursor = pos
+            }
+        }
+    }
+
+    pub(super) fn push_search(&mut self, c: char) {
+        if let Some(ref mut filter) = self.filter {
+            filter.push(c);
+            self.clamp_cursor();
+        }
+    }
+
+    pub(super) fn pop_search(&mut self) {
+        if let Some(ref mut filter) = self.filter {
+            filter.pop();
+            self.clamp_cursor();
+        }
+    }
+
+    /// Move cursor to first visible member if it's been filtered out
+    pub(super) fn clamp_cursor(&mut self) {
+        let visible = self.visible_members();
+        if !visible.contains(&self.cursor) {
+            if let Some(first) = visible.first() {
+                self.cursor = *first
+            }
+        }
+    }
+
+    /// Point cursor at current member, if there is one
+    pub fn jump_to_current(&mut self) -> &mut Self {
+        self.cursor = self.current.unwrap_or(self.cursor);
+        self
+    }
+
+    /// Move cursor among shown members by `step`. With `wrap`, moving past
+    /// the first or last one goes to the other end
+    pub(super) fn move_cursor(&mut self, up: bool, step: usize, wrap: bool) {
+        let visible = self.visible_members();
+        let last = visible.len().saturating_sub(1);
+        let pos = match visible.iter().position(|x| *x == self.cursor) {
+            Some(0) if up && wrap => last,
+            Some(pos) if pos == last && !up && wrap => 0,
+            Some(pos) if up => pos.saturating_sub(step),
+            Some(pos) => (pos + step).min(last),
+            None => 0,
+        };
+        if let Some(index) = visible.get(pos) {
+            self.cursor = *index
+        }
+    }
+
+    /// Scroll member names by `step` columns, stopping before the widest name
+    /// is scrolled out entirely
+    pub(super) fn scroll_members(&mut self, left: bool, step: usize) {
+        let max = (0..self.members.len())
+            .map(|i| self.label(i).width())
+            .max()
+            .unwrap_or_default()
+            .saturating_sub(1);
+        self.member_hscroll = if left {
+            self.member_hscroll.saturating_sub(step)
+        } else {
+            (self.member_hscroll + step).min(max)
+        };
+    }
+
+    /// Samples before the latest one shown for pointed member
+    pub(super) fn history_back(&self) -> usize {
+        match (&self.history_back, self.members.get(self.cursor)) {
+            (Some((name, back)), Some(pointed)) if *name == pointed.name => *back,
+            _ => 0,
+        }
+    }
+
+    /// Show one sample `older` or newer of pointed member's delay history.
+    /// Returns `false` if there's none further that way
+    pub(super) fn step_history(&mut self, older: bool) -> bool {
+        let pointed = match self.members.get(self.cursor) {
+            Some(pointed) => pointed,
+            None => return false,
+        };
+        let back = self.history_back();
+        let back = match older {
+            true if back + 1 < pointed.history.len() => back + 1,
+            false if back > 0 => back - 1,
+            _ => return false,
+        };
+        self.history_back = Some((pointed.name.to_owned(), back));
+        true
+    }
+
+    /// Members listed below the header line within `rows` lines, from top to
+    /// bottom, or `None` if members are summarized (or group is empty)
+    /// instead. Scroll with cursor only when it's `inside` the group
+    pub(super) fn listed_members(&self, inside: bool, rows: usize) -> Option<Vec<usize>> {
+        if self.members.is_empty() || (!inside && !self.expanded) {
+            return None;
+        }
+        let visible = self.visible_members();
+        let skipped = if inside {
+            let pos = visible
+                .iter()
+                .position(|x| *x == self.cursor)
+                .unwrap_or_default();
+            // Keep cursor in the middle, unless it's close to either end
+            pos.saturating_sub(rows / 2)
+                .min(visible.len().saturating_sub(rows))
+        } else {
+            0
+        };
+        Some(visible.into_iter().skip(skipped).take(rows).collect())
+    }
+
+    /// Min & median of members' latest delays, timed out and untested ones
+    /// are ignored. `None` if no member has a delay
+    pub fn latency_stats(&self) -> Option<LatencyStats> {
+        let mut delays = self
+            .members
+            .iter()
+            .filter_map(|x| match x.status() {
+                TestStatus::Ok(delay) => Some(delay),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if delays.is_empty() {
+            return None;
+        }
+        delays.sort_unstable();
+        let mid = delays.len() / 2;
+        let median = if delays.len() % 2 == 0 {
+            (delays[mid - 1] + delays[mid]) / 2
+        } else {
+            delays[mid]
+        };
+        Some(LatencyStats {
+            min: delays[0],
+            median,
+        })
+    }
+
+    /// Count proxy members in latency bands of `thresholds`, other groups
+    /// and built-ins are left out
+    pub fn stats(&self, thresholds: &LatencyThresholds) -> GroupStats {
+        let mut stats = GroupStats::default();
+        for member in self.members.iter().filter(|x| x.proxy_type.is_normal()) {
+            let band = match member.status() {
+                TestStatus::Ok(delay) if delay <= thresholds.low => &mut stats.low,
+                TestStatus::Ok(delay) if delay <= thresholds.mid => &mut stats.mid,
+                TestStatus::Ok(_) => &mut stats.high,
+                TestStatus::Timeout => &mut stats.timeout,
+                TestStatus::Untested => &mut stats.untested,
+            };
+            *band += 1;
+        }
+        stats
+    }
+
+    pub fn get_summary_widget<'s>(
+        &'s self,
+        thresholds: &'s LatencyThresholds,
+        theme: &'s Theme,
+    ) -> impl Iterator<Item = Span<'static>> + 's {
         self.members.iter().map(|x| {
             if x.proxy_type.is_normal() {
-                match x.history {
-                    Some(ref history) => Self::get_delay_span(history.delay),
-                    None => Consts::NO_LATENCY_SPAN,
-                }
+                let style = match x.status() {
+                    TestStatus::Ok(delay) => theme.delay_style(delay, thresholds),
+                    TestStatus::Timeout => theme.timeout_style(),
+                    TestStatus::Untested => theme.no_latency_style(),
+                };
+                Span::styled(Consts::PROXY_LATENCY_SIGN, style)
             } else {
-                Consts::NOT_PROXY_SPAN
+                Span::styled(Consts::NOT_PROXY_SIGN, theme.no_latency_style())
             }
         })
     }
 
-    pub fn get_widget(&'a self, width: usize, status: ProxyGroupFocusStatus) -> Vec<Spans<'a>> {
+    /// Lines of the group, no more than `area.height` (but at least the header)
+    pub fn get_widget(
+        &'a self,
+        area: Rect,
+        thresholds: &LatencyThresholds,
+        theme: &Theme,
+        status: ProxyGroupFocusStatus,
+        testing: bool,
+        compact: bool,
+    ) -> Vec<Spans<'a>> {
         let delimiter = Span::raw(" ");
         let prefix = if matches!(status, ProxyGroupFocusStatus::Focused) {
             Consts::FOCUSED_INDICATOR_SPAN
         } else {
             Consts::UNFOCUSED_INDICATOR_SPAN
         };
-        let name = Span::styled(
-            &self.name,
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        );
+        let name = Span::styled(&self.name, theme.group_name_style());
 
-        let proxy_type = Span::styled(self.proxy_type.to_string(), Consts::PROXY_TYPE_STYLE);
+        let type_name = |proxy_type: &ProxyType| {
+            if compact {
+                proxy_type.short().to_owned()
+            } else {
+                proxy_type.to_string()
+            }
+        };
+        let proxy_type = Span::styled(type_name(&self.proxy_type), theme.proxy_type_style());
 
-        let count = self.members.len();
+        let visible = self.visible_members();
+        let pos = visible.iter().position(|x| *x == self.cursor);
         let proxy_count = Span::styled(
-            if matches!(status, ProxyGroupFocusStatus::Expanded) {
-                format!("{}/{}", self.cursor + 1, count)
-            } else {
-                count.to_string()
+            match (&status, pos) {
+                (ProxyGroupFocusStatus::Expanded, Some(pos)) => {
+                    format!("{}/{}", pos + 1, visible.len())
+                }
+                (ProxyGroupFocusStatus::Expanded, None) => format!("0/{}", visible.len()),
+                _ if self.hide_dead || self.filter.is_some() => {
+                    format!("{}/{}", visible.len(), self.members.len())
+                }
+                _ => self.members.len().to_string(),
             },
             Style::default().fg(Color::Green),
         );
 
-        let mut ret = Vec::with_capacity(if matches!(status, ProxyGroupFocusStatus::Expanded) {
-            self.members.len() + 1
+        let inside = matches!(status, ProxyGroupFocusStatus::Expanded);
+        let width = area.width as usize;
+        let rows = (area.height as usize).saturating_sub(1);
+
+        let mut ret = Vec::with_capacity(if inside || self.expanded {
+            self.members.len().min(rows) + 1
         } else {
             2
         });
 
-        ret.push(Spans::from(vec![
-            prefix.clone(),
+        let mut header = vec![prefix.clone()];
+        if self.pinned {
+            header.push(Span::styled(
+                Consts::PINNED_SIGN,
+                Style::default().fg(Color::LightYellow),
+            ));
+            header.push(delimiter.clone());
+        }
+        header.extend([
             name,
             delimiter.clone(),
             proxy_type,
-            delimiter,
+            delimiter.clone(),
             proxy_count,
-        ]));
+        ]);
 
-        if matches!(status, ProxyGroupFocusStatus::Expanded) {
-            let skipped = self.cursor.saturating_sub(4);
-            let text_style = get_text_style();
-            let is_current =
-                |index: usize| self.current.map(|x| x == index + skipped).unwrap_or(false);
-            let is_pointed = |index: usize| self.cursor == index + skipped;
+        let collapsed = !inside && !self.expanded;
+        if collapsed && self.members.iter().any(|x| x.proxy_type.is_normal()) {
+            header.push(delimiter.clone());
+            match self.latency_stats() {
+                Some(LatencyStats { min, median }) => header.extend([
+                    Span::styled(min.to_string(), theme.delay_style(min, thresholds)),
+                    Span::styled("/", theme.proxy_type_style()),
+                    Span::styled(median.to_string(), theme.delay_style(median, thresholds)),
+                ]),
+                None => header.push(Span::styled(
+                    Consts::NO_LATENCY_SIGN,
+                    theme.no_latency_style(),
+                )),
+            }
+        }
+
+        if !matches!(self.member_order, MemberOrder::Original) {
+            header.push(delimiter);
+            header.push(Span::styled(
+                self.member_order.indicator(),
+                theme.proxy_type_style(),
+            ));
+        }
 
-            let lines = self.members.iter().skip(skipped).enumerate().map(|(i, x)| {
-                let prefix = if self.cursor == i + skipped {
+        if self.hide_dead {
+            header.push(Consts::DELIMITER_SPAN.clone());
+            header.push(Span::styled(Consts::HIDE_DEAD_SIGN, theme.proxy_type_style()));
+        }
+
+        if testing {
+            header.push(Consts::DELIMITER_SPAN.clone());
+            header.push(Span::styled(Consts::TESTING_SIGN, theme.testing_style()));
+        }
+
+        ret.push(Spans::from(header));
+
+        if self.members.is_empty() {
+            if rows > 0 {
+                ret.push(Spans::from(vec![
+                    prefix,
+                    Span::styled(Consts::EMPTY_GROUP_SIGN, theme.no_latency_style()),
+                ]));
+            }
+        } else if let Some(listed) = self.listed_members(inside, rows) {
+            let is_current = |index: usize| self.current.map(|x| x == index).unwrap_or(false);
+            let is_pointed = |index: usize| inside && self.cursor == index;
+            let flashing = self.is_flashing();
+            let hscroll = if inside { self.member_hscroll } else { 0 };
+            let history_back = if inside { self.history_back() } else { 0 };
+            // Pad names to the widest listed one so types and delays line up,
+            // unless it takes more than half of the width
+            let name_width = listed
+                .iter()
+                .map(|&i| skip_columns(self.label(i), hscroll).1.width())
+                .max()
+                .unwrap_or_default()
+                .min(width / 2);
+
+            let lines = listed.into_iter().map(|i| {
+                let x = &self.members[i];
+                let prefix = if is_pointed(i) {
                     Consts::EXPANDED_FOCUSED_INDICATOR_SPAN
                 } else {
                     Consts::EXPANDED_INDICATOR_SPAN
                 };
-                let name = Span::styled(
-                    &x.name,
-                    if is_current(i) {
-                        Style::default()
-                            .fg(Color::Blue)
-                            .add_modifier(Modifier::BOLD)
-                    } else if is_pointed(i) {
-                        text_style.fg(Color::LightBlue)
-                    } else {
-                        text_style
-                    },
-                );
-                let proxy_type = Span::styled(x.proxy_type.to_string(), Consts::PROXY_TYPE_STYLE);
-
-                let delay_span = x
-                    .history
+                let mut name_style = theme.member_name_style(is_current(i), is_pointed(i));
+                if flashing && is_current(i) {
+                    name_style = name_style.patch(theme.changed_style());
+                }
+                let (skipped, shown) = skip_columns(self.label(i), hscroll);
+                let name = match self
+                    .filter
                     .as_ref()
-                    .map(|x| {
-                        if x.delay > 0 {
-                            let style = Self::get_delay_style(x.delay);
-                            Span::styled(x.delay.to_string(), style)
-                        } else {
-                            Span::styled(Consts::NO_LATENCY_SIGN, Consts::NO_LATENCY_STYLE)
-                        }
-                    })
-                    .unwrap_or_else(|| {
-                        if !x.proxy_type.is_normal() {
-                            Span::raw("")
-                        } else {
-                            Span::styled(Consts::NO_LATENCY_SIGN, Consts::NO_LATENCY_STYLE)
-                        }
-                    });
-                vec![
-                    prefix,
-                    Consts::DELIMITER_SPAN.clone(),
-                    name,
-                    Consts::DELIMITER_SPAN.clone(),
-                    proxy_type,
-                    Consts::DELIMITER_SPAN.clone(),
-                    delay_span,
-                ]
-                .into()
+                    .and_then(|filter| fuzzy_match(filter, self.label(i)))
+                {
+                    Some(matched) if !matched.is_empty() => shown
+                        .chars()
+                        .zip(skipped..)
+                        .map(|(c, i)| {
+                            if matched.contains(&i) {
+                                (name_style.patch(theme.search_match_style()), c)
+                            } else {
+                                (name_style, c)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .into_spans()
+                        .0,
+                    _ => vec![Span::styled(shown, name_style)],
+                };
+                let proxy_type = Span::styled(type_name(&x.proxy_type), theme.proxy_type_style());
+
+                let back = if is_pointed(i) { history_back } else { 0 };
+                // Older sample with when it's tested, like `120 @ 14:03:21 -2`
+                let sample = (back > 0)
+                    .then(|| x.history.len().checked_sub(back + 1))
+                    .flatten()
+                    .map(|index| (index, &x.history[index]));
+                let delay_span = match x.status() {
+                    _ if self.testing_members.contains_key(&x.name) => {
+                        let frames = Consts::SPINNER_FRAMES;
+                        let frame = self.testing_members[&x.name].elapsed().as_millis() / 100;
+                        Span::styled(
+                            align_delay(frames[frame as usize % frames.len()]),
+                            theme.testing_style(),
+                        )
+                    }
+                    TestStatus::Ok(delay) => Span::styled(
+                        align_delay(&delay.to_string()),
+                        theme.delay_style(delay, thresholds),
+                    ),
+                    TestStatus::Timeout => {
+                        Span::styled(align_delay(Consts::TIMEOUT_SIGN), theme.timeout_style())
+                    }
+                    TestStatus::Untested if x.proxy_type.is_normal() => {
+                        Span::styled(align_delay(Consts::NO_LATENCY_SIGN), theme.no_latency_style())
+                    }
+                    TestStatus::Untested => Span::raw(""),
+                };
+                let delay_span = match sample {
+                    Some((_, sample)) => Span::styled(
+                        format!(
+                            "{} @ {} -{}",
+                            match sample.delay {
+                                0 => align_delay(Consts::TIMEOUT_SIGN),
+                                delay => align_delay(&delay.to_string()),
+                            },
+                            sample.time.with_timezone(&Local).format("%H:%M:%S"),
+                            back
+                        ),
+                        match sample.delay {
+                            0 => theme.timeout_style(),
+                            delay => theme.delay_style(delay, thresholds),
+                        },
+                    ),
+                    None => delay_span,
+                };
+                let mut line = vec![prefix, Consts::DELIMITER_SPAN.clone()];
+                line.extend(name);
+                let padding = name_width.saturating_sub(shown.width());
+                if padding > 0 {
+                    line.push(Span::raw(" ".repeat(padding)));
+                }
+                line.extend([Consts::DELIMITER_SPAN.clone(), proxy_type]);
+                if x.is_udp() {
+                    line.extend([
+                        Consts::DELIMITER_SPAN.clone(),
+                        Span::styled(Consts::UDP_SIGN, theme.udp_style()),
+                    ]);
+                }
+                line.extend([Consts::DELIMITER_SPAN.clone(), delay_span]);
+                if x.history.len() > 1 {
+                    line.push(Consts::DELIMITER_SPAN.clone());
+                    line.extend(Self::get_history_spans(
+                        &x.history,
+                        sample.map(|(index, _)| index),
+                        thresholds,
+                        theme,
+                    ));
+                }
+                line.into()
             });
             ret.extend(lines);
         } else {
             ret.extend(
-                self.get_summary_widget()
+                self.get_summary_widget(thresholds, theme)
                     .collect::<Vec<_>>()
-                    .chunks(
-                        width
-                            .saturating_sub(Consts::FOCUSED_INDICATOR_SPAN.width() + 2)
-                            .saturating_div(2),
-                    )
+                    .chunks(Self::summary_chunk_size(width))
+                    .take(rows)
                     .map(|x| {
                         std::iter::once(if matches!(status, ProxyGroupFocusStatus::Focused) {
                             Consts::FOCUSED_INDICATOR_SPAN
@@ -172,22 +806,52 @@ impl<'a> ProxyGroup<'a> {
         ret
     }
 
-    fn get_delay_style(delay: u64) -> Style {
-        match delay {
-            0 => Consts::NO_LATENCY_STYLE,
-            1..=200 => Consts::LOW_LATENCY_STYLE,
-            201..=400 => Consts::MID_LATENCY_STYLE,
-            401.. => Consts::HIGH_LATENCY_STYLE,
-        }
+    /// Number of latency signs on each line of collapsed group `width` wide.
+    /// Never zero, which `chunks` panics on, so narrow columns still get one
+    /// sign per line
+    fn summary_chunk_size(width: usize) -> usize {
+        width
+            .saturating_sub(Consts::FOCUSED_INDICATOR_SPAN.width() + 2)
+            .saturating_div(2)
+            .max(1)
     }
 
-    fn get_delay_span(delay: u64) -> Span<'static> {
-        match delay {
-            0 => Consts::NO_LATENCY_SPAN,
-            1..=200 => Consts::LOW_LATENCY_SPAN,
-            201..=400 => Consts::MID_LATENCY_SPAN,
-            401.. => Consts::HIGH_LATENCY_SPAN,
-        }
+    /// Whether current member was just changed elsewhere
+    fn is_flashing(&self) -> bool {
+        self.current_changed
+            .is_some_and(|x| x.elapsed() < CURRENT_FLASH)
+    }
+
+    /// Tiny sparkline of delay history, scaled by the max delay within it.
+    /// Sample at `marked` is reversed
+    fn get_history_spans(
+        history: &[History],
+        marked: Option<usize>,
+        thresholds: &LatencyThresholds,
+        theme: &Theme,
+    ) -> Vec<Span<'static>> {
+        let max = history.iter().map(|x| x.delay).max().unwrap_or_default();
+        let bars = Consts::DELAY_HISTORY_BARS;
+        history
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let mut span = match x.delay {
+                    0 => Span::styled(Consts::DELAY_HISTORY_FAILED, theme.timeout_style()),
+                    delay => {
+                        let level = (delay * bars.len() as u64).div_ceil(max) as usize;
+                        Span::styled(
+                            bars[level.clamp(1, bars.len()) - 1],
+                            theme.delay_style(delay, thresholds),
+                        )
+                    }
+                };
+                if marked == Some(i) {
+                    span.style = span.style.add_modifier(Modifier::REVERSED);
+                }
+                span
+            })
+            .collect()
     }
 }
 
@@ -199,7 +863,405 @@ impl<'a> Default for ProxyGroup<'a> {
             proxy_type: ProxyType::Selector,
             name: String::new(),
             cursor: 0,
+            member_order: MemberOrder::Original,
+            order: None,
+            filter: None,
+            saved_cursor: None,
+            expanded: false,
+            hide_dead: false,
+            source_hash: None,
+            pinned: false,
+            current_changed: None,
+            member_hscroll: 0,
+            testing_members: BTreeMap::new(),
+            history_back: None,
+            aliases: BTreeMap::new(),
             _life: PhantomData,
         }
     }
 }
+
+#[test]
+fn test_sort_by_delay() {
+    use chrono::Utc;
+
+    let item = |name: &str, delay: Option<u64>| ProxyItem {
+        history: delay
+            .map(|delay| History {
+                time: Utc::now(),
+                delay,
+            })
+            .into_iter()
+            .collect(),
+        status: delay.map_or(TestStatus::Untested, TestStatus::from_delay),
+        ..ProxyItem::test(name)
+    };
+    let mut group = ProxyGroup {
+        members: vec![
+            item("a", None),
+            item("b", Some(300)),
+            item("c", Some(0)),
+            item("d", Some(100)),
+        ],
+        ..Default::default()
+    };
+
+    group.sort_by_delay();
+    assert_eq!(group.member_order(), MemberOrder::DelayAsc);
+    assert_eq!(group.visible_members(), vec![3, 1, 0, 2]);
+    // Members themselves should be untouched
+    assert_eq!(group.members()[0].name(), "a");
+
+    group.toggle_delay_sort();
+    assert_eq!(group.visible_members(), vec![0, 1, 2, 3]);
+
+    group.cursor = 2;
+    group.toggle_hide_dead();
+    assert_eq!(group.visible_members(), vec![1, 3]);
+    assert_eq!(group.cursor, 1);
+    assert_eq!(group.members().len(), 4);
+}
+
+#[test]
+fn test_compact_type() {
+    let group = ProxyGroup::new(
+        "Proxy",
+        &Proxy {
+            proxy_type: ProxyType::Selector,
+            history: vec![],
+            udp: None,
+            all: Some(vec!["a".into()]),
+            now: Some("a".into()),
+        },
+        &Proxies {
+            proxies: HashMap::from([(
+                "a".to_owned(),
+                Proxy {
+                    proxy_type: ProxyType::ShadowsocksR,
+                    history: vec![],
+                    udp: None,
+                    all: None,
+                    now: None,
+                },
+            )]),
+        },
+    );
+    let content = |compact| {
+        group
+            .get_widget(
+                Rect::new(0, 0, 80, 10),
+                &LatencyThresholds::default(),
+                &Theme::default(),
+                ProxyGroupFocusStatus::Expanded,
+                false,
+                compact,
+            )
+            .into_iter()
+            .flat_map(|x| x.0.into_iter().map(|x| x.content.into_owned()))
+            .collect::<Vec<_>>()
+    };
+
+    let full = content(false);
+    assert!(full.contains(&"Selector".to_owned()));
+    assert!(full.contains(&"ShadowsocksR".to_owned()));
+
+    let compact = content(true);
+    assert!(compact.contains(&"SEL".to_owned()));
+    assert!(compact.contains(&"SSR".to_owned()));
+    assert!(!compact.contains(&"ShadowsocksR".to_owned()));
+}
+
+#[test]
+fn test_widget_height() {
+    let item = |i: usize| ProxyItem::test(&format!("node-{}", i));
+    let mut group = ProxyGroup {
+        members: (0..500).map(item).collect(),
+        ..Default::default()
+    };
+    let widget = |group: &ProxyGroup, height, status| {
+        group
+            .get_widget(
+                Rect::new(0, 0, 40, height),
+                &LatencyThresholds::default(),
+                &Theme::default(),
+                status,
+                false,
+                false,
+            )
+            .into_iter()
+            .map(|x| x.0.iter().map(|x| x.content.as_ref()).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+
+    for height in [0, 1, 2, 7, 30] {
+        for cursor in [0, 3, 250, 499] {
+            group.cursor = cursor;
+            let lines = widget(&group, height, ProxyGroupFocusStatus::Expanded);
+            assert!(lines.len() <= (height as usize).max(1));
+            if height < 2 {
+                continue;
+            }
+            let name = format!("node-{} ", cursor);
+            let row = lines.iter().position(|x| x.contains(&name)).unwrap();
+            // Cursor stays centered unless it's close to either end
+            if (5..495).contains(&cursor) {
+                assert_eq!(row, 1 + (height as usize - 1) / 2);
+            }
+        }
+        let lines = widget(&group, height, ProxyGroupFocusStatus::None);
+        assert!(lines.len() <= (height as usize).max(1));
+    }
+
+    group.expanded = true;
+    assert_eq!(widget(&group, 7, ProxyGroupFocusStatus::None).len(), 7);
+}
+
+#[test]
+fn test_narrow_summary() {
+    let group = ProxyGroup {
+        members: (0..20)
+            .map(|i| ProxyItem::test(&format!("node-{}", i)))
+            .collect(),
+        ..Default::default()
+    };
+    for width in [0, 1, 4, 10, 30] {
+        let lines = group.get_widget(
+            Rect::new(0, 0, width, 10),
+            &LatencyThresholds::default(),
+            &Theme::default(),
+            ProxyGroupFocusStatus::Focused,
+            false,
+            false,
+        );
+        assert!(lines.len() <= 10);
+    }
+}
+
+#[test]
+fn test_latency_stats() {
+    let item = |status| ProxyItem {
+        status,
+        ..ProxyItem::test("")
+    };
+    let group = |statuses: Vec<TestStatus>| ProxyGroup {
+        members: statuses.into_iter().map(item).collect(),
+        ..Default::default()
+    };
+
+    assert_eq!(group(vec![]).latency_stats(), None);
+    assert_eq!(
+        group(vec![TestStatus::Untested, TestStatus::Timeout]).latency_stats(),
+        None
+    );
+    assert_eq!(
+        group(vec![
+            TestStatus::Ok(300),
+            TestStatus::Timeout,
+            TestStatus::Ok(100),
+            TestStatus::Ok(200),
+        ])
+        .latency_stats(),
+        Some(LatencyStats {
+            min: 100,
+            median: 200
+        })
+    );
+    assert_eq!(
+        group(vec![TestStatus::Ok(300), TestStatus::Ok(100)]).latency_stats(),
+        Some(LatencyStats {
+            min: 100,
+            median: 200
+        })
+    );
+}
+
+#[test]
+fn test_current_changed() {
+    let member = |proxy_type| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        all: None,
+        now: None,
+    };
+    let proxies = Proxies {
+        proxies: HashMap::from([
+            ("a".to_owned(), member(ProxyType::Shadowsocks)),
+            ("b".to_owned(), member(ProxyType::Vmess)),
+        ]),
+    };
+    let group = |now: &str| Proxy {
+        all: Some(vec!["a".into(), "b".into()]),
+        now: Some(now.into()),
+        ..member(ProxyType::Selector)
+    };
+
+    let mut proxy_group = ProxyGroup::new("Proxy", &group("a"), &proxies);
+    assert!(!proxy_group.is_flashing());
+
+    // Same current member, with other data changed
+    let mut udp = group("a");
+    udp.udp = Some(true);
+    proxy_group.update(&udp, &proxies);
+    assert!(!proxy_group.is_flashing());
+
+    proxy_group.update(&group("b"), &proxies);
+    assert!(proxy_group.is_flashing());
+    assert_eq!(proxy_group.current, Some(1));
+
+    proxy_group.current_changed = Some(Instant::now() - CURRENT_FLASH);
+    assert!(!proxy_group.is_flashing());
+}
+
+#[test]
+fn test_aligned_members() {
+    let item = ProxyItem::test;
+    let group = ProxyGroup {
+        members: ["a", "香港 01", "Singapore"].map(item).into(),
+        ..Default::default()
+    };
+    let lines = group.get_widget(
+        Rect::new(0, 0, 60, 10),
+        &LatencyThresholds::default(),
+        &Theme::default(),
+        ProxyGroupFocusStatus::Expanded,
+        false,
+        false,
+    );
+    let columns = lines[1..]
+        .iter()
+        .map(|line| {
+            let text = line.0.iter().map(|x| x.content.as_ref()).collect::<String>();
+            text[..text.find("Shadowsocks").unwrap()].width()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(columns.len(), 3);
+    assert!(columns.iter().all(|x| *x == columns[0]));
+}
+
+#[test]
+fn test_group_stats() {
+    let item = |proxy_type, status| ProxyItem {
+        proxy_type,
+        status,
+        ..ProxyItem::test("")
+    };
+    let group = ProxyGroup {
+        members: vec![
+            item(ProxyType::Shadowsocks, TestStatus::Ok(100)),
+            item(ProxyType::Shadowsocks, TestStatus::Ok(200)),
+            item(ProxyType::Vmess, TestStatus::Ok(300)),
+            item(ProxyType::Vmess, TestStatus::Ok(1000)),
+            item(ProxyType::Trojan, TestStatus::Timeout),
+            item(ProxyType::Trojan, TestStatus::Untested),
+            item(ProxyType::Direct, TestStatus::Untested),
+            item(ProxyType::Selector, TestStatus::Ok(100)),
+        ],
+        ..Default::default()
+    };
+    let stats = group.stats(&LatencyThresholds::default());
+    assert_eq!(
+        stats,
+        GroupStats {
+            low: 2,
+            mid: 1,
+            high: 1,
+            timeout: 1,
+            untested: 1,
+        }
+    );
+    assert_eq!(stats.total(), 6);
+}
+
+#[test]
+fn test_expanded_group_snapshot() {
+    use tui::widgets::Paragraph;
+
+    use crate::ui::utils::render_to_string;
+
+    let item = |(name, proxy_type, delay): (&str, ProxyType, Option<u64>)| {
+        let status = delay.map_or(TestStatus::Untested, TestStatus::from_delay);
+        ProxyItem {
+            proxy_type,
+            status,
+            udp: Some(true),
+            ..ProxyItem::test(name)
+        }
+    };
+    let group = ProxyGroup {
+        name: "Proxy".to_owned(),
+        proxy_type: ProxyType::Selector,
+        members: [
+            ("HK 01", ProxyType::Shadowsocks, Some(80)),
+            ("HK 02", ProxyType::Shadowsocks, Some(250)),
+            ("JP 01", ProxyType::Vmess, Some(600)),
+            ("SG 01", ProxyType::Trojan, Some(0)),
+            ("DIRECT", ProxyType::Direct, None),
+        ]
+        .map(item)
+        .into(),
+        current: Some(1),
+        cursor: 2,
+        ..Default::default()
+    };
+    let lines = group.get_widget(
+        Rect::new(0, 0, 50, 6),
+        &LatencyThresholds::default(),
+        &Theme::default(),
+        ProxyGroupFocusStatus::Expanded,
+        false,
+        false,
+    );
+    let text = render_to_string(Paragraph::new(lines), 50, 6);
+    assert_eq!(
+        text,
+        [
+            "   Proxy Selector 3/5",
+            "🮇    HK 01  Shadowsocks U   80",
+            "🮇    HK 02  Shadowsocks U  250",
+            "🮇  ➤ JP 01  Vmess U  600",
+            "🮇    SG 01  Trojan U    ✗",
+            "🮇    DIRECT Direct U",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn test_summary_in_narrow_area() {
+    use tui::widgets::Paragraph;
+
+    use crate::ui::utils::render_to_string;
+
+    let item = ProxyItem::test;
+    let group = ProxyGroup {
+        members: ["a", "b", "c"].map(item).into(),
+        ..Default::default()
+    };
+    // Narrower than the indicator, chunk size must not be zero
+    for width in 0..8 {
+        assert!(ProxyGroup::summary_chunk_size(width) >= 1);
+        let lines = group.get_widget(
+            Rect::new(0, 0, width as u16, 4),
+            &LatencyThresholds::default(),
+            &Theme::default(),
+            ProxyGroupFocusStatus::Focused,
+            false,
+            false,
+        );
+        assert_eq!(lines.len(), 4);
+    }
+    for width in 1..=4 {
+        let lines = group.get_widget(
+            Rect::new(0, 0, width, 4),
+            &LatencyThresholds::default(),
+            &Theme::default(),
+            ProxyGroupFocusStatus::None,
+            false,
+            false,
+        );
+        assert_eq!(lines.len(), 4);
+        // Doesn't panic when drawn either
+        render_to_string(Paragraph::new(lines), width, 4);
+    }
+}
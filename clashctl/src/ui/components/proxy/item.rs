@@ -1,12 +1,59 @@
 use std::fmt::Debug;
 
+use smart_default::SmartDefault;
+
 use crate::clashctl::model::{History, Proxy, ProxyType};
 
+/// Max number of delay samples kept for each proxy
+pub const MAX_HISTORY: usize = 10;
+
+/// Result of the latest delay test of a proxy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, SmartDefault)]
+pub enum TestStatus {
+    #[default]
+    Untested,
+    Ok(u64),
+    Timeout,
+}
+
+impl TestStatus {
+    /// Clash records failed tests as zero delay
+    pub fn from_delay(delay: u64) -> Self {
+        match delay {
+            0 => Self::Timeout,
+            delay => Self::Ok(delay),
+        }
+    }
+
+    fn of(history: &[History]) -> Self {
+        latest_history(history).map_or(Self::Untested, |x| Self::from_delay(x.delay))
+    }
+}
+
+/// Latest sample by time. Clash doesn't promise any order of history
+pub fn latest_history(history: &[History]) -> Option<&History> {
+    history.iter().max_by(|a, b| a.time.cmp(&b.time))
+}
+
+/// Sort `history` oldest first and keep the last [`MAX_HISTORY`] samples
+fn sort_history(history: &mut Vec<History>) {
+    history.sort_by_key(|x| x.time);
+    history.drain(..history.len().saturating_sub(MAX_HISTORY));
+}
+
+fn recent_history(history: &[History]) -> Vec<History> {
+    let mut ret = history.to_vec();
+    sort_history(&mut ret);
+    ret
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ProxyItem {
     pub(super) name: String,
     pub(super) proxy_type: ProxyType,
-    pub(super) history: Option<History>,
+    // Delay samples, oldest first
+    pub(super) history: Vec<History>,
+    pub(super) status: TestStatus,
     pub(super) udp: Option<bool>,
     pub(super) now: Option<String>,
 }
@@ -16,8 +63,9 @@ impl<'a> From<(&'a str, &'a Proxy)> for ProxyItem {
         let (name, proxy) = val;
         Self {
             name: name.to_owned(),
-            proxy_type: proxy.proxy_type,
-            history: proxy.history.get(0).cloned(),
+            proxy_type: proxy.proxy_type.clone(),
+            history: recent_history(&proxy.history),
+            status: TestStatus::of(&proxy.history),
             udp: proxy.udp,
             now: proxy.now.as_ref().map(Into::into),
         }
@@ -25,15 +73,97 @@ impl<'a> From<(&'a str, &'a Proxy)> for ProxyItem {
 }
 
 impl ProxyItem {
-    pub fn proxy_type(&self) -> ProxyType {
-        self.proxy_type
+    /// Update with latest data of the same proxy, reusing allocations
+    pub(super) fn update(&mut self, proxy: &Proxy) {
+        self.proxy_type = proxy.proxy_type.clone();
+        self.udp = proxy.udp;
+        self.history.clone_from(&proxy.history);
+        sort_history(&mut self.history);
+        self.status = TestStatus::of(&self.history);
+        match (&mut self.now, &proxy.now) {
+            (Some(now), Some(new)) => now.clone_from(new),
+            (now, new) => *now = new.clone(),
+        }
+    }
+
+    pub fn proxy_type(&self) -> &ProxyType {
+        &self.proxy_type
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn is_udp(&self) -> bool {
+        self.udp.unwrap_or(false)
+    }
+
+    /// Recent delay samples, oldest first
+    pub fn history(&self) -> &[History] {
+        &self.history
+    }
+
+    /// Current member, if it's a group itself
+    pub fn now(&self) -> Option<&str> {
+        self.now.as_deref()
+    }
+
+    pub fn latest(&self) -> Option<&History> {
+        self.history.last()
+    }
+
     pub fn delay(&self) -> Option<u64> {
-        self.history.as_ref().map(|x| x.delay)
+        self.latest().map(|x| x.delay)
+    }
+
+    pub fn status(&self) -> TestStatus {
+        self.status
+    }
+
+    /// Record a new delay sample, dropping the oldest one if full
+    pub fn push_history(&mut self, history: History) {
+        if self.history.len() >= MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.status = TestStatus::from_delay(history.delay);
+        self.history.push(history)
+    }
+}
+
+#[cfg(test)]
+impl ProxyItem {
+    /// Untested Shadowsocks proxy without history, to build fixtures from
+    pub(crate) fn test(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            proxy_type: ProxyType::Shadowsocks,
+            history: vec![],
+            status: TestStatus::Untested,
+            udp: None,
+            now: None,
+        }
     }
 }
+
+#[test]
+fn test_push_history() {
+    use chrono::Utc;
+
+    let mut item = ProxyItem::test("a");
+    for delay in 0..MAX_HISTORY as u64 + 5 {
+        item.push_history(History {
+            time: Utc::now(),
+            delay,
+        });
+    }
+    assert_eq!(item.history.len(), MAX_HISTORY);
+    assert_eq!(item.history[0].delay, 5);
+    assert_eq!(item.delay(), Some(MAX_HISTORY as u64 + 4));
+    assert_eq!(item.status(), TestStatus::Ok(MAX_HISTORY as u64 + 4));
+
+    item.push_history(History {
+        time: Utc::now(),
+        delay: 0,
+    });
+    assert_eq!(item.status(), TestStatus::Timeout);
+}
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, mem};
 
 use crate::{
     components::{ProxyGroup, ProxyItem, ProxyTree},
@@ -40,19 +40,26 @@ impl<'a> Sortable<'a, ProxySort> for ProxyGroup<'a> {
     type Item<'b> = ProxyItem;
 
     fn sort_with(&mut self, method: &ProxySort) {
-        let pointed = &self.members[self.cursor].name.clone();
-        let current = self.current.map(|x| self.members[x].name.clone());
-        self.members.sort_by(|a, b| method.sort_fn(a, b));
-        for (i, ProxyItem { name, .. }) in self.members.iter().enumerate() {
-            if name == pointed {
-                self.cursor = i;
-            }
-            if let Some(ref x) = current {
-                if name == x {
-                    self.current = Some(i)
-                }
-            }
+        // Sort indices instead of members, so pointed and current members can
+        // be followed by position without cloning their names
+        let mut order = (0..self.members.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| method.sort_fn(&self.members[*a], &self.members[*b]));
+
+        let position = |old: usize| order.iter().position(|x| *x == old);
+        if let Some(cursor) = position(self.cursor) {
+            self.cursor = cursor;
         }
+        self.current = self.current.and_then(position);
+
+        let mut members = mem::take(&mut self.members)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.members = order
+            .into_iter()
+            .filter_map(|x| members[x].take())
+            .collect();
+        self.refresh_order();
     }
 }
 
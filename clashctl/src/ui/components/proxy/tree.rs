@@ -1,16 +1,30 @@
-use std::{cmp::Ordering, collections::HashMap, fmt::Debug, marker::PhantomData};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    mem,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
-use clashctl_core::model::Proxies;
-use crossterm::event::KeyCode;
+use chrono::{Local, Utc};
+use clashctl_core::model::{History, Proxies, ProxyType};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use tui::{
+    layout::Rect,
     style::{Color, Modifier, Style},
     text::Span,
 };
 
 use crate::{
-    components::{Footer, FooterItem, MovableListManage, ProxyGroup, ProxyItem},
-    interactive::{EndlessSelf, ProxySort, Sortable},
-    ui::{help_footer, tagged_footer, Action, Coord, ListEvent, Wrap},
+    components::{
+        hash_group, Footer, FooterItem, GroupStats, MovableListManage, ProxyGroup, TestStatus,
+    },
+    interactive::{EndlessSelf, GlobalGroup, LatencyThresholds, ProxySort, Sortable, Theme},
+    ui::{
+        help_footer, tagged_footer, Action, Coord, GroupExport, ListEvent, MemberExport,
+        ProxyTreeExport, ProxyTreeSnapshot, Wrap,
+    },
 };
 
 // TODO Proxy tree furthur functions
@@ -19,22 +33,215 @@ use crate::{
 // - [X] Esc for exist expand mode
 // - [X] T for test latency of current group
 // - [X] S for switch between sorting strategies
-// - [ ] / for searching
+// - [X] / for searching
+// - [X] za / zM / zR for folding groups
+// - [X] c for jumping to current proxy
+// - [X] h for hiding dead proxies
+// - [X] Mouse click & scroll
+// - [X] u for updating providers
+// - [X] Shift-T for testing all groups, Esc to cancel
+// - [X] Shift/Ctrl + Left & Right for scrolling long member names
 //
 // In order for functions to be implemented, these are required:
 // - Remove Enter from InterfaceEvent::ToggleHold
 // - Maybe a new InterfaceEvent::Confirm correstponds to Enter
 // - `T`, `S`, `/` in proxy event handling
+/// Columns member names are scrolled by with Shift/Ctrl + Left & Right
+const NAME_SCROLL_STEP: usize = 4;
+
+/// Types of groups shown in the tree, others are hidden but still merged
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GroupFilter {
+    #[default]
+    All,
+    // Groups whose current member can be changed
+    Selectable,
+    UrlTest,
+}
+
+impl GroupFilter {
+    pub fn next(self) -> Self {
+        match self {
+            GroupFilter::All => GroupFilter::Selectable,
+            GroupFilter::Selectable => GroupFilter::UrlTest,
+            GroupFilter::UrlTest => GroupFilter::All,
+        }
+    }
+
+    pub fn matches(self, proxy_type: &ProxyType) -> bool {
+        match self {
+            GroupFilter::All => true,
+            GroupFilter::Selectable => proxy_type.is_selector(),
+            GroupFilter::UrlTest => proxy_type == &ProxyType::URLTest,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupFilter::All => "all",
+            GroupFilter::Selectable => "selectable",
+            GroupFilter::UrlTest => "url-test",
+        }
+    }
+}
+
+/// Order of groups in the tree, below pinned ones. Groups are only reordered,
+/// so merging still matches them by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GroupSort {
+    // Groups used by more rules first
+    #[default]
+    Usage,
+    Name,
+    Type,
+    // Groups with more members first
+    Members,
+}
+
+impl GroupSort {
+    pub fn next(self) -> Self {
+        match self {
+            GroupSort::Usage => GroupSort::Name,
+            GroupSort::Name => GroupSort::Type,
+            GroupSort::Type => GroupSort::Members,
+            GroupSort::Members => GroupSort::Usage,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupSort::Usage => "usage",
+            GroupSort::Name => "name",
+            GroupSort::Type => "type",
+            GroupSort::Members => "members",
+        }
+    }
+
+    /// Unused groups in `freq` go after used ones when sorting by usage. Ties
+    /// are broken by name
+    fn cmp(self, a: &ProxyGroup, b: &ProxyGroup, freq: &HashMap<String, usize>) -> Ordering {
+        let ordering = match self {
+            GroupSort::Usage => match (freq.get(&a.name), freq.get(&b.name)) {
+                (Some(a_freq), Some(b_freq)) => b_freq.cmp(a_freq),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            GroupSort::Name => Ordering::Equal,
+            GroupSort::Type => a.proxy_type.cmp(&b.proxy_type),
+            GroupSort::Members => b.members.len().cmp(&a.members.len()),
+        };
+        ordering.then_with(|| a.name.cmp(&b.name))
+    }
+}
+
+/// Change from Clash queued with [`ProxyTree::queue`], so changes arriving in
+/// quick succession are applied together by [`ProxyTree::flush`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TreeUpdate<'a> {
+    Proxies(Proxies),
+    Groups(Vec<ProxyGroup<'a>>),
+    // Delay of proxies, `None` for failed ones
+    Delays(HashMap<String, Option<u64>>),
+    Selected { group: String, proxy: String },
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ProxyTree<'a> {
     pub(super) groups: Vec<ProxyGroup<'a>>,
+    // Whether cursor is inside current group, moving among its members.
+    // Groups have their own expansion state, see [`ProxyGroup::is_expanded`]
     pub(super) expanded: bool,
     pub(super) cursor: usize,
-    pub(super) testing: bool,
+    // Names of groups with latency tests in flight
+    pub(super) testing: HashSet<String>,
+    // (done, total) groups when testing all groups
+    pub(super) sweep: Option<(usize, usize)>,
+    // When updating providers of current group started, if being updated
+    pub(super) updating: Option<Instant>,
+    pub(super) searching: bool,
+    // Query of search across all groups, see [`ProxyTree::find`]. Typed in
+    // while `searching`, like in-group search
+    pub(super) global_query: Option<String>,
+    // Brief message shown in footer, cleared on next input
+    pub(super) notice: Option<String>,
     pub(super) footer: Footer<'a>,
+    pub(super) thresholds: LatencyThresholds,
+    pub(super) theme: Theme,
+    // Show proxy types as short codes
+    pub(super) compact: bool,
+    // Show collapsed groups as their header only, without latency signs
+    pub(super) single_line: bool,
+    // Name of the pointed member of groups that disappeared on refresh, keyed
+    // by group name, restored once the group reappears
+    pub(super) stashed_cursors: HashMap<String, String>,
+    pub(super) hits: TreeHits,
+    // Snapshot to be restored once groups are loaded
+    pub(super) pending_restore: Option<ProxyTreeSnapshot>,
+    // Hash of proxies last merged, see [`ProxyTree::merge`]
+    pub(super) proxies_hash: Option<u64>,
+    // Names of groups sorted to top regardless of rule frequency
+    pub(super) pinned: HashSet<String>,
+    // Member of `GLOBAL` selected before it's flipped to `DIRECT`, see
+    // [`ProxyTree::flip_global_direct`]
+    pub(super) global_previous: Option<String>,
+    // Aliases of proxies shown in place of their names, keyed by names
+    pub(super) aliases: HashMap<String, String>,
+    // Alias being typed for the pointed member, see [`ProxyTree::start_alias`]
+    pub(super) alias_input: Option<String>,
+    pub(super) global_group: GlobalGroup,
+    // Move from last group or member to the first one and back
+    pub(super) wrap_navigation: bool,
+    pub(super) group_filter: GroupFilter,
+    pub(super) group_sort: GroupSort,
+    // Rule frequency groups were last sorted with, kept for resorting when
+    // `group_sort` changes
+    group_freq: HashMap<String, usize>,
+    // Updates not applied yet, in order of arrival
+    pending: Vec<TreeUpdate<'a>>,
     sort_method: ProxySort,
 }
 
+/// Group & member shown on each line of last rendered tree, used to map mouse
+/// events back to the tree. Written by [`super::ProxyTreeWidget`] on render.
+///
+/// This is a render cache shared between clones, so it's not compared
+#[derive(Clone, Debug, Default)]
+pub struct TreeHits(Arc<Mutex<TreeHitsInner>>);
+
+#[derive(Debug, Default)]
+struct TreeHitsInner {
+    area: Rect,
+    lines: Vec<(usize, Option<usize>)>,
+}
+
+impl TreeHits {
+    pub(super) fn record(&self, area: Rect, lines: Vec<(usize, Option<usize>)>) {
+        *self.0.lock().unwrap() = TreeHitsInner { area, lines };
+    }
+
+    /// Forget last rendered lines, nothing is hit until next render
+    pub(super) fn clear(&self) {
+        *self.0.lock().unwrap() = Default::default();
+    }
+
+    /// Group & member at terminal position, `None` if nothing is there
+    pub(super) fn get(&self, column: u16, row: u16) -> Option<(usize, Option<usize>)> {
+        let inner = self.0.lock().unwrap();
+        let area = inner.area;
+        if column < area.x || column >= area.right() || row < area.y || row >= area.bottom() {
+            return None;
+        }
+        inner.lines.get((row - area.y) as usize).copied()
+    }
+}
+
+impl PartialEq for TreeHits {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
 impl<'a> Default for ProxyTree<'a> {
     fn default() -> Self {
         let mut ret = Self {
@@ -43,49 +250,946 @@ impl<'a> Default for ProxyTree<'a> {
             cursor: Default::default(),
             footer: Default::default(),
             testing: Default::default(),
+            sweep: Default::default(),
+            updating: Default::default(),
+            searching: Default::default(),
+            global_query: Default::default(),
+            notice: Default::default(),
+            thresholds: Default::default(),
+            theme: Default::default(),
+            compact: Default::default(),
+            single_line: Default::default(),
+            stashed_cursors: Default::default(),
+            hits: Default::default(),
+            pending_restore: Default::default(),
+            proxies_hash: Default::default(),
+            pinned: Default::default(),
+            global_previous: Default::default(),
+            aliases: Default::default(),
+            alias_input: Default::default(),
+            global_group: Default::default(),
+            wrap_navigation: Default::default(),
+            group_filter: Default::default(),
+            group_sort: Default::default(),
+            group_freq: Default::default(),
+            pending: Default::default(),
             sort_method: Default::default(),
         };
         ret.update_footer();
         ret
     }
-}
+}
+
+impl<'a> ProxyTree<'a> {
+    pub fn with_thresholds(thresholds: LatencyThresholds) -> Self {
+        Self {
+            thresholds,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    pub fn with_single_line(mut self, single_line: bool) -> Self {
+        self.single_line = single_line;
+        self
+    }
+
+    pub fn with_pinned(mut self, pinned: impl IntoIterator<Item = String>) -> Self {
+        self.pinned = pinned.into_iter().collect();
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.aliases = aliases.into_iter().collect();
+        self
+    }
+
+    pub fn with_global_group(mut self, global_group: GlobalGroup) -> Self {
+        self.global_group = global_group;
+        self
+    }
+
+    pub fn with_wrap_navigation(mut self, wrap_navigation: bool) -> Self {
+        self.wrap_navigation = wrap_navigation;
+        self
+    }
+
+    /// Pin current group or unpin it if it's pinned. Returns name of the group
+    /// and whether it's pinned now. Groups are not resorted until
+    /// [`ProxyTree::sort_groups_with_frequency`]
+    pub fn toggle_pin(&mut self) -> Option<(String, bool)> {
+        let group = self.groups.get_mut(self.cursor)?;
+        group.pinned = !group.pinned;
+        if group.pinned {
+            self.pinned.insert(group.name.to_owned());
+        } else {
+            self.pinned.remove(&group.name);
+        }
+        Some((group.name.to_owned(), group.pinned))
+    }
+
+    /// Member to select in `GLOBAL` group to flip it between `DIRECT` and the
+    /// proxy selected before. Errs with a hint if there's nothing to flip to
+    pub fn flip_global_direct(&mut self) -> Result<String, &'static str> {
+        let group = self
+            .groups
+            .iter()
+            .find(|x| x.is_global())
+            .ok_or("No GLOBAL group to flip")?;
+        let direct = group
+            .members
+            .iter()
+            .find(|x| x.name == "DIRECT")
+            .or_else(|| {
+                group
+                    .members
+                    .iter()
+                    .find(|x| x.proxy_type == ProxyType::Direct)
+            })
+            .ok_or("No DIRECT in GLOBAL group")?;
+        let current = group.current();
+
+        match current {
+            Some(current) if current.name == direct.name => self
+                .global_previous
+                .clone()
+                .filter(|x| group.members.iter().any(|member| &member.name == x))
+                .ok_or("GLOBAL was not flipped to DIRECT here, nothing to flip back to"),
+            _ => {
+                let direct = direct.name.clone();
+                self.global_previous = current.map(|x| x.name.clone());
+                Ok(direct)
+            }
+        }
+    }
+
+    /// Group under cursor and its member after (or before if `back`) the
+    /// current one, wrapping around. Hidden members are skipped. Errs with a
+    /// hint if the group is expanded or not a selector
+    pub fn cycle_selection(&self, back: bool) -> Result<(String, String), String> {
+        if self.expanded {
+            return Err("Press Enter on a member to select it".to_owned());
+        }
+        let group = self.groups.get(self.cursor).ok_or("No group to cycle")?;
+        if !group.proxy_type.is_selector() {
+            return Err(format!("{} is not selectable", group.proxy_type));
+        }
+        let visible = group.visible_members();
+        let pos = group
+            .current
+            .and_then(|current| visible.iter().position(|&x| x == current));
+        let next = match (pos, back) {
+            (Some(pos), false) => visible.get(pos + 1).or_else(|| visible.first()),
+            (Some(pos), true) => pos
+                .checked_sub(1)
+                .and_then(|x| visible.get(x))
+                .or_else(|| visible.last()),
+            (None, false) => visible.first(),
+            (None, true) => visible.last(),
+        };
+        match next {
+            Some(&next) if Some(next) != group.current => {
+                Ok((group.name.to_owned(), group.members[next].name.to_owned()))
+            }
+            _ => Err(format!("No other member of {} to select", group.name)),
+        }
+    }
+
+    /// Latency bands of current group, see [`ProxyGroup::stats`]
+    pub fn current_stats(&self) -> Option<(&str, GroupStats)> {
+        let group = self.groups.get(self.cursor)?;
+        Some((&group.name, group.stats(&self.thresholds)))
+    }
+
+    /// Name of each group with its current member and the member's latest
+    /// delay, in display order. Member is `None` for groups without one, and
+    /// delay is `None` if the member is untested or failed
+    pub fn current_selections(&self) -> Vec<(String, Option<String>, Option<u64>)> {
+        self.groups
+            .iter()
+            .map(|group| {
+                let current = group.current();
+                let delay = current.and_then(|x| match x.status() {
+                    TestStatus::Ok(delay) => Some(delay),
+                    _ => None,
+                });
+                (group.name.to_owned(), current.map(|x| x.name.to_owned()), delay)
+            })
+            .collect()
+    }
+
+    pub fn thresholds(&self) -> &LatencyThresholds {
+        &self.thresholds
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    #[inline]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    #[inline]
+    pub fn current_group(&self) -> &ProxyGroup {
+        &self.groups[self.cursor]
+    }
+
+    /// Like [`ProxyTree::current_group`], but `None` if there's no group
+    #[inline]
+    pub fn get_current_group(&self) -> Option<&ProxyGroup> {
+        self.groups.get(self.cursor)
+    }
+
+    #[inline]
+    pub fn groups(&self) -> &[ProxyGroup<'a>] {
+        &self.groups
+    }
+
+    /// Groups in display order, including hidden ones
+    pub fn iter_groups(&self) -> impl Iterator<Item = &ProxyGroup<'a>> + '_ {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ProxyGroup<'a>> {
+        self.groups.iter().find(|x| x.name == name)
+    }
+
+    /// Name of pointed member when inside a group, name of the group otherwise
+    pub fn focused_name(&self) -> Option<&str> {
+        let (group, member) = self.focused()?;
+        Some(member.unwrap_or(group))
+    }
+
+    /// Names of current group and its pointed member, if inside the group
+    pub fn focused(&self) -> Option<(&str, Option<&str>)> {
+        let group = self.groups.get(self.cursor)?;
+        let member = if self.expanded && group.visible_members().contains(&group.cursor) {
+            group.members.get(group.cursor).map(|x| x.name.as_str())
+        } else {
+            None
+        };
+        Some((&group.name, member))
+    }
+
+    pub fn set_notice(&mut self, notice: impl Into<String>) -> &mut Self {
+        self.notice = Some(notice.into());
+        self.update_footer()
+    }
+
+    /// Whether latency of any group is being tested
+    #[inline]
+    pub fn is_testing(&self) -> bool {
+        !self.testing.is_empty()
+    }
+
+    #[inline]
+    pub fn is_testing_group(&self, group: &str) -> bool {
+        self.testing.contains(group)
+    }
+
+    #[inline]
+    pub fn start_testing(&mut self, group: &str) -> &mut Self {
+        self.testing.insert(group.to_owned());
+        self.update_footer()
+    }
+
+    /// Start testing the pointed member alone, marking it in every group it's
+    /// in until its delay is merged. `None` if cursor is not on a proxy, or
+    /// it's being tested already
+    pub fn start_testing_member(&mut self) -> Option<String> {
+        let (_, member) = self.focused()?;
+        let name = member?.to_owned();
+        let group = self.current_group();
+        let item = group.members.iter().find(|x| x.name == name)?;
+        if !item.proxy_type.is_normal() || group.testing_members.contains_key(&name) {
+            return None;
+        }
+        let now = Instant::now();
+        for group in self.groups.iter_mut() {
+            if group.members.iter().any(|x| x.name == name) {
+                group.testing_members.insert(name.to_owned(), now);
+            }
+        }
+        Some(name)
+    }
+
+    /// Show an `older` or newer delay sample of pointed member, telling in
+    /// footer if there's none further
+    pub fn step_history(&mut self, older: bool) -> &mut Self {
+        if !matches!(self.focused(), Some((_, Some(_)))) {
+            return self;
+        }
+        if !self.groups[self.cursor].step_history(older) {
+            self.notice = Some(if older {
+                " No older samples ".to_owned()
+            } else {
+                " Showing latest sample ".to_owned()
+            });
+        }
+        self.update_footer()
+    }
+
+    #[inline]
+    /// Start testing all `groups`, see [`ProxyTree::sweep_progress`]
+    pub fn start_sweep(&mut self, groups: &[(String, Vec<String>)]) -> &mut Self {
+        self.testing
+            .extend(groups.iter().map(|(group, _)| group.to_owned()));
+        self.sweep = Some((0, groups.len()));
+        self.update_footer()
+    }
+
+    pub fn sweep_progress(&mut self, done: usize, total: usize) -> &mut Self {
+        if self.sweep.is_some() {
+            self.sweep = Some((done, total));
+        }
+        self.update_footer()
+    }
+
+    /// Groups of which latency can be tested, as (group, normal proxies)
+    pub fn testable_groups(&self) -> Vec<(String, Vec<String>)> {
+        self.groups
+            .iter()
+            .filter(|x| matches!(x.proxy_type, ProxyType::Selector | ProxyType::URLTest))
+            .map(|x| {
+                let proxies = x
+                    .members
+                    .iter()
+                    .filter(|x| x.proxy_type.is_normal())
+                    .map(|x| x.name.to_owned())
+                    .collect();
+                (x.name.to_owned(), proxies)
+            })
+            .collect()
+    }
+
+    fn testing_label(&self) -> String {
+        match self.sweep {
+            Some((done, total)) => format!(" Testing {}/{} groups ", done, total),
+            None => " Testing ".to_owned(),
+        }
+    }
+
+    /// Mark tests of `groups` as finished
+    pub fn end_testing(&mut self, groups: &[String]) -> &mut Self {
+        for group in groups {
+            self.testing.remove(group);
+        }
+        if self.testing.is_empty() {
+            self.sweep = None;
+        }
+        self.update_footer()
+    }
+
+    /// Whether footer should show testing label instead of hint for current
+    /// group
+    fn is_testing_current(&self) -> bool {
+        self.sweep.is_some()
+            || self
+                .groups
+                .get(self.cursor)
+                .is_some_and(|x| self.is_testing_group(&x.name))
+    }
+
+    #[inline]
+    pub fn is_updating(&self) -> bool {
+        self.updating.is_some()
+    }
+
+    pub fn start_updating(&mut self) -> &mut Self {
+        self.updating = Some(Instant::now());
+        self.notice = None;
+        self.update_footer()
+    }
+
+    pub fn end_updating(&mut self, group: &str, providers: &[String]) -> &mut Self {
+        self.updating = None;
+        self.notice = Some(if providers.is_empty() {
+            format!(" {} is not backed by a provider ", group)
+        } else {
+            format!(" Updated {} ", providers.join(", "))
+        });
+        self.update_footer()
+    }
+
+    /// Whether search input is being typed, all key events should be passed
+    /// to [`ProxyTree::handle_search`]
+    #[inline]
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    pub fn start_search(&mut self) -> &mut Self {
+        self.global_query = None;
+        if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.start_search();
+            group.expanded = true;
+            self.expanded = true;
+            self.searching = true;
+        }
+        self.update_footer()
+    }
+
+    pub fn start_global_search(&mut self) -> &mut Self {
+        self.global_query = Some(String::new());
+        self.searching = true;
+        self.update_footer()
+    }
+
+    pub fn end_global_search(&mut self) -> &mut Self {
+        self.global_query = None;
+        self.searching = false;
+        self.update_footer()
+    }
+
+    /// Group & member index of the first member matching `query`, looking
+    /// through all groups
+    pub fn find(&self, query: &str) -> Option<(usize, usize)> {
+        self.find_all(query).into_iter().next()
+    }
+
+    /// Group & member indices of all members matching `query`, in display order
+    fn find_all(&self, query: &str) -> Vec<(usize, usize)> {
+        self.groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| group.find(query).into_iter().map(move |j| (i, j)))
+            .collect()
+    }
+
+    /// Jump to the match of global search after the pointed member, wrapping
+    /// around to the first one
+    pub fn next_match(&mut self) -> &mut Self {
+        self.notice = None;
+        let query = match self.global_query {
+            Some(ref query) => query.to_owned(),
+            None => return self,
+        };
+        let hits = self.find_all(&query);
+        let pointed = self
+            .groups
+            .get(self.cursor)
+            .filter(|_| self.expanded)
+            .map(|group| (self.cursor, group.cursor));
+        let next = match pointed.and_then(|x| hits.iter().position(|hit| *hit == x)) {
+            Some(pos) => hits.get(pos + 1),
+            // Not at any match, start from current group
+            None => hits.iter().find(|(group, _)| *group >= self.cursor),
+        }
+        .or_else(|| hits.first())
+        .copied();
+        match next {
+            Some((group, member)) => self.jump_to(group, member),
+            None => self.notice = Some(format!(" Nothing matches {} ", query)),
+        }
+        self.update_footer()
+    }
+
+    /// Jump to the first match of global search
+    fn first_match(&mut self) {
+        self.notice = None;
+        let query = match self.global_query {
+            Some(ref query) if !query.is_empty() => query.to_owned(),
+            _ => return,
+        };
+        match self.find(&query) {
+            Some((group, member)) => self.jump_to(group, member),
+            None => self.notice = Some(format!(" Nothing matches {} ", query)),
+        }
+    }
+
+    /// Expand `group` and point at its `member`, collapsing the group cursor
+    /// was in
+    fn jump_to(&mut self, group: usize, member: usize) {
+        if self.expanded && self.cursor != group {
+            self.leave();
+        }
+        // Matches are searched in all groups
+        if !self.is_visible(group) {
+            self.group_filter = GroupFilter::All;
+        }
+        self.cursor = group;
+        let group = &mut self.groups[group];
+        group.clear_search();
+        group.cursor = member;
+        self.enter();
+    }
+
+    fn handle_global_search(&mut self, event: KeyEvent) {
+        let query = match self.global_query {
+            Some(ref mut query) => query,
+            None => return,
+        };
+        match event.code {
+            KeyCode::Char(c) => {
+                query.push(c);
+                self.first_match();
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                self.first_match();
+            }
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.end_global_search();
+            }
+            _ => {}
+        }
+        self.update_footer();
+    }
+
+    /// Expand current group and move cursor inside
+    fn enter(&mut self) -> &mut Self {
+        if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.expanded = true;
+            self.expanded = true;
+        }
+        self
+    }
+
+    /// Move cursor out of current group and collapse it
+    fn leave(&mut self) -> &mut Self {
+        self.end_search();
+        if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.expanded = false;
+            group.member_hscroll = 0;
+            group.history_back = None;
+        }
+        self.expanded = false;
+        self
+    }
+
+    /// Move cursor inside current group and point at its current member
+    pub fn jump_to_current(&mut self) -> &mut Self {
+        self.notice = None;
+        let group = match self.groups.get(self.cursor) {
+            Some(group) => group,
+            None => return self,
+        };
+        match group.current {
+            Some(current) => {
+                // Current member may be hidden by search
+                if !group.visible_members().contains(&current) {
+                    self.end_search();
+                }
+                self.enter();
+                self.groups[self.cursor].jump_to_current();
+            }
+            None => self.notice = Some(format!(" {} has no current proxy ", group.name)),
+        }
+        self.update_footer()
+    }
+
+    pub fn snapshot(&self) -> ProxyTreeSnapshot {
+        // Not loaded yet, keep what's waiting to be restored
+        if let Some(ref pending) = self.pending_restore {
+            return pending.clone();
+        }
+        ProxyTreeSnapshot {
+            group: self.groups.get(self.cursor).map(|x| x.name.to_owned()),
+            inside: self.expanded,
+            expanded_groups: self
+                .groups
+                .iter()
+                .filter(|x| x.expanded)
+                .map(|x| x.name.to_owned())
+                .collect(),
+        }
+    }
+
+    /// Plain copy of all groups with their members in original order, for
+    /// exporting. Unlike [`ProxyTree::snapshot`] it's about proxies rather
+    /// than position in the tree
+    pub fn to_export(&self) -> ProxyTreeExport {
+        ProxyTreeExport {
+            time: Local::now(),
+            groups: self
+                .groups
+                .iter()
+                .map(|group| GroupExport {
+                    name: group.name.to_owned(),
+                    proxy_type: group.proxy_type.clone(),
+                    now: group
+                        .current
+                        .and_then(|x| group.members.get(x))
+                        .map(|x| x.name.to_owned()),
+                    members: group
+                        .members
+                        .iter()
+                        .map(|x| MemberExport {
+                            name: x.name.to_owned(),
+                            proxy_type: x.proxy_type.clone(),
+                            udp: x.is_udp(),
+                            delay: x.delay(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restore `snapshot` when groups are loaded by [`ProxyTree::merge`]
+    pub fn restore_later(&mut self, snapshot: ProxyTreeSnapshot) -> &mut Self {
+        self.pending_restore = Some(snapshot);
+        self
+    }
+
+    fn restore(&mut self, snapshot: ProxyTreeSnapshot) {
+        let collapsed = self.global_group == GlobalGroup::Collapsed;
+        for group in self.groups.iter_mut() {
+            group.expanded = snapshot.expanded_groups.contains(&group.name)
+                && !(collapsed && group.is_global());
+        }
+        let found = snapshot
+            .group
+            .and_then(|name| self.groups.iter().position(|x| x.name == name));
+        self.cursor = found.unwrap_or_default();
+        self.expanded = false;
+        self.keep_cursor_visible();
+        if found.is_some_and(|x| x == self.cursor) && snapshot.inside {
+            self.enter();
+        }
+    }
+
+    /// Terminal is resized, so lines recorded by last render no longer match
+    /// what's on screen. Clicks are ignored until the tree is rendered again
+    pub fn resized(&mut self) {
+        self.hits.clear();
+    }
+
+    /// Click on group to enter or leave it, click on member to select it.
+    /// Scrolling moves cursor
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> Option<Action> {
+        let code = match event.kind {
+            MouseEventKind::ScrollUp => KeyCode::Up,
+            MouseEventKind::ScrollDown => KeyCode::Down,
+            MouseEventKind::Down(MouseButton::Left) => {
+                match self.hits.get(event.column, event.row)? {
+                    (group, None) if self.expanded && group == self.cursor => {
+                        self.leave();
+                    }
+                    (group, None) => {
+                        if self.expanded {
+                            self.leave();
+                        }
+                        self.cursor = group;
+                        self.enter();
+                    }
+                    (group, Some(member)) => {
+                        if self.expanded && group != self.cursor {
+                            self.leave();
+                        }
+                        self.cursor = group;
+                        self.enter();
+                        self.groups[group].cursor = member;
+                        return self.handle(ListEvent {
+                            fast: false,
+                            code: KeyCode::Enter,
+                        });
+                    }
+                }
+                self.update_footer();
+                return None;
+            }
+            _ => return None,
+        };
+        self.handle(ListEvent { fast: false, code })
+    }
+
+    /// Toggle expansion of current group without moving cursor inside
+    pub fn toggle_fold(&mut self) -> &mut Self {
+        if self.expanded {
+            self.leave();
+        } else if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.expanded = !group.expanded;
+        }
+        self.update_footer()
+    }
+
+    pub fn collapse_all(&mut self) -> &mut Self {
+        if self.expanded {
+            self.leave();
+        }
+        self.groups.iter_mut().for_each(|x| x.expanded = false);
+        self.update_footer()
+    }
+
+    pub fn expand_all(&mut self) -> &mut Self {
+        let collapsed = self.global_group == GlobalGroup::Collapsed;
+        self.groups
+            .iter_mut()
+            .for_each(|x| x.expanded = !(collapsed && x.is_global()));
+        self.update_footer()
+    }
+
+    pub fn end_search(&mut self) -> &mut Self {
+        if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.clear_search();
+        }
+        self.searching = false;
+        self.update_footer()
+    }
+
+    pub fn is_typing_alias(&self) -> bool {
+        self.alias_input.is_some()
+    }
+
+    /// Start typing alias of pointed member, beginning with its current alias.
+    /// Returns `false` if no member is pointed
+    pub fn start_alias(&mut self) -> bool {
+        let name = match self.focused() {
+            Some((_, Some(member))) => member.to_owned(),
+            _ => return false,
+        };
+        self.alias_input = Some(self.aliases.get(&name).cloned().unwrap_or_default());
+        true
+    }
+
+    /// Type alias of pointed member. On `Enter` it's applied, or cleared if
+    /// empty, and returned with name of the member to be saved
+    pub fn handle_alias(&mut self, event: KeyEvent) -> Option<(String, Option<String>)> {
+        let input = self.alias_input.as_mut()?;
+        match event.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.alias_input = None,
+            KeyCode::Enter => {
+                let alias = self
+                    .alias_input
+                    .take()
+                    .map(|x| x.trim().to_owned())
+                    .filter(|x| !x.is_empty());
+                let name = self.focused()?.1?.to_owned();
+                self.set_alias(&name, alias.clone());
+                return Some((name, alias));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Show `alias` in place of proxy `name`, or the name itself if `None`.
+    /// Selecting and testing still go by the name
+    pub fn set_alias(&mut self, name: &str, alias: Option<String>) -> &mut Self {
+        match alias {
+            Some(ref alias) => self.aliases.insert(name.to_owned(), alias.to_owned()),
+            None => self.aliases.remove(name),
+        };
+        for group in self.groups.iter_mut() {
+            if !group.members.iter().any(|x| x.name == name) {
+                continue;
+            }
+            match alias {
+                Some(ref alias) => group.aliases.insert(name.to_owned(), alias.to_owned()),
+                None => group.aliases.remove(name),
+            };
+        }
+        self
+    }
+
+    pub fn handle_search(&mut self, event: KeyEvent) -> Option<Action> {
+        if self.global_query.is_some() {
+            self.handle_global_search(event);
+            return None;
+        }
+        let group = match self.groups.get_mut(self.cursor) {
+            Some(group) => group,
+            None => {
+                self.searching = false;
+                return None;
+            }
+        };
+        match event.code {
+            KeyCode::Char(c) => group.push_search(c),
+            KeyCode::Backspace => group.pop_search(),
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.end_search();
+            }
+            code @ (KeyCode::Up | KeyCode::Down) => {
+                return self.handle(ListEvent {
+                    fast: matches!(event.modifiers, KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+                    code,
+                })
+            }
+            _ => {}
+        }
+        self.update_footer();
+        None
+    }
+
+    /// Mark `proxy` as the current one of `group`, after selection is applied
+    pub fn set_current(&mut self, group: &str, proxy: &str) -> &mut Self {
+        if let Some(group) = self.groups.iter_mut().find(|x| x.name == group) {
+            if let Some(index) = group.members.iter().position(|x| x.name == proxy) {
+                group.current = Some(index);
+            }
+        }
+        self.update_footer()
+    }
+
+    /// Toggle ordering members of expanded group by delay
+    pub fn toggle_delay_sort(&mut self) -> &mut Self {
+        if self.expanded {
+            if let Some(group) = self.groups.get_mut(self.cursor) {
+                group.toggle_delay_sort();
+            }
+        }
+        self
+    }
+
+    pub fn toggle_hide_dead(&mut self) -> &mut Self {
+        if let Some(group) = self.groups.get_mut(self.cursor) {
+            group.toggle_hide_dead();
+        }
+        self.update_footer()
+    }
+
+    /// Indices of groups passing [`GroupFilter`], in display order. `GLOBAL`
+    /// is left out if it's hidden
+    pub(super) fn visible_groups(&self) -> Vec<usize> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| self.shows(x))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn is_visible(&self, index: usize) -> bool {
+        self.groups.get(index).is_some_and(|x| self.shows(x))
+    }
+
+    fn shows(&self, group: &ProxyGroup) -> bool {
+        self.group_filter.matches(&group.proxy_type)
+            && !(self.global_group == GlobalGroup::Hidden && group.is_global())
+    }
+
+    /// Show only groups of the next [`GroupFilter`]
+    pub fn next_group_filter(&mut self) -> &mut Self {
+        self.group_filter = self.group_filter.next();
+        self.keep_cursor_visible();
+        self.update_footer()
+    }
+
+    /// Point to the first shown group once groups are loaded, instead of the
+    /// one Clash happens to list first. Skipped with [`GlobalGroup::Normal`],
+    /// which keeps the default cursor
+    fn point_to_top(&mut self) {
+        if self.global_group == GlobalGroup::Normal {
+            return;
+        }
+        self.cursor = 0;
+        self.keep_cursor_visible();
+    }
+
+    /// Move cursor off a hidden group, to the next visible one or the last
+    /// one if there's none below. Stays if all groups are hidden
+    fn keep_cursor_visible(&mut self) {
+        if self.is_visible(self.cursor) {
+            return;
+        }
+        let visible = self.visible_groups();
+        let next = visible
+            .iter()
+            .find(|&&x| x > self.cursor)
+            .or_else(|| visible.last());
+        if let Some(&next) = next {
+            if self.expanded {
+                self.leave();
+            }
+            self.cursor = next;
+        }
+    }
+
+    pub fn toggle_compact(&mut self) -> &mut Self {
+        self.compact = !self.compact;
+        self
+    }
 
-impl<'a> ProxyTree<'a> {
-    #[inline]
-    pub fn cursor(&self) -> usize {
-        self.cursor
+    pub fn toggle_single_line(&mut self) -> &mut Self {
+        self.single_line = !self.single_line;
+        self
     }
 
-    #[inline]
-    pub fn current_group(&self) -> &ProxyGroup {
-        &self.groups[self.cursor]
+    /// Record latest delay of proxies in all groups. `None` delay is recorded as
+    /// zero, same as how clash records failed tests
+    pub fn merge_delays(&mut self, delays: &HashMap<String, Option<u64>>) -> &mut Self {
+        let now = Utc::now();
+        for group in self.groups.iter_mut() {
+            group.testing_members.retain(|name, _| !delays.contains_key(name));
+        }
+        for member in self.groups.iter_mut().flat_map(|x| x.members.iter_mut()) {
+            if let Some(delay) = delays.get(&member.name) {
+                member.push_history(History {
+                    time: now,
+                    delay: delay.unwrap_or_default(),
+                });
+            }
+        }
+        let method = self.sort_method;
+        self.sort_with(&method);
+        // Pointed member may die and be hidden
+        self.groups.iter_mut().for_each(ProxyGroup::clamp_cursor);
+        self
     }
 
-    #[inline]
-    pub fn is_testing(&self) -> bool {
-        self.testing
+    /// Sort pinned groups to top, then the rest by [`GroupSort`], with `freq`
+    /// telling how many rules use each group. Cursor stays at the same group
+    pub fn sort_groups_with_frequency(&mut self, freq: &HashMap<String, usize>) -> &mut Self {
+        self.group_freq.clone_from(freq);
+        self.sort_groups()
     }
 
-    #[inline]
-    pub fn start_testing(&mut self) -> &mut Self {
-        self.testing = true;
-        self.update_footer()
+    pub fn group_sort(&self) -> GroupSort {
+        self.group_sort
     }
 
-    #[inline]
-    pub fn end_testing(&mut self) -> &mut Self {
-        self.testing = false;
-        self.update_footer()
+    /// Sort groups by the next [`GroupSort`]
+    pub fn next_group_sort(&mut self) -> &mut Self {
+        self.group_sort = self.group_sort.next();
+        self.sort_groups().update_footer()
     }
 
-    pub fn sort_groups_with_frequency(&mut self, freq: &HashMap<String, usize>) -> &mut Self {
-        self.groups
-            .sort_by(|a, b| match (freq.get(&a.name), freq.get(&b.name)) {
-                (Some(a_freq), Some(b_freq)) => b_freq.cmp(a_freq),
-                (Some(_), None) => Ordering::Less,
-                (None, Some(_)) => Ordering::Greater,
-                (None, None) => a.name.cmp(&b.name),
-            });
+    fn sort_groups(&mut self) -> &mut Self {
+        let current = self.groups.get(self.cursor).map(|x| x.name.clone());
+        for group in self.groups.iter_mut() {
+            group.pinned = self.pinned.contains(&group.name);
+            group.aliases = group
+                .members
+                .iter()
+                .filter_map(|x| Some((x.name.to_owned(), self.aliases.get(&x.name)?.to_owned())))
+                .collect();
+        }
+        let (sort, freq) = (self.group_sort, &self.group_freq);
+        let bottom = self.global_group == GlobalGroup::Bottom;
+        let at_bottom = |x: &ProxyGroup| bottom && x.is_global();
+        self.groups.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| at_bottom(a).cmp(&at_bottom(b)))
+                .then_with(|| sort.cmp(a, b, freq))
+        });
+        if let Some(current) = current {
+            self.cursor = self
+                .groups
+                .iter()
+                .position(|x| x.name == current)
+                .unwrap_or_default();
+        }
+        self.keep_cursor_visible();
         self
     }
 
@@ -108,8 +1212,8 @@ impl<'a> ProxyTree<'a> {
             let mut left = vec![
                 FooterItem::span(Span::styled(" FREE ", style)),
                 FooterItem::span(Span::styled(" SPACE to expand ", style)),
-                if self.testing {
-                    FooterItem::span(Span::styled(" Testing ", highlight.fg(Color::Green)))
+                if self.is_testing_current() {
+                    FooterItem::span(Span::styled(self.testing_label(), highlight.fg(Color::Green)))
                 } else {
                     FooterItem::spans(help_footer("Test", style, highlight)).wrapped()
                 },
@@ -135,100 +1239,226 @@ impl<'a> ProxyTree<'a> {
 
             footer.push_left(FooterItem::span(Span::styled(" [^] ▲ ▼ Move ", style)));
 
+            match (&self.global_query, &current_group.filter) {
+                (Some(query), _) => footer.push_left(FooterItem::span(Span::styled(
+                    format!(
+                        " f {}{} ({}) n Next ",
+                        query,
+                        if self.searching { "▏" } else { "" },
+                        self.find_all(query).len()
+                    ),
+                    highlight,
+                ))),
+                (None, Some(filter)) => footer.push_left(FooterItem::span(Span::styled(
+                    format!(" / {}{} ", filter, if self.searching { "▏" } else { "" }),
+                    highlight,
+                ))),
+                (None, None) => {
+                    footer.push_left(FooterItem::span(Span::styled(" / Search ", style)))
+                }
+            };
+
             if current_group.proxy_type.is_selector() {
                 footer.push_left(FooterItem::span(Span::styled(" ▶ Select ", style)));
             }
 
-            footer.push_left(if self.testing {
-                FooterItem::span(Span::styled(" Testing ", highlight.fg(Color::Blue)))
+            footer.push_left(if self.is_testing_current() {
+                FooterItem::span(Span::styled(self.testing_label(), highlight.fg(Color::Blue)))
             } else {
                 FooterItem::spans(help_footer("Test", style, highlight)).wrapped()
             });
 
             footer.push_left(tagged_footer("Sort", style, self.sort_method).into());
 
-            if let Some(ref now) = current_group.members[current_group.cursor].now {
+            if let Some(now) = current_group
+                .members
+                .get(current_group.cursor)
+                .and_then(|x| x.now.as_ref())
+            {
                 footer.push_right(FooterItem::span(Span::raw(now.to_owned())).wrapped());
             }
         }
+        if let Some(ref notice) = self.notice {
+            footer.push_right(FooterItem::span(Span::styled(
+                notice.to_owned(),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
         self.footer = footer;
         self
     }
 
-    pub fn replace_with(&mut self, mut new_tree: ProxyTree<'a>) -> &mut Self {
-        // let map = HashMap::<_, _, RandomState>::from_iter(self.groups.iter().map(|x|
-        // (&x.name, x)));
-        let old_groups = &self.groups;
-        let current_group = self.groups.get(self.cursor);
-        for (index, new_group) in new_tree.groups.iter_mut().enumerate() {
-            if let Some(true) = current_group.map(|x| x.name == new_group.name) {
-                new_tree.cursor = index;
+    /// Whether no proxies have been merged yet. Groups may still be added
+    /// with [`ProxyTree::extend_groups`] in the meantime
+    pub fn is_loading(&self) -> bool {
+        self.proxies_hash.is_none()
+    }
+
+    /// Queue `update` to be applied by next [`ProxyTree::flush`]. Proxies are
+    /// whole snapshots, so earlier queued ones are dropped for later ones
+    pub fn queue(&mut self, update: TreeUpdate<'a>) -> &mut Self {
+        if let TreeUpdate::Proxies(_) = update {
+            self.pending.retain(|x| !matches!(x, TreeUpdate::Proxies(_)));
+        }
+        self.pending.push(update);
+        self
+    }
+
+    /// Apply queued updates in order, with groups ordered by `freq`. Returns
+    /// whether there was any
+    pub fn flush(&mut self, freq: &HashMap<String, usize>) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+        for update in mem::take(&mut self.pending) {
+            match update {
+                TreeUpdate::Proxies(proxies) => self.merge(&proxies, freq),
+                TreeUpdate::Groups(groups) => self.extend_groups(groups, freq),
+                TreeUpdate::Delays(delays) => self.merge_delays(&delays),
+                TreeUpdate::Selected { group, proxy } => self.set_current(&group, &proxy),
+            };
+        }
+        true
+    }
+
+    /// Add groups built elsewhere before all proxies are loaded, so they're
+    /// shown as soon as possible. Groups already in the tree are skipped, and
+    /// cursor stays on the same group. Full proxies should still be merged
+    /// with [`ProxyTree::merge`] afterwards
+    pub fn extend_groups(
+        &mut self,
+        groups: Vec<ProxyGroup<'a>>,
+        freq: &HashMap<String, usize>,
+    ) -> &mut Self {
+        let first = self.groups.is_empty();
+        let method = self.sort_method;
+        for mut group in groups {
+            if self.groups.iter().any(|x| x.name == group.name) {
+                continue;
             }
-            if let Some(old_group) = old_groups.iter().find(|group| group.name == new_group.name) {
-                new_group.cursor = old_group
-                    .members
-                    .get(old_group.cursor)
-                    .and_then(|old_member| {
-                        new_group
-                            .members
-                            .iter()
-                            .position(|new_member| new_member.name == old_member.name)
-                    })
-                    .or(new_group.current)
-                    .unwrap_or_default()
+            if let Some(member) = self.stashed_cursors.remove(&group.name) {
+                if let Some(index) = group.members.iter().position(|x| x.name == member) {
+                    group.cursor = index
+                }
             }
+            group.sort_with(&method);
+            self.groups.push(group);
+        }
+        self.sort_groups_with_frequency(freq);
+        if first {
+            self.point_to_top();
+        }
+        self.update_footer()
+    }
+
+    /// Merge latest `proxies` into the tree in place, new groups are ordered
+    /// with `freq`. Nothing is done if `proxies` haven't changed since last
+    /// merge, and only changed groups are updated otherwise
+    pub fn merge(&mut self, proxies: &Proxies, freq: &HashMap<String, usize>) -> &mut Self {
+        let hash = Some(hash_proxies(proxies));
+        if self.proxies_hash == hash {
+            return self;
         }
-        self.groups = new_tree.groups;
+        self.proxies_hash = hash;
+
         let method = self.sort_method;
-        self.sort_with(&method);
+        let first = self.groups.is_empty();
+        let current = self.groups.get(self.cursor).map(|x| x.name.clone());
+        let mut groups = mem::take(&mut self.groups);
+
+        groups.retain(|group| {
+            let keep = proxies
+                .get(&group.name)
+                .is_some_and(|x| x.proxy_type.is_group());
+            if !keep {
+                if let Some(member) = group.members.get(group.cursor) {
+                    self.stashed_cursors
+                        .insert(group.name.to_owned(), member.name.to_owned());
+                }
+            }
+            keep
+        });
+
+        for (name, group) in proxies.groups() {
+            match groups.iter_mut().find(|x| &x.name == name) {
+                Some(old) => {
+                    if old.update(group, proxies) {
+                        old.sort_with(&method)
+                    }
+                }
+                None => {
+                    let mut new = ProxyGroup::new(name, group, proxies);
+                    if let Some(member) = self.stashed_cursors.remove(name) {
+                        if let Some(index) = new.members.iter().position(|x| x.name == member) {
+                            new.cursor = index
+                        }
+                    }
+                    new.sort_with(&method);
+                    groups.push(new);
+                }
+            }
+        }
+
+        self.groups = groups;
+        self.cursor = current
+            .and_then(|name| self.groups.iter().position(|x| x.name == name))
+            .unwrap_or_default();
+        self.sort_groups_with_frequency(freq);
+        if first {
+            self.point_to_top();
+        }
+        if !self.groups.is_empty() {
+            if let Some(snapshot) = self.pending_restore.take() {
+                self.restore(snapshot);
+            }
+        }
         self.update_footer()
     }
 }
 
 impl<'a> From<Proxies> for ProxyTree<'a> {
     fn from(val: Proxies) -> Self {
-        let mut ret = Self {
-            groups: Vec::with_capacity(val.len()),
+        #[cfg(feature = "parallel")]
+        let groups = build_groups_par(&val);
+        #[cfg(not(feature = "parallel"))]
+        let groups = build_groups(&val);
+
+        Self {
+            groups,
             ..Default::default()
-        };
-        for (name, group) in val.groups() {
-            let all = group
-                .all
-                .as_ref()
-                .expect("ProxyGroup should have member vec");
-            let mut members = Vec::with_capacity(all.len());
-            for x in all.iter() {
-                let member = (
-                    x.as_str(),
-                    val.get(x)
-                        .to_owned()
-                        .expect("Group member should be in all proxies"),
-                )
-                    .into();
-                members.push(member);
-            }
+        }
+    }
+}
 
-            // if group.now.is_some then it must be in all proxies
-            // So use map & expect instead of Option#and_then
-            let current = group.now.as_ref().map(|name| {
-                members
-                    .iter()
-                    .position(|item: &ProxyItem| &item.name == name)
-                    .expect("Group member should be in all proxies")
-            });
+#[cfg(any(not(feature = "parallel"), test))]
+fn build_groups<'a>(proxies: &Proxies) -> Vec<ProxyGroup<'a>> {
+    proxies
+        .groups()
+        .map(|(name, group)| ProxyGroup::new(name, group, proxies))
+        .collect()
+}
 
-            ret.groups.push(ProxyGroup {
-                _life: PhantomData,
-                name: name.to_owned(),
-                proxy_type: group.proxy_type,
-                cursor: current.unwrap_or_default(),
-                current,
-                members,
-            })
-        }
+/// Like [`build_groups`], but groups are built on rayon's global pool. Order
+/// of groups is kept
+#[cfg(feature = "parallel")]
+fn build_groups_par<'a>(proxies: &Proxies) -> Vec<ProxyGroup<'a>> {
+    use rayon::prelude::*;
 
-        ret
-    }
+    proxies
+        .groups()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(name, group)| ProxyGroup::new(name, group, proxies))
+        .collect()
+}
+
+/// Cache key of the whole tree, sum of [`hash_group`] of each group so it's
+/// independent of iteration order of the map. Proxies outside of any group
+/// are not shown and don't count
+fn hash_proxies(proxies: &Proxies) -> u64 {
+    proxies.groups().fold(0, |acc, (name, group)| {
+        acc.wrapping_add(hash_group(name, group, proxies))
+    })
 }
 
 impl<'a> MovableListManage for ProxyTree<'a> {
@@ -258,14 +1488,28 @@ impl<'a> MovableListManage for ProxyTree<'a> {
 
     #[inline]
     fn toggle(&mut self) -> &mut Self {
-        self.expanded = !self.expanded;
+        if self.expanded {
+            self.leave();
+        } else {
+            self.enter();
+        }
         self.update_footer()
     }
 
-    #[inline]
     fn end(&mut self) -> &mut Self {
-        self.expanded = false;
-        self.update_footer()
+        // Clear search first if there is one, collapse otherwise
+        if self.global_query.is_some() {
+            return self.end_global_search();
+        }
+        match self.groups.get(self.cursor) {
+            Some(group) if group.filter.is_some() => self.end_search(),
+            _ => {
+                if self.expanded {
+                    self.leave();
+                }
+                self.update_footer()
+            }
+        }
     }
 
     #[inline]
@@ -279,32 +1523,33 @@ impl<'a> MovableListManage for ProxyTree<'a> {
     }
 
     fn hold(&mut self) -> &mut Self {
-        self.expanded = true;
-        self
+        self.enter()
     }
 
     fn handle(&mut self, event: ListEvent) -> Option<Action> {
+        self.notice = None;
         if self.expanded {
             let step = if event.fast { 3 } else { 1 };
             let group = &mut self.groups[self.cursor];
             match event.code {
-                KeyCode::Up => {
-                    if group.cursor > 0 {
-                        group.cursor = group.cursor.saturating_sub(step)
-                    }
-                }
-                KeyCode::Down => {
-                    let left = group.members.len().saturating_sub(group.cursor + 1);
-
-                    group.cursor += left.min(step)
+                KeyCode::Up => group.move_cursor(true, step, self.wrap_navigation),
+                KeyCode::Down => group.move_cursor(false, step, self.wrap_navigation),
+                // Plain right arrow applies selection, so scrolling long names
+                // is done with Shift or Ctrl
+                KeyCode::Left | KeyCode::Right if event.fast => {
+                    group.scroll_members(event.code == KeyCode::Left, NAME_SCROLL_STEP)
                 }
                 KeyCode::Right | KeyCode::Enter => {
-                    if group.proxy_type.is_selector() {
+                    if group.proxy_type.is_selector()
+                        && group.visible_members().contains(&group.cursor)
+                    {
                         let current = group.members[group.cursor].name.to_owned();
                         return Some(Action::ApplySelection {
                             group: group.name.to_owned(),
                             proxy: current,
                         });
+                    } else if !group.proxy_type.is_selector() {
+                        self.notice = Some(format!(" {} is not selectable ", group.proxy_type));
                     }
                 }
                 _ => {}
@@ -312,16 +1557,24 @@ impl<'a> MovableListManage for ProxyTree<'a> {
         } else {
             match event.code {
                 KeyCode::Up => {
-                    if self.cursor > 0 {
-                        self.cursor = self.cursor.saturating_sub(1)
+                    let visible = self.visible_groups();
+                    let prev = visible.iter().rev().find(|&&x| x < self.cursor);
+                    let wrapped = visible.last().filter(|_| self.wrap_navigation);
+                    if let Some(&prev) = prev.or(wrapped) {
+                        self.cursor = prev
                     }
                 }
                 KeyCode::Down => {
-                    if self.cursor < self.groups.len() - 1 {
-                        self.cursor = self.cursor.saturating_add(1)
+                    let visible = self.visible_groups();
+                    let next = visible.iter().find(|&&x| x > self.cursor);
+                    let wrapped = visible.first().filter(|_| self.wrap_navigation);
+                    if let Some(&next) = next.or(wrapped) {
+                        self.cursor = next
                     }
                 }
-                KeyCode::Enter => self.expanded = true,
+                KeyCode::Enter if self.is_visible(self.cursor) => {
+                    self.enter();
+                }
                 _ => {}
             }
         }
@@ -337,3 +1590,1069 @@ impl<'a> MovableListManage for ProxyTree<'a> {
         }
     }
 }
+
+#[test]
+fn test_fold() {
+    let group = |name: &str| ProxyGroup {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![group("a"), group("b")],
+        ..Default::default()
+    };
+    let expanded = |tree: &ProxyTree| {
+        tree.groups
+            .iter()
+            .map(ProxyGroup::is_expanded)
+            .collect::<Vec<_>>()
+    };
+
+    tree.toggle_fold();
+    assert_eq!(expanded(&tree), vec![true, false]);
+    assert!(!tree.expanded);
+
+    tree.expand_all();
+    assert_eq!(expanded(&tree), vec![true, true]);
+
+    // Leaving a group only collapses that group
+    tree.cursor = 1;
+    tree.toggle();
+    assert!(tree.expanded);
+    tree.toggle();
+    assert_eq!(expanded(&tree), vec![true, false]);
+
+    tree.collapse_all();
+    assert_eq!(expanded(&tree), vec![false, false]);
+}
+
+#[test]
+fn test_stash_cursor() {
+    use clashctl_core::model::Proxy;
+
+    let proxy = |proxy_type, all: Option<&[&str]>, now: Option<&str>| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        all: all.map(|x| x.iter().map(|&x| x.to_owned()).collect()),
+        now: now.map(Into::into),
+    };
+    let proxies = |groups: &[(&str, &[&str], &str)]| {
+        let mut proxies = ["x", "y", "z"]
+            .map(|x| (x.to_owned(), proxy(ProxyType::Shadowsocks, None, None)))
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        for (name, members, now) in groups {
+            let group = proxy(ProxyType::Selector, Some(members), Some(now));
+            proxies.insert(name.to_string(), group);
+        }
+        Proxies { proxies }
+    };
+    let pointed = |tree: &ProxyTree, name: &str| {
+        let group = tree.groups.iter().find(|x| x.name == name).unwrap();
+        group.members[group.cursor].name.clone()
+    };
+    let point = |tree: &mut ProxyTree, name: &str, member: &str| {
+        let group = tree.groups.iter_mut().find(|x| x.name == name).unwrap();
+        group.cursor = group.members.iter().position(|x| x.name == member).unwrap();
+    };
+    let freq = HashMap::new();
+
+    let mut tree = ProxyTree::default();
+    tree.merge(&proxies(&[("a", &["x", "y", "z"], "x"), ("b", &["x"], "x")]), &freq);
+    point(&mut tree, "a", "z");
+
+    tree.merge(&proxies(&[("b", &["x"], "x")]), &freq);
+    tree.merge(&proxies(&[("a", &["x", "z"], "x"), ("b", &["x"], "x")]), &freq);
+    assert_eq!(pointed(&tree, "a"), "z");
+
+    // Fall back to current if pointed member is gone
+    point(&mut tree, "a", "x");
+    tree.merge(&proxies(&[("b", &["x"], "x")]), &freq);
+    tree.merge(&proxies(&[("a", &["y", "z"], "z"), ("b", &["x"], "x")]), &freq);
+    assert_eq!(pointed(&tree, "a"), "z");
+}
+
+#[test]
+fn test_tree_hits() {
+    let hits = TreeHits::default();
+    hits.record(Rect::new(1, 1, 10, 5), vec![(0, None), (0, Some(2)), (1, None)]);
+
+    assert_eq!(hits.get(1, 1), Some((0, None)));
+    assert_eq!(hits.get(5, 2), Some((0, Some(2))));
+    // Border
+    assert_eq!(hits.get(0, 2), None);
+    assert_eq!(hits.get(11, 2), None);
+    // Empty area below groups
+    assert_eq!(hits.get(5, 4), None);
+
+    hits.clear();
+    assert_eq!(hits.get(1, 1), None);
+}
+
+#[test]
+fn test_restore_snapshot() {
+    use clashctl_core::model::Proxy;
+
+    let proxy = |proxy_type, all: Option<Vec<String>>| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        now: all.as_ref().map(|_| "x".to_owned()),
+        all,
+    };
+    let proxies = |names: &[&str]| Proxies {
+        proxies: names
+            .iter()
+            .map(|x| (x.to_string(), proxy(ProxyType::Selector, Some(vec!["x".into()]))))
+            .chain([("x".to_owned(), proxy(ProxyType::Shadowsocks, None))])
+            .collect(),
+    };
+    let focused = |tree: &ProxyTree| tree.groups[tree.cursor].name.clone();
+    let freq = HashMap::new();
+
+    let mut old = ProxyTree::default();
+    old.merge(&proxies(&["a", "b", "c"]), &freq);
+    old.cursor = old.groups.iter().position(|x| x.name == "c").unwrap();
+    old.enter();
+    let snapshot = old.snapshot();
+    assert_eq!(snapshot.group.as_deref(), Some("c"));
+
+    let mut new = ProxyTree::default();
+    new.restore_later(snapshot.clone());
+    // Quitting before groups are loaded shouldn't lose the saved position
+    assert_eq!(new.snapshot(), snapshot);
+    new.merge(&proxies(&["c", "b"]), &freq);
+    assert_eq!(focused(&new), "c");
+    assert!(new.expanded);
+    assert!(new.groups[new.cursor].expanded);
+
+    // Fall back to first group if the focused one is gone
+    let mut new = ProxyTree::default();
+    new.restore_later(snapshot);
+    new.merge(&proxies(&["a", "b"]), &freq);
+    assert_eq!(new.cursor, 0);
+    assert!(!new.expanded);
+}
+
+#[test]
+fn test_empty_group() {
+    use clashctl_core::model::Proxy;
+
+    use crate::components::{Consts, ProxyGroupFocusStatus};
+
+    let proxy = |proxy_type, all: Option<Vec<&str>>, now: Option<&str>| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        all: all.map(|x| x.into_iter().map(Into::into).collect()),
+        now: now.map(Into::into),
+    };
+    let proxies = Proxies {
+        proxies: HashMap::from([
+            ("a".into(), proxy(ProxyType::Selector, None, None)),
+            ("b".into(), proxy(ProxyType::URLTest, Some(vec![]), None)),
+            (
+                "c".into(),
+                proxy(ProxyType::Selector, Some(vec!["gone"]), Some("gone")),
+            ),
+        ]),
+    };
+
+    let mut tree = ProxyTree::from(proxies);
+    assert_eq!(tree.groups.len(), 3);
+    assert!(tree.groups.iter().all(|x| x.members.is_empty()));
+    assert!(tree.groups.iter().all(|x| x.current.is_none()));
+
+    tree.sort_with(&ProxySort::default());
+    for i in 0..tree.groups.len() {
+        tree.cursor = i;
+        tree.enter();
+        tree.handle(ListEvent {
+            fast: false,
+            code: KeyCode::Down,
+        });
+        assert!(tree
+            .handle(ListEvent {
+                fast: false,
+                code: KeyCode::Enter,
+            })
+            .is_none());
+        tree.jump_to_current();
+
+        let widget = tree.groups[i].get_widget(
+            Rect::new(0, 0, 40, 10),
+            &LatencyThresholds::default(),
+            &Theme::default(),
+            ProxyGroupFocusStatus::Expanded,
+            false,
+            false,
+        );
+        assert_eq!(widget.len(), 2);
+        assert_eq!(widget[1].0[1].content, Consts::EMPTY_GROUP_SIGN);
+        tree.leave();
+    }
+}
+
+#[test]
+fn test_testing_groups() {
+    let mut tree = ProxyTree::default();
+    assert!(!tree.is_testing());
+
+    tree.start_testing("a").start_testing("b");
+    assert!(tree.is_testing_group("a"));
+    tree.end_testing(&["a".to_owned()]);
+    assert!(!tree.is_testing_group("a"));
+    assert!(tree.is_testing());
+
+    let groups = [("b", vec![]), ("c", vec![])].map(|(x, y)| (x.to_owned(), y));
+    tree.start_sweep(&groups).sweep_progress(1, 2);
+    tree.end_testing(&["b".to_owned()]);
+    assert_eq!(tree.sweep, Some((1, 2)));
+    tree.end_testing(&["c".to_owned()]);
+    assert!(!tree.is_testing());
+    assert_eq!(tree.sweep, None);
+}
+
+#[test]
+fn test_testing_member() {
+    use crate::components::ProxyItem;
+
+    let item = ProxyItem::test;
+    let group = |name: &str, members: &[&str]| ProxyGroup {
+        name: name.to_owned(),
+        members: members.iter().map(|x| item(x)).collect(),
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![group("a", &["hk", "jp"]), group("b", &["jp"])],
+        ..Default::default()
+    };
+    // Not inside a group
+    assert_eq!(tree.start_testing_member(), None);
+
+    tree.enter();
+    tree.groups[0].cursor = 1;
+    assert_eq!(tree.start_testing_member().as_deref(), Some("jp"));
+    assert!(tree.groups.iter().all(|x| x.testing_members.contains_key("jp")));
+    // Already being tested
+    assert_eq!(tree.start_testing_member(), None);
+
+    tree.merge_delays(&HashMap::from([("jp".to_owned(), Some(120))]));
+    assert!(tree.groups.iter().all(|x| x.testing_members.is_empty()));
+    assert_eq!(tree.groups[1].members[0].history.len(), 1);
+}
+
+#[test]
+fn test_step_history() {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::components::ProxyItem;
+
+    let start = Utc.timestamp_opt(0, 0).unwrap();
+    let item = |name: &str, delays: &[u64]| ProxyItem {
+        history: delays
+            .iter()
+            .enumerate()
+            .map(|(i, delay)| History {
+                time: start + Duration::minutes(i as i64),
+                delay: *delay,
+            })
+            .collect(),
+        ..ProxyItem::test(name)
+    };
+    let mut tree = ProxyTree {
+        groups: vec![ProxyGroup {
+            name: "a".to_owned(),
+            members: vec![item("hk", &[100, 0, 300]), item("jp", &[])],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    // Not inside a group
+    tree.step_history(true);
+    assert_eq!(tree.groups[0].history_back(), 0);
+
+    tree.enter();
+    tree.step_history(true).step_history(true);
+    assert_eq!(tree.groups[0].history_back(), 2);
+    tree.step_history(true);
+    assert_eq!(tree.groups[0].history_back(), 2);
+    assert_eq!(tree.notice.as_deref(), Some(" No older samples "));
+    tree.step_history(false);
+    assert_eq!(tree.groups[0].history_back(), 1);
+
+    // Only applies to the member it's stepped on
+    tree.groups[0].cursor = 1;
+    assert_eq!(tree.groups[0].history_back(), 0);
+    tree.groups[0].cursor = 0;
+    assert_eq!(tree.groups[0].history_back(), 1);
+
+    tree.leave();
+    assert_eq!(tree.groups[0].history_back(), 0);
+}
+
+#[test]
+fn test_merge() {
+    use clashctl_core::model::Proxy;
+
+    let proxy = |proxy_type, all: Option<Vec<String>>, now: Option<&str>| Proxy {
+        proxy_type,
+        history: vec![History {
+            time: Utc::now(),
+            delay: 100,
+        }],
+        udp: None,
+        all,
+        now: now.map(Into::into),
+    };
+    let names = (0..2000).map(|i| format!("node-{}", i)).collect::<Vec<_>>();
+    let mut proxies = Proxies {
+        proxies: names
+            .iter()
+            .map(|x| (x.to_owned(), proxy(ProxyType::Shadowsocks, None, None)))
+            .collect(),
+    };
+    let group = |now| proxy(ProxyType::Selector, Some(names.clone()), Some(now));
+    proxies.proxies.insert("a".into(), group("node-0"));
+    proxies.proxies.insert("b".into(), group("node-0"));
+
+    let freq = HashMap::from([("b".to_owned(), 1)]);
+    let mut tree = ProxyTree::default();
+    tree.merge(&proxies, &freq);
+    assert_eq!(
+        tree.groups.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(),
+        ["b", "a"]
+    );
+    assert_eq!(tree.groups[0].members.len(), 2000);
+
+    let member = |group: &ProxyGroup, name: &str| {
+        group
+            .members
+            .iter()
+            .find(|x| x.name == name)
+            .unwrap()
+            .clone()
+    };
+
+    // Unchanged proxies are skipped, local delays are kept
+    tree.merge_delays(&HashMap::from([("node-1".to_owned(), Some(50))]));
+    tree.merge(&proxies, &freq);
+    assert_eq!(member(&tree.groups[1], "node-1").delay(), Some(50));
+
+    // Only changed groups are updated, reusing existing members
+    let find = |group: &ProxyGroup| group.members.iter().position(|x| x.name == "node-1");
+    let index = find(&tree.groups[1]).unwrap();
+    let name_ptr = tree.groups[1].members[index].name.as_ptr();
+    let history_ptr = tree.groups[1].members[index].history.as_ptr();
+    proxies.proxies.insert("a".into(), group("node-1"));
+    tree.merge(&proxies, &freq);
+    let (b, a) = (&tree.groups[0], &tree.groups[1]);
+    assert_eq!(member(b, "node-1").delay(), Some(50));
+    assert_eq!(member(a, "node-1").delay(), Some(100));
+    let index = find(a).unwrap();
+    assert_eq!(a.current, Some(index));
+    assert_eq!(a.members[index].name.as_ptr(), name_ptr);
+    assert_eq!(a.members[index].history.as_ptr(), history_ptr);
+
+    // Cursor follows its group & member when groups are removed and re-added
+    tree.cursor = 1;
+    tree.groups[1].cursor = 5;
+    proxies.proxies.remove("b");
+    tree.merge(&proxies, &freq);
+    assert_eq!(tree.cursor, 0);
+    assert_eq!(tree.groups[0].cursor, 5);
+}
+
+#[test]
+fn test_flush() {
+    use clashctl_core::model::Proxy;
+
+    let proxy = |proxy_type, all: Option<Vec<String>>, now: Option<&str>| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        all,
+        now: now.map(Into::into),
+    };
+    let snapshot = |now: &str| {
+        let mut proxies = Proxies {
+            proxies: ["x", "y", "z"]
+                .iter()
+                .map(|x| (x.to_string(), proxy(ProxyType::Shadowsocks, None, None)))
+                .collect(),
+        };
+        let all = vec!["x".to_owned(), "y".to_owned(), "z".to_owned()];
+        proxies
+            .proxies
+            .insert("a".into(), proxy(ProxyType::Selector, Some(all), Some(now)));
+        proxies
+    };
+    let freq = HashMap::new();
+    let mut tree = ProxyTree::default();
+    for now in ["x", "z", "y", "x", "z"] {
+        tree.queue(TreeUpdate::Proxies(snapshot(now)));
+    }
+    tree.queue(TreeUpdate::Delays(HashMap::from([("y".to_owned(), Some(80))])));
+    tree.queue(TreeUpdate::Selected {
+        group: "a".to_owned(),
+        proxy: "y".to_owned(),
+    });
+    // Older snapshots are dropped, the rest are kept in order
+    assert_eq!(tree.pending.len(), 3);
+    assert!(tree.is_loading());
+
+    assert!(tree.flush(&freq));
+    assert!(!tree.flush(&freq));
+    let mut expected = ProxyTree::default();
+    expected
+        .merge(&snapshot("z"), &freq)
+        .merge_delays(&HashMap::from([("y".to_owned(), Some(80))]))
+        .set_current("a", "y");
+    // Same but time of delay samples
+    let members = |tree: &ProxyTree| {
+        tree.groups[0]
+            .members
+            .iter()
+            .map(|x| (x.name.to_owned(), x.status))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(tree.groups.len(), 1);
+    assert_eq!(members(&tree), members(&expected));
+    assert_eq!(tree.groups[0].current, expected.groups[0].current);
+    assert_eq!(tree.proxies_hash, expected.proxies_hash);
+}
+
+#[test]
+fn test_group_filter() {
+    let group = |name: &str, proxy_type| ProxyGroup {
+        name: name.to_owned(),
+        proxy_type,
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![
+            group("auto", ProxyType::URLTest),
+            group("proxy", ProxyType::Selector),
+            group("fallback", ProxyType::Fallback),
+            group("media", ProxyType::Selector),
+        ],
+        ..Default::default()
+    };
+    let down = |tree: &mut ProxyTree| {
+        tree.handle(ListEvent {
+            fast: false,
+            code: KeyCode::Down,
+        });
+    };
+
+    // Cursor moves off the hidden group, skipping hidden ones
+    tree.next_group_filter();
+    assert_eq!(tree.group_filter, GroupFilter::Selectable);
+    assert_eq!(tree.visible_groups(), [1, 3]);
+    assert_eq!(tree.cursor, 1);
+    down(&mut tree);
+    assert_eq!(tree.cursor, 3);
+    down(&mut tree);
+    assert_eq!(tree.cursor, 3);
+
+    // Last visible one is taken if there's none below
+    tree.next_group_filter();
+    assert_eq!(tree.visible_groups(), [0]);
+    assert_eq!(tree.cursor, 0);
+
+    tree.next_group_filter();
+    assert_eq!(tree.group_filter, GroupFilter::All);
+    assert_eq!(tree.cursor, 0);
+    assert_eq!(tree.visible_groups().len(), 4);
+}
+
+#[test]
+fn test_wrap_navigation() {
+    use crate::components::ProxyItem;
+
+    let item = ProxyItem::test;
+    let group = |name: &str, proxy_type| ProxyGroup {
+        name: name.to_owned(),
+        proxy_type,
+        members: vec![item("hk-1"), item("jp-1"), item("hk-2"), item("us-1")],
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![
+            group("auto", ProxyType::URLTest),
+            group("proxy", ProxyType::Selector),
+            group("media", ProxyType::Selector),
+        ],
+        ..Default::default()
+    };
+    let press = |tree: &mut ProxyTree, code| {
+        tree.handle(ListEvent { fast: false, code });
+    };
+
+    // Stops at the top without it
+    press(&mut tree, KeyCode::Up);
+    assert_eq!(tree.cursor, 0);
+
+    // Only shown groups are cycled through
+    tree.wrap_navigation = true;
+    tree.next_group_filter();
+    assert_eq!(tree.cursor, 1);
+    press(&mut tree, KeyCode::Up);
+    assert_eq!(tree.cursor, 2);
+    press(&mut tree, KeyCode::Down);
+    assert_eq!(tree.cursor, 1);
+
+    // So are members matching search
+    tree.enter();
+    tree.groups[1].filter = Some("hk".to_owned());
+    press(&mut tree, KeyCode::Up);
+    assert_eq!(tree.groups[1].cursor, 2);
+    press(&mut tree, KeyCode::Down);
+    assert_eq!(tree.groups[1].cursor, 0);
+    // Fast moves stop at the end before wrapping
+    tree.handle(ListEvent {
+        fast: true,
+        code: KeyCode::Down,
+    });
+    assert_eq!(tree.groups[1].cursor, 2);
+}
+
+#[test]
+fn test_hash_proxies() {
+    use chrono::Duration;
+    use clashctl_core::model::Proxy;
+
+    let now = Utc::now();
+    let history = |delays: &[u64]| {
+        delays
+            .iter()
+            .enumerate()
+            .map(|(i, &delay)| History {
+                time: now + Duration::seconds(i as i64),
+                delay,
+            })
+            .collect::<Vec<_>>()
+    };
+    let node = |history| Proxy {
+        proxy_type: ProxyType::Shadowsocks,
+        history,
+        udp: Some(true),
+        all: None,
+        now: None,
+    };
+    let group = Proxy {
+        proxy_type: ProxyType::Selector,
+        history: vec![],
+        udp: None,
+        all: Some(vec!["x".into(), "y".into()]),
+        now: Some("x".into()),
+    };
+    let build = |entries: Vec<(&str, Proxy)>| Proxies {
+        proxies: entries
+            .into_iter()
+            .map(|(name, proxy)| (name.to_owned(), proxy))
+            .collect(),
+    };
+
+    let base = build(vec![
+        ("a", group.clone()),
+        ("x", node(history(&[100, 200]))),
+        ("y", node(history(&[300]))),
+    ]);
+    // Same tree inserted in another order, history listed newest first,
+    // with older samples differing
+    let mut reversed = history(&[50, 200]);
+    reversed.reverse();
+    let same = build(vec![
+        ("y", node(history(&[300]))),
+        ("x", node(reversed)),
+        ("a", group.clone()),
+    ]);
+    assert_eq!(hash_proxies(&base), hash_proxies(&same));
+
+    // Latest delay is shown, so it counts
+    let latest = build(vec![
+        ("a", group.clone()),
+        ("x", node(history(&[100, 150]))),
+        ("y", node(history(&[300]))),
+    ]);
+    assert_ne!(hash_proxies(&base), hash_proxies(&latest));
+
+    // So does member order
+    let mut swapped = group;
+    swapped.all = Some(vec!["y".into(), "x".into()]);
+    let swapped = build(vec![
+        ("a", swapped),
+        ("x", node(history(&[100, 200]))),
+        ("y", node(history(&[300]))),
+    ]);
+    assert_ne!(hash_proxies(&base), hash_proxies(&swapped));
+
+    // Reordered history is not a change of the group either
+    let mut tree = ProxyTree::default();
+    tree.merge(&base, &HashMap::new());
+    assert!(!tree.groups[0].update(&same.proxies["a"], &same));
+    assert_eq!(tree.groups[0].members[0].delay(), Some(200));
+}
+
+#[test]
+fn test_global_search() {
+    use crate::components::ProxyItem;
+
+    let item = ProxyItem::test;
+    let group = |name: &str, members: &[&str]| ProxyGroup {
+        name: name.to_owned(),
+        members: members.iter().map(|x| item(x)).collect(),
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![
+            group("a", &["hk-1", "jp-1"]),
+            group("b", &["us-1"]),
+            group("c", &["sg-1", "jp-2"]),
+        ],
+        ..Default::default()
+    };
+    let pointed = |tree: &ProxyTree| (tree.cursor, tree.groups[tree.cursor].cursor);
+
+    assert_eq!(tree.find("jp"), Some((0, 1)));
+    assert_eq!(tree.find("us"), Some((1, 0)));
+    assert_eq!(tree.find("kr"), None);
+
+    tree.start_global_search();
+    for c in "jp2".chars() {
+        tree.handle_search(KeyEvent::from(KeyCode::Char(c)));
+    }
+    assert_eq!(pointed(&tree), (2, 1));
+    assert!(tree.expanded && tree.groups[2].is_expanded());
+
+    tree.handle_search(KeyEvent::from(KeyCode::Backspace));
+    tree.handle_search(KeyEvent::from(KeyCode::Enter));
+    assert!(!tree.is_searching());
+    assert_eq!(pointed(&tree), (0, 1));
+
+    // Cycles through matches across groups
+    tree.next_match();
+    assert_eq!(pointed(&tree), (2, 1));
+    assert!(!tree.groups[0].is_expanded());
+    tree.next_match();
+    assert_eq!(pointed(&tree), (0, 1));
+
+    tree.end();
+    assert_eq!(tree.global_query, None);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_build_groups_par() {
+    use clashctl_core::model::Proxy;
+
+    let proxy = |proxy_type, all: Option<Vec<String>>, now: Option<String>| Proxy {
+        proxy_type,
+        history: vec![],
+        udp: None,
+        all,
+        now,
+    };
+    let names = (0..500).map(|i| format!("node-{}", i)).collect::<Vec<_>>();
+    let mut proxies = Proxies {
+        proxies: names
+            .iter()
+            .map(|x| (x.to_owned(), proxy(ProxyType::Vmess, None, None)))
+            .collect(),
+    };
+    for i in 0..200 {
+        let all = names.iter().skip(i).step_by(3).cloned().collect::<Vec<_>>();
+        let now = all.get(i % 7).cloned();
+        proxies
+            .proxies
+            .insert(format!("group-{}", i), proxy(ProxyType::Selector, Some(all), now));
+    }
+
+    let groups = build_groups_par(&proxies);
+    assert_eq!(groups.len(), 200);
+    assert_eq!(groups, build_groups(&proxies));
+}
+
+#[test]
+fn test_pin() {
+    let group = |name: &str| ProxyGroup {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    let names = |tree: &ProxyTree| {
+        tree.groups
+            .iter()
+            .map(|x| x.name.to_owned())
+            .collect::<Vec<_>>()
+    };
+    let freq = HashMap::from([("a".to_owned(), 2), ("b".to_owned(), 1)]);
+    let mut tree = ProxyTree::default().with_pinned(["c".to_owned()]);
+    tree.groups = vec![group("a"), group("b"), group("c")];
+
+    tree.sort_groups_with_frequency(&freq);
+    assert_eq!(names(&tree), ["c", "a", "b"]);
+    assert!(tree.groups[0].is_pinned());
+
+    tree.cursor = 2;
+    assert_eq!(tree.toggle_pin(), Some(("b".to_owned(), true)));
+    tree.sort_groups_with_frequency(&freq);
+    assert_eq!(names(&tree), ["b", "c", "a"]);
+    assert_eq!(tree.cursor, 0);
+
+    tree.cursor = 1;
+    assert_eq!(tree.toggle_pin(), Some(("c".to_owned(), false)));
+    tree.sort_groups_with_frequency(&freq);
+    assert_eq!(names(&tree), ["b", "a", "c"]);
+}
+
+#[test]
+fn test_group_sort() {
+    use crate::components::ProxyItem;
+
+    let item = ProxyItem::test("x");
+    let group = |name: &str, proxy_type: ProxyType, members: usize| ProxyGroup {
+        name: name.to_owned(),
+        proxy_type,
+        members: vec![item.clone(); members],
+        ..Default::default()
+    };
+    let names = |tree: &ProxyTree| {
+        tree.groups
+            .iter()
+            .map(|x| x.name.to_owned())
+            .collect::<Vec<_>>()
+    };
+    let freq = HashMap::from([("c".to_owned(), 1)]);
+    let mut tree = ProxyTree::default().with_pinned(["d".to_owned()]);
+    tree.groups = vec![
+        group("a", ProxyType::URLTest, 1),
+        group("b", ProxyType::Selector, 3),
+        group("c", ProxyType::Fallback, 2),
+        group("d", ProxyType::Fallback, 0),
+    ];
+
+    tree.sort_groups_with_frequency(&freq);
+    assert_eq!(names(&tree), ["d", "c", "a", "b"]);
+    tree.cursor = 2;
+
+    tree.next_group_sort();
+    assert_eq!(tree.group_sort(), GroupSort::Name);
+    assert_eq!(names(&tree), ["d", "a", "b", "c"]);
+    assert_eq!(tree.cursor, 1);
+
+    tree.next_group_sort();
+    assert_eq!(names(&tree), ["d", "b", "a", "c"]);
+    assert_eq!(tree.cursor, 2);
+
+    tree.next_group_sort();
+    assert_eq!(names(&tree), ["d", "b", "c", "a"]);
+    assert_eq!(tree.cursor, 3);
+
+    // Order is kept when groups are resorted after rules are updated
+    tree.sort_groups_with_frequency(&freq);
+    assert_eq!(names(&tree), ["d", "b", "c", "a"]);
+
+    tree.next_group_sort();
+    assert_eq!(tree.group_sort(), GroupSort::Usage);
+    assert_eq!(names(&tree), ["d", "c", "a", "b"]);
+    assert_eq!(tree.cursor, 2);
+}
+
+#[test]
+fn test_alias() {
+    use crate::components::ProxyItem;
+
+    let item = ProxyItem::test;
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let mut tree = ProxyTree::default().with_aliases([("node-2".to_owned(), "Tokyo".to_owned())]);
+    tree.groups = vec![ProxyGroup {
+        name: "Proxy".to_owned(),
+        members: vec![item("node-1"), item("node-2")],
+        ..Default::default()
+    }];
+    tree.sort_groups_with_frequency(&HashMap::new());
+    assert_eq!(tree.groups[0].label(0), "node-1");
+    assert_eq!(tree.groups[0].label(1), "Tokyo");
+    assert_eq!(tree.groups[0].find("tky"), [1]);
+
+    // Only members can be aliased
+    assert!(!tree.start_alias());
+    tree.expanded = true;
+    assert!(tree.start_alias());
+    for c in "HK".chars() {
+        assert_eq!(tree.handle_alias(key(KeyCode::Char(c))), None);
+    }
+    assert_eq!(
+        tree.handle_alias(key(KeyCode::Enter)),
+        Some(("node-1".to_owned(), Some("HK".to_owned())))
+    );
+    assert!(!tree.is_typing_alias());
+    assert_eq!(tree.groups[0].label(0), "HK");
+    // Still selected by real name
+    assert_eq!(tree.focused_name(), Some("node-1"));
+
+    assert!(tree.start_alias());
+    assert_eq!(tree.alias_input.as_deref(), Some("HK"));
+    tree.handle_alias(key(KeyCode::Backspace));
+    tree.handle_alias(key(KeyCode::Backspace));
+    assert_eq!(
+        tree.handle_alias(key(KeyCode::Enter)),
+        Some(("node-1".to_owned(), None))
+    );
+    assert_eq!(tree.groups[0].label(0), "node-1");
+}
+
+#[test]
+fn test_global_group() {
+    let group = |name: &str| ProxyGroup {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    let tree = |global_group| {
+        let mut tree = ProxyTree::default().with_global_group(global_group);
+        let groups = vec![group("b"), group("GLOBAL"), group("a")];
+        tree.extend_groups(groups, &HashMap::new());
+        tree
+    };
+    let names = |tree: &ProxyTree| {
+        tree.visible_groups()
+            .into_iter()
+            .map(|i| tree.groups[i].name.to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let normal = tree(GlobalGroup::Normal);
+    assert_eq!(names(&normal), ["GLOBAL", "a", "b"]);
+    // Cursor is left on the group listed first
+    assert_eq!(normal.groups[normal.cursor].name, "b");
+    assert_eq!(names(&tree(GlobalGroup::Bottom)), ["a", "b", "GLOBAL"]);
+    let hidden = tree(GlobalGroup::Hidden);
+    assert_eq!(names(&hidden), ["a", "b"]);
+    // Cursor starts at the first shown group
+    assert_eq!(hidden.groups[hidden.cursor].name, "a");
+
+    // Still above others once pinned
+    let mut pinned = tree(GlobalGroup::Bottom);
+    pinned.pinned.insert("GLOBAL".to_owned());
+    pinned.sort_groups_with_frequency(&HashMap::new());
+    assert_eq!(names(&pinned), ["GLOBAL", "a", "b"]);
+
+    let mut collapsed = tree(GlobalGroup::Collapsed);
+    collapsed.expand_all();
+    let expanded = |tree: &ProxyTree| tree.groups.iter().map(|x| x.expanded).collect::<Vec<_>>();
+    assert_eq!(expanded(&collapsed), [false, true, true]);
+    assert_eq!(expanded(tree(GlobalGroup::Normal).expand_all()), [true, true, true]);
+}
+
+#[test]
+fn test_current_selections() {
+    use crate::components::ProxyItem;
+
+    let item = |name: &str, status| ProxyItem {
+        status,
+        ..ProxyItem::test(name)
+    };
+    let group = |name: &str, current| ProxyGroup {
+        name: name.to_owned(),
+        members: vec![item("x", TestStatus::Ok(120)), item("y", TestStatus::Timeout)],
+        current,
+        ..Default::default()
+    };
+    let tree = ProxyTree {
+        groups: vec![group("a", Some(0)), group("b", Some(1)), group("c", None)],
+        ..Default::default()
+    };
+    assert_eq!(
+        tree.current_selections(),
+        [
+            ("a".to_owned(), Some("x".to_owned()), Some(120)),
+            ("b".to_owned(), Some("y".to_owned()), None),
+            ("c".to_owned(), None, None),
+        ]
+    );
+}
+
+#[test]
+fn test_flip_global_direct() {
+    use crate::components::ProxyItem;
+
+    let member = |name: &str, proxy_type| ProxyItem {
+        proxy_type,
+        ..ProxyItem::test(name)
+    };
+    let mut tree = ProxyTree::default();
+    assert!(tree.flip_global_direct().is_err());
+
+    tree.groups = vec![ProxyGroup {
+        name: "GLOBAL".to_owned(),
+        members: vec![
+            member("DIRECT", ProxyType::Direct),
+            member("HK", ProxyType::Shadowsocks),
+        ],
+        current: Some(1),
+        ..Default::default()
+    }];
+    assert_eq!(tree.flip_global_direct(), Ok("DIRECT".to_owned()));
+    tree.set_current("GLOBAL", "DIRECT");
+    assert_eq!(tree.flip_global_direct(), Ok("HK".to_owned()));
+
+    // Flipped to DIRECT elsewhere, nothing to go back to
+    tree.global_previous = None;
+    assert!(tree.flip_global_direct().is_err());
+}
+
+#[test]
+fn test_extend_groups() {
+    use clashctl_core::model::Proxy;
+
+    let group = |name: &str| ProxyGroup {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    let names = |tree: &ProxyTree| {
+        tree.groups
+            .iter()
+            .map(|x| x.name.to_owned())
+            .collect::<Vec<_>>()
+    };
+    let freq = HashMap::new();
+    let mut tree = ProxyTree::default();
+    assert!(tree.is_loading());
+
+    tree.extend_groups(vec![group("b"), group("d")], &freq);
+    tree.cursor = 1;
+    tree.extend_groups(vec![group("a"), group("c"), group("b")], &freq);
+    assert_eq!(names(&tree), ["a", "b", "c", "d"]);
+    // Still on `d`
+    assert_eq!(tree.cursor, 3);
+
+    let proxies = Proxies {
+        proxies: ["a", "b", "c", "d"]
+            .map(|name| {
+                let group = Proxy {
+                    proxy_type: ProxyType::Selector,
+                    history: vec![],
+                    udp: None,
+                    all: Some(vec![]),
+                    now: None,
+                };
+                (name.to_owned(), group)
+            })
+            .into(),
+    };
+    tree.merge(&proxies, &freq);
+    assert!(!tree.is_loading());
+    assert_eq!(names(&tree), ["a", "b", "c", "d"]);
+    assert_eq!(tree.cursor, 3);
+}
+
+#[test]
+fn test_cycle_selection() {
+    use crate::components::ProxyItem;
+
+    let member = ProxyItem::test;
+    let group = |name: &str, proxy_type, current| ProxyGroup {
+        name: name.to_owned(),
+        proxy_type,
+        members: vec![member("HK"), member("JP"), member("US")],
+        current,
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![
+            group("Proxy", ProxyType::Selector, Some(2)),
+            group("Auto", ProxyType::URLTest, Some(0)),
+        ],
+        ..Default::default()
+    };
+    let selected = |group: &str, proxy: &str| Ok((group.to_owned(), proxy.to_owned()));
+
+    assert_eq!(tree.cycle_selection(false), selected("Proxy", "HK"));
+    assert_eq!(tree.cycle_selection(true), selected("Proxy", "JP"));
+    tree.groups[0].current = None;
+    assert_eq!(tree.cycle_selection(false), selected("Proxy", "HK"));
+    assert_eq!(tree.cycle_selection(true), selected("Proxy", "US"));
+
+    tree.expanded = true;
+    assert!(tree.cycle_selection(false).is_err());
+    tree.expanded = false;
+
+    tree.cursor = 1;
+    assert!(tree.cycle_selection(false).is_err());
+}
+
+#[test]
+fn test_iter_groups() {
+    use crate::components::ProxyItem;
+
+    let member = |name: &str| ProxyItem {
+        udp: Some(true),
+        ..ProxyItem::test(name)
+    };
+    let tree = ProxyTree {
+        groups: vec![
+            ProxyGroup {
+                name: "Proxy".to_owned(),
+                members: vec![member("HK"), member("JP")],
+                current: Some(1),
+                ..Default::default()
+            },
+            ProxyGroup {
+                name: "Empty".to_owned(),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+
+    let names = tree.iter_groups().map(ProxyGroup::name).collect::<Vec<_>>();
+    assert_eq!(names, ["Proxy", "Empty"]);
+
+    let group = tree.group("Proxy").unwrap();
+    let members = group.iter_members().map(ProxyItem::name).collect::<Vec<_>>();
+    assert_eq!(members, ["HK", "JP"]);
+    assert_eq!(group.current().map(ProxyItem::name), Some("JP"));
+    assert!(group.member("HK").unwrap().is_udp());
+    assert!(group.member("US").is_none());
+
+    assert!(tree.group("Empty").unwrap().current().is_none());
+    assert!(tree.group("Other").is_none());
+}
+
+#[test]
+fn test_single_line() {
+    use crate::{
+        components::{Consts, ProxyItem, ProxyTreeWidget},
+        ui::utils::render_to_string,
+    };
+
+    let member = |name: &str| ProxyItem {
+        status: TestStatus::Ok(100),
+        ..ProxyItem::test(name)
+    };
+    let group = |name: &str| ProxyGroup {
+        name: name.to_owned(),
+        proxy_type: ProxyType::Selector,
+        members: vec![member("HK"), member("JP")],
+        ..Default::default()
+    };
+    let mut tree = ProxyTree {
+        groups: vec![group("a"), group("b")],
+        ..Default::default()
+    };
+    let rows = |tree: &ProxyTree| {
+        let text = render_to_string(ProxyTreeWidget::new(tree), 40, 8);
+        text.lines().filter(|x| x.contains(Consts::PROXY_LATENCY_SIGN.trim())).count()
+    };
+    assert_eq!(rows(&tree), 2);
+
+    tree.toggle_single_line();
+    assert_eq!(rows(&tree), 0);
+
+    // Expanded groups are listed as usual
+    tree.groups[1].expanded = true;
+    let text = render_to_string(ProxyTreeWidget::new(&tree), 40, 8);
+    assert_eq!(text.matches("HK").count(), 1);
+}
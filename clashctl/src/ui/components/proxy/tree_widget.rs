@@ -1,8 +1,11 @@
-use tui::widgets::{Paragraph, Widget};
+use tui::{
+    layout::Rect,
+    widgets::{Paragraph, Widget},
+};
 
 use crate::{
-    components::{FooterWidget, ProxyGroupFocusStatus, ProxyTree},
-    get_block, get_focused_block,
+    components::{Consts, FooterWidget, GroupFilter, GroupSort, ProxyGroupFocusStatus, ProxyTree},
+    get_block, get_focused_block, scrollbar_thumb,
 };
 
 #[derive(Clone, Debug)]
@@ -18,48 +21,146 @@ impl<'a> ProxyTreeWidget<'a> {
 
 impl<'a> Widget for ProxyTreeWidget<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let cursor = &self.state.cursor;
-        let skip = if self.state.expanded {
-            *cursor
+        let cursor = self.state.cursor;
+        let height = area.height as usize;
+        // Hidden groups are skipped, positions below are among visible ones
+        let visible = self.state.visible_groups();
+        let pos = visible.iter().position(|&x| x == cursor).unwrap_or_default();
+        let total = visible.len();
+        let mut title = if total > 1 {
+            format!("Proxies {}/{}", pos + 1, total)
         } else {
-            cursor.saturating_sub(2)
+            "Proxies".to_owned()
+        };
+        if self.state.group_filter != GroupFilter::All {
+            title += &format!(" · {} only", self.state.group_filter.label());
+        }
+        if self.state.group_sort != GroupSort::Usage {
+            title += &format!(" · by {}", self.state.group_sort.label());
+        }
+        if let Some(ref input) = self.state.alias_input {
+            title += &format!(" · Alias: {}_", input);
+        }
+        if self.state.is_loading() && total > 0 {
+            title += &format!(" · {}", Consts::LOADING_SIGN);
+        }
+        if let Some(since) = self.state.updating {
+            let frames = Consts::SPINNER_FRAMES;
+            let frame = since.elapsed().as_millis() / 100;
+            title += &format!(" {} Updating", frames[frame as usize % frames.len()]);
+        }
+
+        let block = if self.state.expanded {
+            get_focused_block(&title)
+        } else {
+            get_block(&title)
+        };
+
+        let inner = block.inner(area);
+        // Each group fits in the inner height, summaries are still chunked by
+        // width of the whole area
+        let size = Rect {
+            width: area.width,
+            ..inner
         };
-        let text = self
-            .state
-            .groups
+        let widgets = visible
             .iter()
-            .skip(skip)
-            .enumerate()
-            .map(|(i, x)| {
-                x.get_widget(
-                    area.width as usize,
-                    match (self.state.expanded, *cursor == i + skip) {
-                        (true, true) => ProxyGroupFocusStatus::Expanded,
-                        (false, true) => ProxyGroupFocusStatus::Focused,
-                        _ => ProxyGroupFocusStatus::None,
-                    },
-                )
+            .map(|&i| {
+                let x = &self.state.groups[i];
+                let status = match (self.state.expanded, cursor == i) {
+                    (true, true) => ProxyGroupFocusStatus::Expanded,
+                    (false, true) => ProxyGroupFocusStatus::Focused,
+                    _ => ProxyGroupFocusStatus::None,
+                };
+                let collapsed = !x.expanded && !matches!(status, ProxyGroupFocusStatus::Expanded);
+                let mut lines = x.get_widget(
+                    size,
+                    &self.state.thresholds,
+                    &self.state.theme,
+                    status,
+                    self.state.is_testing_group(&x.name),
+                    self.state.compact,
+                );
+                // Header has the best delay already, latency signs are dropped
+                if collapsed && self.state.single_line {
+                    lines.truncate(1);
+                }
+                lines
             })
-            .reduce(|mut a, b| {
-                a.extend(b);
-                a
-            })
-            .unwrap_or_default()
-            .into_iter()
-            .take(area.height as usize)
             .collect::<Vec<_>>();
 
-        let block = if self.state.expanded {
-            get_focused_block("Proxies")
+        // Show two groups above the focused one, unless expanded groups above
+        // would push it out of the upper half of the view
+        let skip = if self.state.expanded {
+            pos
         } else {
-            get_block("Proxies")
+            let mut skip = pos.saturating_sub(2);
+            while skip < pos
+                && widgets[skip..pos].iter().map(Vec::len).sum::<usize>() > height / 2
+            {
+                skip += 1
+            }
+            skip
         };
 
-        let inner = block.inner(area);
+        let hits = visible
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .flat_map(|(j, &i)| {
+                let group = &self.state.groups[i];
+                let inside = self.state.expanded && i == cursor;
+                let rows = size.height.saturating_sub(1) as usize;
+                let members = match group.listed_members(inside, rows) {
+                    Some(listed) => listed.into_iter().map(Some).collect(),
+                    None => vec![None; widgets[j].len() - 1],
+                };
+                std::iter::once((i, None)).chain(members.into_iter().map(move |x| (i, x)))
+            })
+            .take(inner.height as usize)
+            .collect();
+        self.state.hits.record(inner, hits);
 
+        let text = widgets
+            .into_iter()
+            .skip(skip)
+            .flatten()
+            .take(height)
+            .collect::<Vec<_>>();
         block.render(area, buf);
 
-        Paragraph::new(text).render(inner, buf);
+        // Scrollbar takes the rightmost column, skipped when there's no room
+        // for it to mean anything
+        let text_area = if total > 1 && inner.height >= 3 && inner.width >= 10 {
+            let x = inner.right() - 1;
+            let thumb = scrollbar_thumb(pos, total, inner.height as usize);
+            for row in 0..inner.height {
+                let symbol = if thumb.contains(&(row as usize)) {
+                    Consts::SCROLLBAR_THUMB
+                } else {
+                    Consts::SCROLLBAR_TRACK
+                };
+                buf.set_string(x, inner.y + row, symbol, Consts::SCROLLBAR_STYLE);
+            }
+            Rect {
+                width: inner.width - 1,
+                ..inner
+            }
+        } else {
+            inner
+        };
+
+        if total == 0 && self.state.is_loading() {
+            Paragraph::new(Consts::LOADING_SIGN)
+                .style(self.state.theme.no_latency_style())
+                .render(text_area, buf);
+        } else if total == 0 && !self.state.groups.is_empty() {
+            Paragraph::new(format!("No {} groups", self.state.group_filter.label()))
+                .style(self.state.theme.no_latency_style())
+                .render(text_area, buf);
+        } else {
+            Paragraph::new(text).render(text_area, buf);
+        }
         FooterWidget::new(&self.state.footer).render(area, buf);
     }
 }
@@ -0,0 +1,93 @@
+use tui::{
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph, Widget},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::{components::Consts, define_widget, utils::get_focused_block};
+
+/// Shown in place of member for groups without a current one
+const NO_SELECTION: &str = "(none)";
+
+define_widget!(SelectionsPopup);
+
+/// Popup of current member of each group with its delay, drawn in the middle
+/// of `area`. Groups beyond its height are counted in the last line
+impl<'a> Widget for SelectionsPopup<'a> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        let tree = &self.state.proxy_tree;
+        let selections = tree.current_selections();
+        let (thresholds, theme) = (tree.thresholds(), tree.theme());
+
+        let group_width = selections.iter().map(|x| x.0.width()).max().unwrap_or_default();
+        let member_width = selections
+            .iter()
+            .map(|x| x.1.as_deref().unwrap_or(NO_SELECTION).width())
+            .max()
+            .unwrap_or_default();
+        // Names can be wide chars, so they're padded by width instead of count
+        let pad = |text: &str, width: usize| {
+            format!("{}{} ", text, " ".repeat(width.saturating_sub(text.width())))
+        };
+        let title = "Selections · Esc to close";
+        let width = (group_width + member_width + 10).max(title.width() + 4) as u16;
+        let width = width.min(area.width);
+        let height = (selections.len().max(1) as u16 + 2).min(area.height);
+        let rows = height.saturating_sub(2) as usize;
+        // Leave the last row to tell how many are left out
+        let shown = if selections.len() > rows {
+            rows.saturating_sub(1)
+        } else {
+            rows
+        };
+
+        let mut lines = selections
+            .iter()
+            .take(shown)
+            .map(|(group, member, delay)| {
+                let delay = match (member, delay) {
+                    (_, Some(delay)) => {
+                        Span::styled(delay.to_string(), theme.delay_style(*delay, thresholds))
+                    }
+                    (Some(_), None) => {
+                        Span::styled(Consts::NO_LATENCY_SIGN, theme.no_latency_style())
+                    }
+                    (None, None) => Span::raw(""),
+                };
+                let member = match member {
+                    Some(member) => Span::styled(
+                        pad(member, member_width),
+                        theme.member_name_style(true, false),
+                    ),
+                    None => Span::styled(pad(NO_SELECTION, member_width), theme.no_latency_style()),
+                };
+                Spans::from(vec![
+                    Span::styled(pad(group, group_width), theme.group_name_style()),
+                    member,
+                    delay,
+                ])
+            })
+            .collect::<Vec<_>>();
+        if selections.len() > shown {
+            lines.push(Spans::from(Span::styled(
+                format!("{} more groups", selections.len() - shown),
+                theme.no_latency_style(),
+            )));
+        }
+        if selections.is_empty() {
+            lines.push(Spans::from(Span::styled("No groups", theme.no_latency_style())));
+        }
+
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(get_focused_block(title))
+            .render(popup, buf);
+    }
+}
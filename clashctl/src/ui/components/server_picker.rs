@@ -0,0 +1,59 @@
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph, Widget},
+};
+
+use crate::ui::{define_widget, utils::get_focused_block};
+
+define_widget!(ServerPicker);
+
+/// Popup listing configured servers, drawn in the middle of `area`
+impl<'a> Widget for ServerPicker<'a> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        let config = &self.state.config_state;
+        let using = config.server();
+        let index = self.state.server_picker.unwrap_or_default();
+
+        let lines = config
+            .servers()
+            .iter()
+            .enumerate()
+            .map(|(i, server)| {
+                let style = if i == index {
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                Spans::from(vec![
+                    Span::styled(if i == index { "➤ " } else { "  " }, style),
+                    Span::styled(server.display_name().to_owned(), style),
+                    Span::raw(" "),
+                    Span::styled(server.url.to_string(), Style::default().fg(Color::DarkGray)),
+                    Span::styled(
+                        if Some(server) == using { " ✓" } else { "" },
+                        Style::default().fg(Color::Green),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let width = lines.iter().map(Spans::width).max().unwrap_or_default() as u16 + 4;
+        let width = width.max(30).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(get_focused_block("Servers · Enter to switch"))
+            .render(popup, buf);
+    }
+}
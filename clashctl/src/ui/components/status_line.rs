@@ -0,0 +1,151 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Paragraph, Widget},
+};
+
+use crate::ui::define_widget;
+
+/// How long transient messages stay in status line
+const TRANSIENT: Duration = Duration::from_secs(4);
+
+/// Transient messages are dimmed for this long before they're gone
+const FADE: Duration = Duration::from_secs(1);
+
+/// Max number of messages kept in queue
+const MAX_STATUS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusLevel {
+    Info,
+    // Kept until dismissed
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub level: StatusLevel,
+    pub text: String,
+    pub time: DateTime<Local>,
+    received: Instant,
+}
+
+impl Status {
+    fn is_live(&self, now: Instant) -> bool {
+        self.level == StatusLevel::Error || now.duration_since(self.received) < TRANSIENT
+    }
+
+    fn is_fading(&self, now: Instant) -> bool {
+        self.level == StatusLevel::Info && now.duration_since(self.received) + FADE >= TRANSIENT
+    }
+}
+
+/// Messages shown in status line, newest last
+#[derive(Debug, Clone, Default)]
+pub struct StatusQueue {
+    messages: VecDeque<Status>,
+}
+
+impl StatusQueue {
+    fn push(&mut self, level: StatusLevel, text: String) -> &mut Self {
+        let now = Instant::now();
+        self.messages.retain(|x| x.is_live(now));
+        if self.messages.len() >= MAX_STATUS {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(Status {
+            level,
+            text,
+            time: Local::now(),
+            received: now,
+        });
+        self
+    }
+
+    /// Show `text` for a few seconds
+    pub fn info(&mut self, text: impl Into<String>) -> &mut Self {
+        self.push(StatusLevel::Info, text.into())
+    }
+
+    /// Show `error`, like a `TuiError`, until dismissed
+    pub fn error(&mut self, error: impl Display) -> &mut Self {
+        self.push(StatusLevel::Error, error.to_string())
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.messages.iter().any(|x| x.level == StatusLevel::Error)
+    }
+
+    /// Remove all errors
+    pub fn dismiss(&mut self) -> &mut Self {
+        self.messages.retain(|x| x.level != StatusLevel::Error);
+        self
+    }
+
+    /// Newest message to be shown at `now`
+    pub fn current(&self, now: Instant) -> Option<&Status> {
+        self.messages.iter().rev().find(|x| x.is_live(now))
+    }
+}
+
+define_widget!(StatusLine);
+
+impl<'a> Widget for StatusLine<'a> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        let now = Instant::now();
+        let status = match self.state.status.current(now) {
+            Some(status) => status,
+            None => return,
+        };
+        let style = match status.level {
+            StatusLevel::Error => Style::default().fg(Color::Red),
+            StatusLevel::Info if status.is_fading(now) => Style::default().fg(Color::DarkGray),
+            StatusLevel::Info => Style::default().fg(Color::White),
+        };
+        let mut line = vec![
+            Span::styled(
+                status.time.format(" %H:%M:%S ").to_string(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(status.text.to_owned(), style),
+        ];
+        if status.level == StatusLevel::Error {
+            line.push(Span::styled(
+                " · Esc to dismiss",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        Paragraph::new(Spans::from(line)).render(area, buf)
+    }
+}
+
+#[test]
+fn test_status_queue() {
+    let mut queue = StatusQueue::default();
+    assert!(queue.current(Instant::now()).is_none());
+
+    queue.error(crate::ui::TuiError::TuiInternalErr).info("Copied");
+    let now = Instant::now();
+    assert_eq!(queue.current(now).unwrap().text, "Copied");
+    assert!(!queue.current(now).unwrap().is_fading(now));
+
+    let fading = now + TRANSIENT - FADE;
+    assert!(queue.current(fading).unwrap().is_fading(fading));
+
+    // Transient message is gone, error stays
+    let later = now + TRANSIENT * 2;
+    assert_eq!(queue.current(later).unwrap().level, StatusLevel::Error);
+    assert!(queue.has_error());
+
+    queue.dismiss();
+    assert!(!queue.has_error());
+    assert!(queue.current(later).is_none());
+}
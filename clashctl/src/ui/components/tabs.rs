@@ -1,22 +1,27 @@
+use std::time::Instant;
+
 use tui::{
     style::{Color, Modifier, Style},
     text::{Span, Spans},
     widgets::{Tabs as TuiTabs, Widget},
 };
 
-use crate::ui::{define_widget, utils::get_block, TuiStates};
+use crate::ui::{
+    define_widget,
+    utils::{get_block, Health},
+};
 
 define_widget!(Tabs);
 
 impl<'a> Widget for Tabs<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let len = TuiStates::TITLES.len();
+        let len = self.state.titles.len();
         let range = if self.state.show_debug {
             0..len
         } else {
             0..len - 1
         };
-        let titles = TuiStates::TITLES[range]
+        let titles = self.state.titles[range]
             .iter()
             .enumerate()
             .map(|(i, t)| {
@@ -26,8 +31,49 @@ impl<'a> Widget for Tabs<'a> {
                 ))
             })
             .collect();
+        let mut title = "Clashctl".to_owned();
+        if let Some(server) = self.state.config_state.server() {
+            title += &format!(" · {}", server.display_name());
+        }
+        if let Some(ref version) = self.state.version {
+            title += &format!(" · Clash {}", version.version.to_string());
+            if let Some(edition) = version.edition() {
+                title += &format!(" {}", edition);
+            }
+        }
+        if let Some(config) = self.state.config_state.clash() {
+            title += &format!(" · {} mode", config.mode);
+        }
+        if self.state.paused {
+            title += " · PAUSED";
+        }
+        if self.state.read_only {
+            title += " · read-only";
+        }
+        // Glyph of polling health goes before the title, colored on its own
+        let block = match self.state.poll_health.health(Instant::now()) {
+            Some(health) => {
+                let color = match health {
+                    Health::Ok => Color::Green,
+                    Health::Stale => Color::Yellow,
+                    Health::Failing => Color::Red,
+                };
+                let mut spans = vec![
+                    Span::styled(" ●", Style::default().fg(color)),
+                    Span::raw(format!(" {} ", title)),
+                ];
+                if let Some(error) = self.state.poll_health.last_error() {
+                    spans.push(Span::styled(
+                        format!("· {} ", error),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                get_block(&title).title(Spans::from(spans))
+            }
+            None => get_block(&title),
+        };
         let tabs = TuiTabs::new(titles)
-            .block(get_block("Clashctl"))
+            .block(block)
             .highlight_style(
                 Style::default()
                     .fg(Color::White)
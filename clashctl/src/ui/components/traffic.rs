@@ -4,12 +4,16 @@ use tui::{
     style::{Color, Style},
     symbols::bar::Set,
     text::Span,
-    widgets::Widget,
+    widgets::{Paragraph, Widget},
 };
 
 use crate::{
     components::{Footer, FooterItem, FooterWidget, Sparkline},
-    ui::{define_widget, utils::get_block},
+    ui::{
+        define_widget,
+        utils::{get_block, get_text_style},
+        StreamState,
+    },
 };
 
 pub const DOTS: Set = Set {
@@ -42,9 +46,18 @@ define_widget!(Traffics);
 
 impl<'a> Widget for Traffics<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let traffic_size = area.width - 2;
+        if self.state.traffic_stream == StreamState::Unsupported {
+            Paragraph::new("Traffic is unsupported on this server")
+                .block(get_block("Traffic"))
+                .style(get_text_style())
+                .render(area, buf);
+            return;
+        }
 
-        let traffics = self.state.traffics.iter().rev().take(traffic_size.into());
+        let traffic_size = area.width.saturating_sub(2);
+
+        let traffics = self.state.recent_traffics().take(traffic_size.into());
+        let current = self.state.current_traffic();
 
         let (up, down): (Vec<_>, Vec<_>) = traffics.map(|x| (x.up, x.down)).unzip();
 
@@ -53,7 +66,11 @@ impl<'a> Widget for Traffics<'a> {
             *down.iter().max().unwrap_or(&100),
         );
 
-        let title = format!("▲ Max = {}/s", ByteSize(up_max).to_string_as(true));
+        let title = format!(
+            "▲ {}/s (Max = {}/s)",
+            ByteSize(current.up).to_string_as(true),
+            ByteSize(up_max).to_string_as(true)
+        );
 
         let up_line = Sparkline::default()
             .data(&up)
@@ -84,7 +101,8 @@ impl<'a> Widget for Traffics<'a> {
         let mut footer = Footer::default();
         footer
             .push_left(FooterItem::span(Span::raw(format!(
-                " ▼ Max = {}/s ",
+                " ▼ {}/s (Max = {}/s) ",
+                ByteSize(current.down).to_string_as(true),
                 ByteSize(down_max).to_string_as(true)
             ))))
             .left_offset(1);
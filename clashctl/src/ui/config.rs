@@ -3,7 +3,7 @@ use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use once_cell::sync::OnceCell;
 use smart_default::SmartDefault;
 
-use crate::interactive::{clashctl::model::Config as ConfigModel, Config, ConfigData};
+use crate::interactive::{clashctl::model::Config as ConfigModel, Config, ConfigData, Server};
 
 // static CONFIG: OnceCell<RwLock<Config>> = OnceCell::new();
 static CONFIG: OnceCell<RwLock<Config>> = OnceCell::new();
@@ -39,6 +39,16 @@ pub struct ConfigState {
 }
 
 impl ConfigState {
+    /// Server in use
+    pub fn server(&self) -> Option<&Server> {
+        let using = self.clashctl.using.as_ref()?;
+        self.clashctl.servers.iter().find(|x| &x.url == using)
+    }
+
+    pub fn servers(&self) -> &[Server] {
+        &self.clashctl.servers
+    }
+
     pub fn clashctl_list(&self) -> impl Iterator<Item = (&str, String)> {
         let server = self
             .clashctl
@@ -54,7 +64,18 @@ impl ConfigState {
             .and_then(|x| x.to_str())
             .unwrap_or("N/A")
             .to_string();
-        [("Server", server), ("Log dir", log_dir)].into_iter()
+        let latency = format!(
+            "{} / {} ms",
+            self.clashctl.tui.latency.low, self.clashctl.tui.latency.mid
+        );
+        let theme = format!("{:?}", self.clashctl.tui.theme.preset);
+        [
+            ("Server", server),
+            ("Log dir", log_dir),
+            ("Latency", latency),
+            ("Theme", theme),
+        ]
+        .into_iter()
     }
 
     pub fn clash_list(&self) -> impl Iterator<Item = (&str, String)> {
@@ -90,6 +111,10 @@ impl ConfigState {
         }
     }
 
+    pub fn clash(&self) -> Option<&ConfigModel> {
+        self.clash.as_ref()
+    }
+
     pub fn update_clash(&mut self, config: ConfigModel) {
         self.clash = Some(config)
     }
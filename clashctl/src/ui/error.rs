@@ -18,6 +18,9 @@ pub enum TuiError {
     #[error("TUI internal error")]
     TuiInternalErr,
 
+    #[error("Unable to serve on port {0} ({1})")]
+    ServeError(u16, std::io::Error),
+
     #[error("Set logger error ({0})")]
     SetLoggerError(#[from] log::SetLoggerError),
 }
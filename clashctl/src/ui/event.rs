@@ -1,15 +1,21 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display, time::Duration};
 
 use clashctl_core::model::{ConnectionsWithSpeed, Log, Proxies, Rules, Traffic, Version};
-use crossterm::event::{KeyCode as KC, KeyEvent as KE, KeyModifiers as KM};
+use crossterm::event::{KeyCode as KC, KeyEvent as KE, KeyModifiers as KM, MouseEvent};
 use log::Level;
+use smart_default::SmartDefault;
 use tui::{
     style::{Color, Style},
     text::{Span, Spans},
 };
 
 use crate::{
-    ui::{components::MovableListItem, utils::AsColor, TuiError, TuiResult},
+    interactive::{KeyAction, Server},
+    ui::{
+        components::{MovableListItem, ProxyGroup},
+        utils::AsColor,
+        TuiError, TuiResult,
+    },
     Action,
 };
 
@@ -18,6 +24,12 @@ use crate::{
 pub enum Event {
     Quit,
     Action(Action),
+    // Raw key event, turned into [`InputEvent`] by [`crate::TuiStates`] unless
+    // it's being consumed by text input
+    Key(KE),
+    Mouse(MouseEvent),
+    // New size of terminal, (columns, rows)
+    Resize(u16, u16),
     Input(InputEvent),
     Update(UpdateEvent),
     Diagnostic(DiagnosticEvent),
@@ -35,6 +47,18 @@ impl<'a> MovableListItem<'a> for Event {
                 Span::styled("⇵  ", Style::default().fg(Color::Yellow)),
                 Span::raw(event.to_string()),
             ]),
+            Event::Key(event) => Spans(vec![
+                Span::styled("✜  ", Style::default().fg(Color::Green)),
+                Span::raw(format!("{:?}", event)),
+            ]),
+            Event::Mouse(event) => Spans(vec![
+                Span::styled("✜  ", Style::default().fg(Color::Green)),
+                Span::raw(format!("{:?}", event)),
+            ]),
+            Event::Resize(columns, rows) => Spans(vec![
+                Span::styled("✜  ", Style::default().fg(Color::Green)),
+                Span::raw(format!("Resize {}x{}", columns, rows)),
+            ]),
             Event::Input(event) => Spans(vec![
                 Span::styled("✜  ", Style::default().fg(Color::Green)),
                 Span::raw(format!("{:?}", event)),
@@ -58,7 +82,7 @@ impl Event {
     }
 
     pub fn is_interface(&self) -> bool {
-        matches!(self, Event::Input(_))
+        matches!(self, Event::Input(_) | Event::Key(_) | Event::Mouse(_))
     }
 
     pub fn is_update(&self) -> bool {
@@ -68,6 +92,68 @@ impl Event {
     pub fn is_diagnostic(&self) -> bool {
         matches!(self, Event::Diagnostic(_))
     }
+
+    /// Event of a bound [`KeyAction`], triggered by `key`
+    pub fn from_action(action: KeyAction, key: KE) -> Self {
+        let fast = matches!(key.modifiers, KM::CONTROL | KM::SHIFT);
+        let list = |code| InputEvent::List(ListEvent { fast, code });
+        let input = match action {
+            KeyAction::MoveUp => list(KC::Up),
+            KeyAction::MoveDown => list(KC::Down),
+            KeyAction::Select => list(KC::Enter),
+            KeyAction::Quit => return Event::Quit,
+            KeyAction::Expand => InputEvent::ToggleHold,
+            KeyAction::TestDelay => InputEvent::TestLatency,
+            KeyAction::TestMember => InputEvent::TestMemberLatency,
+            KeyAction::Search => InputEvent::Search,
+            KeyAction::TestAllLatency => InputEvent::TestAllLatency,
+            KeyAction::TestAndSelectFastest => InputEvent::TestAndSelectFastest,
+            KeyAction::OlderSample => InputEvent::OlderSample,
+            KeyAction::NewerSample => InputEvent::NewerSample,
+            KeyAction::IncreaseTestTimeout => InputEvent::IncreaseDelayTimeout,
+            KeyAction::DecreaseTestTimeout => InputEvent::DecreaseDelayTimeout,
+            KeyAction::SearchAllGroups => InputEvent::GlobalSearch,
+            KeyAction::NextMatch => InputEvent::NextMatch,
+            KeyAction::JumpToCurrent => InputEvent::JumpToCurrent,
+            KeyAction::ToggleDelaySort => InputEvent::ToggleDelaySort,
+            KeyAction::ToggleHideDead => InputEvent::ToggleHideDead,
+            KeyAction::ToggleCompact => InputEvent::ToggleCompact,
+            KeyAction::ToggleSingleLineGroups => InputEvent::ToggleSingleLine,
+            KeyAction::TogglePin => InputEvent::TogglePin,
+            KeyAction::NextGroupFilter => InputEvent::NextGroupFilter,
+            KeyAction::NextGroupSort => InputEvent::NextGroupSort,
+            KeyAction::EditAlias => InputEvent::EditAlias,
+            KeyAction::ShowGroupStats => InputEvent::ShowGroupStats,
+            KeyAction::ShowSelections => InputEvent::ShowSelections,
+            KeyAction::FlipGlobalDirect => InputEvent::FlipGlobalDirect,
+            KeyAction::SelectNextMember => InputEvent::NextSelection,
+            KeyAction::SelectPreviousMember => InputEvent::PrevSelection,
+            KeyAction::UpdateProvider => InputEvent::UpdateProvider,
+            KeyAction::CopyName => InputEvent::CopyName,
+            KeyAction::OpenDashboard => InputEvent::OpenDashboard,
+            KeyAction::ExportProxies => InputEvent::ExportProxies,
+            KeyAction::ToggleLogRecording => InputEvent::ToggleLogRecord,
+            KeyAction::MatchRule => InputEvent::MatchRule,
+            KeyAction::NextLogLevel => InputEvent::NextLogLevel,
+            KeyAction::NextMode => InputEvent::NextMode,
+            KeyAction::ReloadConfig => InputEvent::ReloadConfig,
+            KeyAction::PickServer => InputEvent::PickServer,
+            KeyAction::Reconnect => InputEvent::Reconnect,
+            KeyAction::TogglePause => InputEvent::TogglePause,
+            KeyAction::OpenPalette => InputEvent::OpenPalette,
+        };
+        Event::Input(input)
+    }
+
+    /// Interpret key following the `prefix` key of a chord
+    pub fn from_chord(prefix: char, key: KE) -> Self {
+        match (prefix, key.code) {
+            ('z', KC::Char('a')) => Event::Input(InputEvent::ToggleFold),
+            ('z', KC::Char('M')) => Event::Input(InputEvent::CollapseAll),
+            ('z', KC::Char('R')) => Event::Input(InputEvent::ExpandAll),
+            _ => Event::Input(InputEvent::Other(key)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -79,6 +165,69 @@ pub enum InputEvent {
     ToggleHold,
     List(ListEvent),
     TestLatency,
+    // Test only the pointed member of current group
+    TestMemberLatency,
+    TestAllLatency,
+    // Step through delay samples of pointed proxy
+    OlderSample,
+    NewerSample,
+    // Adjust timeout of following latency tests
+    IncreaseDelayTimeout,
+    DecreaseDelayTimeout,
+    Search,
+    // Search across all groups
+    GlobalSearch,
+    NextMatch,
+    ToggleDelaySort,
+    NextLogLevel,
+    // First key of a chord, next key is interpreted by [`Event::from_chord`]
+    Chord(char),
+    JumpToCurrent,
+    UpdateProvider,
+    ToggleHideDead,
+    ToggleCompact,
+    // Show collapsed groups in one line, without latency signs
+    ToggleSingleLine,
+    CopyName,
+    // Open pointed group or proxy in web dashboard
+    OpenDashboard,
+    // Pin current group to top, or unpin it
+    TogglePin,
+    // Find the rule matching a host typed in
+    MatchRule,
+    // Stop or resume polling the server
+    TogglePause,
+    // Flip GLOBAL group between DIRECT and the proxy selected before
+    FlipGlobalDirect,
+    // Test group under cursor, then select its fastest member
+    TestAndSelectFastest,
+    // Select member after the current one of the group under cursor
+    NextSelection,
+    PrevSelection,
+    // Show latency bands of current group
+    ShowGroupStats,
+    // Show current member of every group
+    ShowSelections,
+    // Write all groups to a file
+    ExportProxies,
+    // Start or stop appending logs received to a file
+    ToggleLogRecord,
+    // Reconnect to the server in use
+    Reconnect,
+    // Show only groups of the next type filter
+    NextGroupFilter,
+    // Order groups by the next of name, type and so on
+    NextGroupSort,
+    // Type alias shown in place of name of pointed proxy
+    EditAlias,
+    // List all commands to pick one to run
+    OpenPalette,
+    ToggleFold,
+    CollapseAll,
+    ExpandAll,
+    ReloadConfig,
+    NextMode,
+    PickServer,
     NextSort,
     PrevSort,
     Other(KE),
@@ -95,12 +244,36 @@ pub struct ListEvent {
 pub enum UpdateEvent {
     Config(crate::interactive::clashctl::model::Config),
     Connection(ConnectionsWithSpeed),
-    Version(Version),
+    // `None` if server is too old to tell
+    Version(Option<Version>),
     Traffic(Traffic),
     Proxies(Proxies),
+    // Groups built before proxies are fully loaded, followed by `Proxies`
+    ProxyGroups(Vec<ProxyGroup<'static>>),
     Rules(Rules),
     Log(Log),
-    ProxyTestLatencyDone,
+    LogStream(StreamState),
+    TrafficStream(StreamState),
+    // Delay of proxies, `None` for failed ones
+    ProxyLatency(HashMap<String, Option<u64>>),
+    ProxySelected { group: String, proxy: String },
+    ProxyTestProgress { done: usize, total: usize },
+    // Latency tests of `groups` are finished or canceled
+    ProxyTestLatencyDone { groups: Vec<String> },
+    // Names of providers updated, empty if group is not backed by any
+    ProviderUpdateDone { group: String, providers: Vec<String> },
+    // Connected to another server, followed by its states
+    ServerSwitched(Server),
+    // Client of the same server rebuilt, followed by its states
+    Reconnected(Server),
+    ReconnectFailed(String),
+    // A round of polling succeeded after the duration
+    PollOk(Duration),
+    PollFailed(String),
+    // Failure to be shown in status line
+    StatusError(String),
+    // Message shown in status line for a few seconds
+    StatusInfo(String),
 }
 
 impl Display for UpdateEvent {
@@ -111,13 +284,46 @@ impl Display for UpdateEvent {
             UpdateEvent::Version(x) => write!(f, "{:?}", x),
             UpdateEvent::Traffic(x) => write!(f, "{:?}", x),
             UpdateEvent::Proxies(x) => write!(f, "{:?}", x),
+            UpdateEvent::ProxyGroups(x) => write!(f, "{} groups built", x.len()),
             UpdateEvent::Rules(x) => write!(f, "{:?}", x),
             UpdateEvent::Log(x) => write!(f, "{:?}", x),
-            UpdateEvent::ProxyTestLatencyDone => write!(f, "Test latency done"),
+            UpdateEvent::LogStream(x) => write!(f, "Log stream {:?}", x),
+            UpdateEvent::TrafficStream(x) => write!(f, "Traffic stream {:?}", x),
+            UpdateEvent::ProxyLatency(x) => write!(f, "{:?}", x),
+            UpdateEvent::ProxySelected { group, proxy } => {
+                write!(f, "Group {} selected {}", group, proxy)
+            }
+            UpdateEvent::ProxyTestProgress { done, total } => {
+                write!(f, "Tested {}/{} groups", done, total)
+            }
+            UpdateEvent::ProxyTestLatencyDone { groups } => {
+                write!(f, "Test latency done: {:?}", groups)
+            }
+            UpdateEvent::ProviderUpdateDone { group, providers } => {
+                write!(f, "Providers of {} updated: {:?}", group, providers)
+            }
+            UpdateEvent::ServerSwitched(server) => write!(f, "Switched to {}", server),
+            UpdateEvent::Reconnected(server) => write!(f, "Reconnected to {}", server),
+            UpdateEvent::ReconnectFailed(x) => write!(f, "{}", x),
+            UpdateEvent::PollOk(x) => write!(f, "Polled in {:?}", x),
+            UpdateEvent::PollFailed(x) => write!(f, "Polling failed ({})", x),
+            UpdateEvent::StatusError(x) => write!(f, "{}", x),
+            UpdateEvent::StatusInfo(x) => write!(f, "{}", x),
         }
     }
 }
 
+/// State of a longhaul stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+pub enum StreamState {
+    #[default]
+    Connecting,
+    Connected,
+    Reconnecting,
+    // Endpoint is missing on server, or kept failing
+    Unsupported,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum DiagnosticEvent {
@@ -129,10 +335,8 @@ impl TryFrom<KC> for Event {
 
     fn try_from(value: KC) -> TuiResult<Self> {
         match value {
-            KC::Char('q') | KC::Char('x') => Ok(Event::Quit),
-            KC::Char('t') => Ok(Event::Input(InputEvent::TestLatency)),
+            KC::Char('z') => Ok(Event::Input(InputEvent::Chord('z'))),
             KC::Esc => Ok(Event::Input(InputEvent::Esc)),
-            KC::Char(' ') => Ok(Event::Input(InputEvent::ToggleHold)),
             KC::Char(char) if char.is_ascii_digit() => Ok(Event::Input(InputEvent::TabGoto(
                 char.to_digit(10)
                     .expect("char.is_ascii_digit() should be able to parse into number")
@@ -148,7 +352,7 @@ impl From<KE> for Event {
         match (value.modifiers, value.code) {
             (KM::CONTROL, KC::Char('c')) => Self::Quit,
             (KM::CONTROL, KC::Char('d')) => Self::Input(InputEvent::ToggleDebug),
-            (modi, arrow @ (KC::Left | KC::Right | KC::Up | KC::Down | KC::Enter)) => {
+            (modi, arrow @ (KC::Left | KC::Right)) => {
                 Event::Input(InputEvent::List(ListEvent {
                     fast: matches!(modi, KM::CONTROL | KM::SHIFT),
                     code: arrow,
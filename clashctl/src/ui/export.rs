@@ -0,0 +1,175 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use clashctl_core::model::ProxyType;
+use serde::Serialize;
+
+/// Where proxies are exported to if `export_path` is not set in config
+pub const DEFAULT_EXPORT_PATH: &str = "clashctl-proxies.json";
+
+/// Plain copy of proxy tree for sharing or debugging, see
+/// [`crate::components::ProxyTree::to_export`]. Only written, never read
+/// back or sent to Clash
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProxyTreeExport {
+    pub time: DateTime<Local>,
+    pub groups: Vec<GroupExport>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroupExport {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: ProxyType,
+    // Name of current member
+    pub now: Option<String>,
+    pub members: Vec<MemberExport>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemberExport {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: ProxyType,
+    pub udp: bool,
+    // Latest delay sample, `0` for failed tests as Clash does
+    pub delay: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+}
+
+impl ExportFormat {
+    /// YAML for `.yaml` & `.yml`, JSON otherwise
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// `base` with `time` inserted before its extension, like
+/// `proxies-20221015-201530.yaml` out of `proxies.yaml`
+pub fn export_path(base: &Path, time: DateTime<Local>) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map(|x| x.to_string_lossy())
+        .unwrap_or_default();
+    let mut name = format!("{}-{}", stem, time.format("%Y%m%d-%H%M%S"));
+    if let Some(ext) = base.extension() {
+        name += ".";
+        name += &ext.to_string_lossy();
+    }
+    base.with_file_name(name)
+}
+
+impl ProxyTreeExport {
+    /// Write to `path` in format decided by its extension
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let content = match ExportFormat::of(path) {
+            ExportFormat::Json => serde_json::to_string_pretty(self)?,
+            ExportFormat::Yaml => self.to_yaml(),
+        };
+        fs::write(path, content)
+    }
+
+    /// Fields are written in declared order. Strings are double quoted the
+    /// same way as JSON, which YAML accepts as is
+    pub fn to_yaml(&self) -> String {
+        let quote = |x: &str| serde_json::Value::from(x).to_string();
+        let optional = |x: Option<String>| x.unwrap_or_else(|| "null".to_owned());
+
+        let mut ret = String::new();
+        let _ = writeln!(ret, "time: {}", quote(&self.time.to_rfc3339()));
+        if self.groups.is_empty() {
+            ret += "groups: []\n";
+            return ret;
+        }
+        ret += "groups:\n";
+        for group in &self.groups {
+            let _ = writeln!(ret, "  - name: {}", quote(&group.name));
+            let _ = writeln!(ret, "    type: {}", quote(group.proxy_type.name()));
+            let _ = writeln!(ret, "    now: {}", optional(group.now.as_deref().map(quote)));
+            if group.members.is_empty() {
+                ret += "    members: []\n";
+                continue;
+            }
+            ret += "    members:\n";
+            for member in &group.members {
+                let _ = writeln!(ret, "      - name: {}", quote(&member.name));
+                let _ = writeln!(ret, "        type: {}", quote(member.proxy_type.name()));
+                let _ = writeln!(ret, "        udp: {}", member.udp);
+                let _ = writeln!(
+                    ret,
+                    "        delay: {}",
+                    optional(member.delay.map(|x| x.to_string()))
+                );
+            }
+        }
+        ret
+    }
+}
+
+#[test]
+fn test_export() {
+    use chrono::TimeZone;
+
+    let time = Local.ymd(2022, 10, 15).and_hms(20, 15, 30);
+    assert_eq!(
+        export_path(Path::new("/tmp/proxies.yaml"), time),
+        Path::new("/tmp/proxies-20221015-201530.yaml")
+    );
+    assert_eq!(
+        export_path(Path::new(DEFAULT_EXPORT_PATH), time),
+        Path::new("clashctl-proxies-20221015-201530.json")
+    );
+    assert_eq!(ExportFormat::of(Path::new("a.yml")), ExportFormat::Yaml);
+    assert_eq!(ExportFormat::of(Path::new("a")), ExportFormat::Json);
+
+    let export = ProxyTreeExport {
+        time,
+        groups: vec![GroupExport {
+            name: "Proxy \"main\"".to_owned(),
+            proxy_type: ProxyType::Selector,
+            now: Some("HK".to_owned()),
+            members: vec![
+                MemberExport {
+                    name: "HK".to_owned(),
+                    proxy_type: ProxyType::Shadowsocks,
+                    udp: true,
+                    delay: Some(120),
+                },
+                MemberExport {
+                    name: "JP".to_owned(),
+                    proxy_type: ProxyType::Vmess,
+                    udp: false,
+                    delay: None,
+                },
+            ],
+        }],
+    };
+    let yaml = export.to_yaml();
+    assert!(yaml.contains(concat!(
+        "  - name: \"Proxy \\\"main\\\"\"\n",
+        "    type: \"Selector\"\n",
+        "    now: \"HK\"\n",
+    )));
+    assert!(yaml.contains(concat!(
+        "      - name: \"JP\"\n",
+        "        type: \"Vmess\"\n",
+        "        udp: false\n",
+        "        delay: null\n",
+    )));
+
+    let json: serde_json::Value = serde_json::to_value(&export).unwrap();
+    assert_eq!(json["groups"][0]["members"][0]["type"], "Shadowsocks");
+    assert_eq!(json["groups"][0]["members"][0]["delay"], 120);
+}
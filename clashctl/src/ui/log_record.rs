@@ -0,0 +1,133 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use clashctl_core::model::Log;
+
+/// Where logs are recorded to if `log_record_path` is not set in config
+pub const DEFAULT_LOG_RECORD_PATH: &str = "clashctl-logs.log";
+
+/// Recorded logs are flushed to file at least this often
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRecordFormat {
+    // `<time> <LEVEL> <payload>`
+    Plain,
+    // One object per line, with `time`, `type` & `payload`
+    Json,
+}
+
+impl LogRecordFormat {
+    /// JSON lines for `.json` & `.jsonl`, plain text otherwise
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json" | "jsonl") => Self::Json,
+            _ => Self::Plain,
+        }
+    }
+
+    pub fn line(self, log: &Log, time: DateTime<Local>) -> String {
+        match self {
+            Self::Plain => format!(
+                "{} {} {}",
+                time.format("%Y-%m-%d %H:%M:%S"),
+                log.log_type,
+                log.payload
+            ),
+            Self::Json => serde_json::json!({
+                "time": time,
+                "type": log.log_type,
+                "payload": log.payload,
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// File logs of the stream are appended to as they're received, one line
+/// each. Clones write to the same file
+#[derive(Clone)]
+pub struct LogRecorder {
+    path: PathBuf,
+    format: LogRecordFormat,
+    writer: Arc<Mutex<(BufWriter<File>, Instant)>>,
+}
+
+impl LogRecorder {
+    /// Open `path` in append mode, so records of earlier sessions are kept
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            format: LogRecordFormat::of(&path),
+            path,
+            writer: Arc::new(Mutex::new((BufWriter::new(file), Instant::now()))),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, log: &Log) -> io::Result<()> {
+        let line = self.format.line(log, Local::now());
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer.0, "{}", line)?;
+        drop(writer);
+        self.flush_if_due()
+    }
+
+    /// Flush if it's not flushed for [`FLUSH_INTERVAL`], so records show up
+    /// in file without flushing every line
+    pub fn flush_if_due(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.1.elapsed() < FLUSH_INTERVAL {
+            return Ok(());
+        }
+        writer.1 = Instant::now();
+        writer.0.flush()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.1 = Instant::now();
+        writer.0.flush()
+    }
+}
+
+impl fmt::Debug for LogRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogRecorder")
+            .field("path", &self.path)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+#[test]
+fn test_log_record_line() {
+    use chrono::{NaiveDateTime, TimeZone};
+    use clashctl_core::model::Level;
+
+    let log = Log {
+        log_type: Level::Warning,
+        payload: "dial DIRECT failed".to_owned(),
+    };
+    let naive = NaiveDateTime::parse_from_str("2022-10-15 20:15:30", "%Y-%m-%d %H:%M:%S").unwrap();
+    let time = Local.from_local_datetime(&naive).unwrap();
+    assert_eq!(
+        LogRecordFormat::of(Path::new("logs.txt")).line(&log, time),
+        "2022-10-15 20:15:30 WARN dial DIRECT failed"
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&LogRecordFormat::of(Path::new("logs.jsonl")).line(&log, time))
+            .unwrap();
+    assert_eq!(json["payload"], "dial DIRECT failed");
+    assert_eq!(json["time"], serde_json::to_value(time).unwrap());
+}
@@ -2,7 +2,8 @@ pub mod components;
 pub mod pages;
 
 mod_use::mod_use![
-    utils, action, app, event, servo, state, error, tui_opt, config
+    utils, action, app, event, servo, state, error, tui_opt, config, persist, export,
+    serve, log_record
 ];
 
 macro_rules! define_widget {
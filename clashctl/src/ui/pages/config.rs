@@ -61,7 +61,7 @@ impl<'a> ConfigListItem<'a> {
 impl<'a> Widget for ConfigPage<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
         let width = area.width.saturating_sub(4).max(10);
-        let block = get_block("Config");
+        let block = get_block("Config · [r] Reload · [m] Switch mode");
         let list = ConfigListItem::title("Clash")
             .chain(self.state.clash_list().map(|x| ConfigListItem::Item {
                 label: x.0,
@@ -1,27 +1,70 @@
+use std::time::Instant;
+
+use unicode_width::UnicodeWidthStr;
+
 use tui::{
-    style::Style,
+    style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::Widget,
+    widgets::{Paragraph, Widget},
 };
 
 use crate::{
-    clashctl::model::Log,
-    components::{MovableList, MovableListItem},
-    define_widget, AsColor,
+    clashctl::model::{Level, Log},
+    components::{Consts, MovableList, MovableListItem},
+    define_widget, get_block, get_text_style, StreamState, HMS,
 };
 
-impl<'a> MovableListItem<'a> for Log {
+/// Log stamped with when it's received, as Clash doesn't send time of logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedLog {
+    pub received: Instant,
+    pub log: Log,
+    // From [`crate::interactive::Theme::log_style`] of its level
+    pub style: Style,
+}
+
+impl TimedLog {
+    pub fn new(log: Log, style: Style) -> Self {
+        Self {
+            received: Instant::now(),
+            log,
+            style,
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self.log.log_type {
+            Level::Error => Consts::LOG_ERROR_TAG,
+            Level::Warning => Consts::LOG_WARNING_TAG,
+            Level::Info => Consts::LOG_INFO_TAG,
+            Level::Debug => Consts::LOG_DEBUG_TAG,
+        }
+    }
+
+    /// Whether payload contains `pattern`, which should be lowercase
+    fn payload_matches(&self, pattern: &str) -> bool {
+        self.log.payload.to_lowercase().contains(pattern)
+    }
+}
+
+impl<'a> MovableListItem<'a> for TimedLog {
     fn to_spans(&self) -> Spans<'a> {
-        let color = self.log_type.clone().as_color();
         Spans::from(vec![
             Span::styled(
-                format!("{:<5}", self.log_type.to_string().to_uppercase()),
-                Style::default().fg(color),
+                format!("{:>3}", self.received.elapsed().coarse()),
+                Style::default().fg(Color::DarkGray),
             ),
             Span::raw(" "),
-            Span::raw(self.payload.to_owned()),
+            Span::styled(self.tag(), self.style.add_modifier(Modifier::BOLD)),
+            Span::raw(" "),
+            Span::styled(self.log.payload.to_owned(), self.style),
         ])
     }
+
+    /// Wrapped payload is aligned with its first line
+    fn wrap_indent(&self) -> usize {
+        self.to_spans().width() - self.log.payload.width()
+    }
 }
 
 define_widget!(LogPage);
@@ -29,7 +72,66 @@ define_widget!(LogPage);
 // TODO Pretty print parsed Log
 impl<'a> Widget for LogPage<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let list = MovableList::new("Logs", &self.state.log_state);
-        list.render(area, buf);
+        let level = &self.state.log_level;
+        if self.state.log_stream == StreamState::Unsupported {
+            Paragraph::new("Logs are unsupported on this server")
+                .block(get_block(&format!("Logs ≥ {}", level)))
+                .style(get_text_style())
+                .render(area, buf);
+            return;
+        }
+        let mut suffix = match self.state.log_stream {
+            StreamState::Connected | StreamState::Unsupported => "",
+            StreamState::Connecting => " (connecting…)",
+            StreamState::Reconnecting => " (reconnecting…)",
+        }
+        .to_owned();
+        if self.state.log_record.is_some() {
+            suffix += " · recording";
+        }
+        // Applied here instead of when logs are received, so clearing the
+        // filter brings back all buffered ones
+        let pattern = self.state.log_filter.as_deref().unwrap_or_default().to_lowercase();
+        let list = MovableList::new("", &self.state.log_state)
+            .filter(|x| x.log.log_type.rank() >= level.rank() && x.payload_matches(&pattern));
+        let title = match self.state.log_filter {
+            Some(ref filter) => {
+                let cursor = if self.state.log_searching { "_" } else { "" };
+                format!(
+                    "Logs ≥ {} /{}{} ({}/{}){}",
+                    level,
+                    filter,
+                    cursor,
+                    list.num_displayed(),
+                    self.state.log_state.len(),
+                    suffix
+                )
+            }
+            None => match self.state.log_state.capacity() {
+                Some(capacity) => format!(
+                    "Logs ≥ {} · {}/{}{}",
+                    level,
+                    self.state.log_state.len(),
+                    capacity,
+                    suffix
+                ),
+                None => format!("Logs ≥ {}{}", level, suffix),
+            },
+        };
+        list.title(title).render(area, buf);
     }
 }
+
+#[test]
+fn test_payload_matches() {
+    let log = TimedLog::new(
+        Log {
+            log_type: Level::Info,
+            payload: "[TCP] 127.0.0.1:5000 --> Google.com:443".to_owned(),
+        },
+        Style::default(),
+    );
+    assert!(log.payload_matches(""));
+    assert!(log.payload_matches("google.com"));
+    assert!(!log.payload_matches("github"));
+}
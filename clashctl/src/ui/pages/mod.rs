@@ -1,24 +1,31 @@
 mod config;
+#[cfg(feature = "connections")]
 mod connection;
 mod debug;
+#[cfg(feature = "logs")]
 mod log;
 mod proxy;
 mod rule;
 mod status;
 
+#[cfg(feature = "logs")]
+pub use self::log::TimedLog;
+
 use tui::{layout::Rect, Frame};
 
 use crate::{Backend, TuiStates};
 
 pub fn route(state: &TuiStates, area: Rect, f: &mut Frame<Backend>) {
-    match state.page_index {
-        0 => f.render_widget(status::StatusPage::new(state), area),
-        1 => f.render_widget(proxy::ProxyPage::new(state), area),
-        2 => f.render_widget(rule::RulePage::new(state), area),
-        3 => f.render_widget(connection::ConnectionPage::new(state), area),
-        4 => f.render_widget(log::LogPage::new(state), area),
-        5 => f.render_widget(config::ConfigPage::new(&state.config_state), area),
-        6 => f.render_widget(debug::DebugPage::new(state), area),
+    match state.title() {
+        "Status" => f.render_widget(status::StatusPage::new(state), area),
+        "Proxies" => f.render_widget(proxy::ProxyPage::new(state), area),
+        "Rules" => f.render_widget(rule::RulePage::new(state), area),
+        #[cfg(feature = "connections")]
+        "Conns" => f.render_widget(connection::ConnectionPage::new(state), area),
+        #[cfg(feature = "logs")]
+        "Logs" => f.render_widget(log::LogPage::new(state), area),
+        "Configs" => f.render_widget(config::ConfigPage::new(&state.config_state), area),
+        "Debug" => f.render_widget(debug::DebugPage::new(state), area),
         _ => unreachable!(),
     };
 }
@@ -1,4 +1,4 @@
-use clashctl_core::model::{Rule, RuleType, Rules};
+use clashctl_core::model::{Rule, RuleStats, RuleType, Rules};
 use tui::{
     style::{Color, Modifier, Style},
     text::{Span, Spans},
@@ -16,8 +16,58 @@ define_widget!(RulePage);
 
 impl<'a> Widget for RulePage<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        MovableList::new("Rules", &self.state.rule_state).render(area, buf);
+        let state = &self.state.rule_state;
+        if let Some(ref host) = self.state.rule_host {
+            let cursor = if self.state.rule_host_typing { "_" } else { "" };
+            let found = self.state.rules.match_host(host);
+            let result = match found.rule {
+                Some(rule) => {
+                    let r_type: &'static str = rule.rule_type.into();
+                    format!("{} by {},{}", rule.proxy, r_type, rule.payload)
+                }
+                None => "no match".to_owned(),
+            };
+            let note = match found.skipped {
+                0 => String::new(),
+                n => format!(" ({} unsupported rules skipped)", n),
+            };
+            let title = format!("Rules ?{}{} → {}{}", host, cursor, result, note);
+            return MovableList::new(title, state)
+                .filter(|x| Some(x) == found.rule)
+                .render(area, buf);
+        }
+        match self.state.rule_filter {
+            Some(ref filter) => {
+                let pattern = filter.to_lowercase();
+                let list = MovableList::new("", state).filter(|x| payload_matches(x, &pattern));
+                let cursor = if self.state.rule_searching { "_" } else { "" };
+                let title = format!(
+                    "Rules /{}{} ({}/{})",
+                    filter,
+                    cursor,
+                    list.num_displayed(),
+                    state.len()
+                );
+                list.title(title).render(area, buf)
+            }
+            None => MovableList::new(stats_title(&self.state.rule_stats), state).render(area, buf),
+        }
+    }
+}
+
+/// Like `Rules · 1024 total · DomainSuffix 800 · IPCIDR 200 · Match 1`
+fn stats_title(stats: &RuleStats) -> String {
+    let mut title = format!("Rules · {} total", stats.total);
+    for (rule_type, count) in stats.most_common() {
+        let r_type: &'static str = rule_type.into();
+        title += &format!(" · {} {}", r_type, count);
     }
+    title
+}
+
+/// Whether payload of `rule` contains `pattern`, which should be lowercase
+fn payload_matches(rule: &Rule, pattern: &str) -> bool {
+    rule.payload.to_lowercase().contains(pattern)
 }
 
 impl AsColor for RuleType {
@@ -72,3 +122,36 @@ impl<'a> MovableListItem<'a> for Rule {
         .into()
     }
 }
+
+#[test]
+fn test_payload_matches() {
+    let rule = Rule {
+        rule_type: RuleType::DomainSuffix,
+        payload: "Google.com".to_owned(),
+        proxy: "Proxy".to_owned(),
+    };
+    assert!(payload_matches(&rule, ""));
+    assert!(payload_matches(&rule, "google"));
+    assert!(!payload_matches(&rule, "proxy"));
+}
+
+#[test]
+fn test_stats_title() {
+    let rule = |rule_type| Rule {
+        rule_type,
+        payload: String::new(),
+        proxy: "DIRECT".to_owned(),
+    };
+    let rules = Rules {
+        rules: vec![
+            rule(RuleType::GeoIP),
+            rule(RuleType::DomainSuffix),
+            rule(RuleType::DomainSuffix),
+        ],
+    };
+    assert_eq!(
+        stats_title(&rules.stats()),
+        "Rules · 3 total · DomainSuffix 2 · GeoIP 1"
+    );
+    assert_eq!(stats_title(&RuleStats::default()), "Rules · 0 total");
+}
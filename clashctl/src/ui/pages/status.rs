@@ -6,57 +6,109 @@ use tui::{
     widgets::{Paragraph, Widget},
 };
 
-use crate::ui::{
-    components::{MovableListManage, Traffics},
-    define_widget, get_block, get_text_style,
+#[cfg(feature = "logs")]
+use super::log::LogPage;
+#[cfg(feature = "connections")]
+use crate::ui::components::MovableListManage;
+use crate::{
+    interactive::{LayoutConfig, Panel},
+    ui::{
+        components::{ProxyTreeWidget, Traffics},
+        define_widget, get_block, get_config, get_text_style,
+    },
 };
 
 define_widget!(StatusPage);
 
 impl<'a> Widget for StatusPage<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let main = Layout::default()
-            .constraints([Constraint::Length(35), Constraint::Min(0)])
-            .direction(Direction::Horizontal)
-            .split(area);
+        let tui = &get_config().tui;
+        let hidden = [
+            (Panel::Traffic, LayoutConfig::TRAFFIC_PANE),
+            (Panel::Logs, LayoutConfig::LOGS_PANE),
+        ]
+        .into_iter()
+        .filter(|(panel, _)| !tui.shows(*panel))
+        .map(|(_, pane)| pane)
+        .collect::<Vec<_>>();
+        // Logs pane is always hidden with `logs` compiled out
+        #[cfg_attr(not(feature = "logs"), allow(unused_variables))]
+        let [info, traffic, proxies, logs] = match tui.layout {
+            Some(ref layout) => layout.hiding(&hidden).split(area),
+            None => {
+                let main = Layout::default()
+                    .constraints([Constraint::Length(35), Constraint::Min(0)])
+                    .direction(Direction::Horizontal)
+                    .split(area);
+                if hidden.contains(&LayoutConfig::TRAFFIC_PANE) {
+                    [Some(main[0]), None, Some(main[1]), None]
+                } else {
+                    [Some(main[0]), Some(main[1]), None, None]
+                }
+            }
+        };
 
-        let last_traffic = self
-            .state
-            .traffics
-            .iter()
-            .last()
-            .map(|x| x.to_owned())
-            .unwrap_or_default();
+        if let Some(area) = info {
+            self.render_info(area, buf);
+        }
+        if let Some(area) = traffic {
+            Traffics::new(self.state).render(area, buf);
+        }
+        if let Some(area) = proxies {
+            ProxyTreeWidget::new(&self.state.proxy_tree).render(area, buf);
+        }
+        #[cfg(feature = "logs")]
+        if let Some(area) = logs {
+            LogPage::new(self.state).render(area, buf);
+        }
+    }
+}
+
+impl<'a> StatusPage<'a> {
+    fn render_info(&self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        let last_traffic = self.state.current_traffic();
 
         let (up_avg, down_avg) = match self.state.start_time {
             time if time.elapsed().as_secs() == 0 => ("?".to_string(), "?".to_string()),
             time => {
                 let elapsed = time.elapsed().as_secs();
-                let (up_all, down_all) = self
-                    .state
-                    .traffics
-                    .iter()
-                    .fold((0, 0), |(up, down), traffic| {
-                        (up + traffic.up, down + traffic.down)
-                    });
+                let (up_all, down_all) =
+                    (self.state.total_traffic.up, self.state.total_traffic.down);
 
                 (
                     ByteSize(up_all / elapsed).to_string_as(true) + "/s",
-                    ByteSize(down_all / elapsed).to_string_as(true),
+                    ByteSize(down_all / elapsed).to_string_as(true) + "/s",
                 )
             }
         };
 
-        let con_num = self.state.con_state.len().to_string();
-        let (total_up, total_down) = self.state.con_size;
-        let height = main[0].height;
+        #[cfg(feature = "connections")]
+        let (con_num, (total_up, total_down)) =
+            (self.state.con_state.len().to_string(), self.state.con_size);
+        #[cfg(not(feature = "connections"))]
+        let (con_num, (total_up, total_down)) = ("?".to_owned(), (0, 0));
+        let height = area.height;
         let clash_ver = self
             .state
             .version
             .to_owned()
             .map_or_else(|| "?".to_owned(), |v| v.version.to_string());
 
+        let (mode, port, allow_lan, log_level) = match self.state.config_state.clash() {
+            Some(config) => (
+                config.mode.to_string(),
+                config.port.to_string(),
+                config.allow_lan.to_string(),
+                config.log_level.to_string(),
+            ),
+            None => ("?".into(), "?".into(), "?".into(), "?".into()),
+        };
+
         let tails = [
+            ("Mode", mode.as_str()),
+            ("Port", port.as_str()),
+            ("Allow LAN", allow_lan.as_str()),
+            ("Log Level", log_level.as_str()),
             ("Clash Ver.", clash_ver.as_str()),
             ("Clashctl Ver.", env!("CARGO_PKG_VERSION")),
         ];
@@ -102,9 +154,6 @@ impl<'a> Widget for StatusPage<'a> {
         Paragraph::new(info_str)
             .block(get_block("Info"))
             .style(get_text_style())
-            .render(main[0], buf);
-
-        let traffic = Traffics::new(self.state);
-        traffic.render(main[1], buf)
+            .render(area, buf);
     }
 }
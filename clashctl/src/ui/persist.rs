@@ -0,0 +1,70 @@
+use std::{fs, path::Path};
+
+use log::{debug, warn};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{interactive::InteractiveError, ui::TuiResult};
+
+/// UI state kept across restarts. Purely local, never sent to Clash
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct PersistState {
+    pub proxy_tree: ProxyTreeSnapshot,
+}
+
+/// Position in proxy tree, matched by group name so it survives reordering
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyTreeSnapshot {
+    // Name of the focused group
+    pub group: Option<String>,
+    // Whether cursor is inside the focused group
+    pub inside: bool,
+    pub expanded_groups: Vec<String>,
+}
+
+impl PersistState {
+    /// Load state from `path`. Missing or broken file is not an error since
+    /// it's only for convenience, default state is used instead
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring broken state file @ {} ({})", path.display(), e);
+                Self::default()
+            }),
+            Err(e) => {
+                debug!("No state loaded from {} ({})", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> TuiResult<()> {
+        let content = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(InteractiveError::from)?;
+        fs::write(path, content).map_err(InteractiveError::ConfigFileIoError)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_persist_state() {
+    let path = std::env::temp_dir().join(format!("clashctl-state-{}.ron", std::process::id()));
+    let state = PersistState {
+        proxy_tree: ProxyTreeSnapshot {
+            group: Some("Proxy".to_owned()),
+            inside: true,
+            expanded_groups: vec!["Proxy".to_owned(), "Auto".to_owned()],
+        },
+    };
+    state.save(&path).unwrap();
+    assert_eq!(PersistState::load(&path), state);
+
+    fs::write(&path, "(broken").unwrap();
+    assert_eq!(PersistState::load(&path), PersistState::default());
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(PersistState::load(&path), PersistState::default());
+}
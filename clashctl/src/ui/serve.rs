@@ -0,0 +1,146 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use log::debug;
+
+use crate::ui::{ProxyTreeExport, TuiError, TuiResult, TuiStates};
+
+/// Clients taking longer than this to send request are dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Listen on localhost only, the state is not meant for other machines
+pub fn bind_serve(port: u16) -> TuiResult<TcpListener> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| TuiError::ServeError(port, e))
+}
+
+/// Answer requests one by one with states cached by TUI, see [`respond`]
+/// for paths. Nothing is ever sent to Clash
+pub fn serve_job(listener: TcpListener, state: Arc<RwLock<TuiStates>>) {
+    for stream in listener.incoming() {
+        // One broken client shouldn't stop serving others
+        if let Err(e) = stream.and_then(|x| handle_request(x, &state)) {
+            debug!("Failed to serve request ({})", e);
+        }
+    }
+}
+
+fn handle_request(mut stream: TcpStream, state: &RwLock<TuiStates>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers and body are of no use
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let (status, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        [method, path, ..] => {
+            let export = match state.read() {
+                Ok(state) if !state.proxy_tree.is_loading() => {
+                    Some(state.proxy_tree.to_export())
+                }
+                _ => None,
+            };
+            respond(method, path, export.as_ref())
+        }
+        _ => (400, error_body("Malformed request")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Status code and JSON body of `GET` requests to:
+///
+/// - `/proxies` (or `/`): all groups and members, same as exported ones
+/// - `/selections`: name of current member of each group
+///
+/// `export` is `None` until proxies are loaded
+fn respond(method: &str, path: &str, export: Option<&ProxyTreeExport>) -> (u16, String) {
+    let path = path.split('?').next().unwrap_or_default();
+    if !matches!(path, "/" | "/proxies" | "/selections") {
+        return (404, error_body("Not found"));
+    }
+    if method != "GET" {
+        return (405, error_body("Only GET is allowed"));
+    }
+    let export = match export {
+        Some(export) => export,
+        None => return (503, error_body("Proxies are not loaded yet")),
+    };
+    let body = if path == "/selections" {
+        let selections = export
+            .groups
+            .iter()
+            .map(|x| (x.name.as_str(), x.now.as_deref()))
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string(&selections)
+    } else {
+        serde_json::to_string(export)
+    };
+    match body {
+        Ok(body) => (200, body),
+        Err(e) => (500, error_body(&e.to_string())),
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[test]
+fn test_respond() {
+    use chrono::Local;
+    use clashctl_core::model::ProxyType;
+
+    use crate::ui::GroupExport;
+
+    let export = ProxyTreeExport {
+        time: Local::now(),
+        groups: vec![GroupExport {
+            name: "Proxy".to_owned(),
+            proxy_type: ProxyType::Selector,
+            now: Some("HK".to_owned()),
+            members: vec![],
+        }],
+    };
+
+    let (status, body) = respond("GET", "/selections?x=1", Some(&export));
+    assert_eq!(status, 200);
+    assert_eq!(body, r#"{"Proxy":"HK"}"#);
+
+    let (status, body) = respond("GET", "/", Some(&export));
+    assert_eq!(status, 200);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["groups"][0]["type"], "Selector");
+
+    assert_eq!(respond("GET", "/proxies", None).0, 503);
+    assert_eq!(respond("PUT", "/proxies", Some(&export)).0, 405);
+    assert_eq!(respond("GET", "/configs", Some(&export)).0, 404);
+}
@@ -1,43 +1,260 @@
+#[cfg(any(feature = "traffic", feature = "logs"))]
+use std::thread::sleep;
 use std::{
-    sync::mpsc::{Receiver, Sender},
-    thread::{scope, JoinHandle},
-    time::Duration,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread::{scope, spawn, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use clashctl_core::Clash;
-use crossterm::event::Event as CrossTermEvent;
-use log::warn;
-use rayon::prelude::*;
+#[cfg(feature = "logs")]
+use clashctl_core::model::Level;
+#[cfg(any(feature = "traffic", feature = "logs"))]
+use clashctl_core::LongHaul;
+use clashctl_core::{
+    model::{Proxies, Version},
+    resolve_test_url, Clash,
+};
+use crossterm::event::{Event as CrossTermEvent, MouseEvent, MouseEventKind};
+use log::{debug, info, warn};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+#[cfg(any(feature = "traffic", feature = "logs"))]
+use serde::de::DeserializeOwned;
 
+#[cfg(any(feature = "traffic", feature = "logs"))]
+use crate::ui::{event::StreamState, utils::Backoff};
 use crate::{
-    interactive::Flags,
+    interactive::{Flags, Panel},
     ui::{
+        components::ProxyGroup,
         event::{Event, UpdateEvent},
-        utils::{Interval, Pulse},
-        Action, TuiOpt, TuiResult,
+        get_config, get_config_mut,
+        utils::{fill_hook, run_hook, Interval, Pulse},
+        Action, TuiError, TuiOpt, TuiResult,
     },
 };
 
 pub type Job = JoinHandle<TuiResult<()>>;
 
-pub fn servo(tx: Sender<Event>, rx: Receiver<Action>, opt: TuiOpt, flags: Flags) -> TuiResult<()> {
+/// Max number of delay tests running at the same time
+const MAX_DELAY_TESTS: usize = 16;
+
+/// How often idle jobs check whether server has been switched or it's
+/// shutting down
+const STREAM_POLL: Duration = Duration::from_millis(200);
+
+/// How long to wait for a stream to respond when probing. Streams that are
+/// still silent after this are considered available, as idle log streams
+/// don't respond until there's a log.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait for a stopped stream reader to quit, shorter than the
+/// main loop waits for jobs on shutdown
+#[cfg(any(feature = "traffic", feature = "logs"))]
+const READER_JOIN_TIMEOUT: Duration = Duration::from_millis(400);
+
+/// Streams failed this many times in a row without ever connecting are given
+/// up until server is switched
+#[cfg(any(feature = "traffic", feature = "logs"))]
+const MAX_STREAM_FAILURES: usize = 5;
+
+/// Ticks of [`req_job`], which polls the server
+const REQ_TICK: Duration = Duration::from_millis(50);
+
+/// Number of groups built at a time when loading proxies for the first time,
+/// see [`send_proxies_lazily`]
+const LAZY_GROUPS: usize = 16;
+
+/// Seconds between polling states other than connections, if not configured
+const DEFAULT_POLL_INTERVAL: f32 = 5.0;
+
+/// Long running streams of the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Traffic,
+    Log,
+}
+
+impl Stream {
+    fn name(self) -> &'static str {
+        match self {
+            Stream::Traffic => "traffic",
+            Stream::Log => "log",
+        }
+    }
+
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn state_event(self, state: StreamState) -> Event {
+        Event::Update(match self {
+            Stream::Traffic => UpdateEvent::TrafficStream(state),
+            Stream::Log => UpdateEvent::LogStream(state),
+        })
+    }
+}
+
+/// Streams and APIs available on the server. Older or restricted setups may
+/// lack some streams, whose jobs then stay idle instead of reconnecting
+/// forever
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Capabilities {
+    traffic: bool,
+    log: bool,
+    // Told by `version`, so old servers without it get members tested one
+    // by one right away
+    group_delay: bool,
+}
+
+impl Capabilities {
+    fn probe(clash: &Arc<Clash>, version: Option<&Version>) -> Self {
+        // Silent streams are stopped when done, instead of waiting for a log
+        let stop = Arc::new(AtomicBool::new(false));
+        let clash = Arc::new(Clash::clone(clash).stop_on(stop.clone()));
+        let start = |open: fn(&Clash) -> clashctl_core::Result<()>| {
+            let (tx, rx) = channel();
+            let clash = clash.clone();
+            spawn(move || tx.send(open(&clash)));
+            rx
+        };
+        let traffic = start(|clash| clash.get_traffic().map(drop));
+        let log = start(|clash| clash.get_log().map(drop));
+
+        let deadline = Instant::now() + PROBE_TIMEOUT;
+        let is_available = |stream: Stream, rx: Receiver<clashctl_core::Result<()>>| {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Err(e)) if !e.kind().is_transient() => {
+                    warn!("{} stream is unsupported on this server ({})", stream.name(), e);
+                    false
+                }
+                _ => true,
+            }
+        };
+        let capabilities = Self {
+            traffic: is_available(Stream::Traffic, traffic),
+            log: is_available(Stream::Log, log),
+            group_delay: version.is_some_and(Version::has_group_delay),
+        };
+        stop.store(true, Ordering::Release);
+        capabilities
+    }
+
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn get(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Traffic => self.traffic,
+            Stream::Log => self.log,
+        }
+    }
+
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn get_mut(&mut self, stream: Stream) -> &mut bool {
+        match stream {
+            Stream::Traffic => &mut self.traffic,
+            Stream::Log => &mut self.log,
+        }
+    }
+}
+
+/// Client of the server in use, replaced when switching servers. Jobs with
+/// long running streams compare `generation` to find out theirs are outdated,
+/// and stop once `shutdown` is set
+struct Connection {
+    clash: RwLock<Arc<Clash>>,
+    capabilities: RwLock<Capabilities>,
+    generation: AtomicUsize,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Connection {
+    fn new(clash: Clash, version: Option<&Version>, shutdown: Arc<AtomicBool>) -> Self {
+        let clash = Arc::new(clash);
+        Self {
+            capabilities: RwLock::new(Capabilities::probe(&clash, version)),
+            clash: RwLock::new(clash),
+            generation: AtomicUsize::new(0),
+            shutdown,
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    fn clash(&self) -> Arc<Clash> {
+        self.clash.read().unwrap().clone()
+    }
+
+    fn generation(&self) -> usize {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn is_available(&self, stream: Stream) -> bool {
+        self.capabilities.read().unwrap().get(stream)
+    }
+
+    fn has_group_delay(&self) -> bool {
+        self.capabilities.read().unwrap().group_delay
+    }
+
+    /// Mark `stream` unavailable, unless server has been switched since
+    /// `generation`
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn disable(&self, stream: Stream, generation: usize) {
+        let mut capabilities = self.capabilities.write().unwrap();
+        if self.generation() == generation {
+            *capabilities.get_mut(stream) = false;
+        }
+    }
+
+    #[cfg(any(feature = "traffic", feature = "logs"))]
+    fn wait_for_switch(&self, generation: usize) {
+        while self.generation() == generation && !self.is_shut_down() {
+            sleep(STREAM_POLL);
+        }
+    }
+
+    fn replace(&self, clash: Clash, version: Option<&Version>) {
+        let clash = Arc::new(clash);
+        let probed = Capabilities::probe(&clash, version);
+        // Held until generation is bumped, so it's not disabled by old jobs
+        let mut capabilities = self.capabilities.write().unwrap();
+        *capabilities = probed;
+        *self.clash.write().unwrap() = clash;
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Run all jobs till `shutdown` is set, see [`Connection`]
+pub fn servo(
+    tx: Sender<Event>,
+    rx: Receiver<Action>,
+    opt: TuiOpt,
+    flags: Flags,
+    shutdown: Arc<AtomicBool>,
+) -> TuiResult<()> {
     let clash = flags.connect_server_from_config()?;
-    clash.get_version()?;
+    let version = clash.try_get_version()?;
+    let conn = Connection::new(clash, version.as_ref(), shutdown);
+    let paused = AtomicBool::new(false);
 
     scope(|r| -> TuiResult<()> {
         let tx_clone = tx.clone();
-        let handle1 = r.spawn(|| input_job(tx_clone));
+        let handle1 = r.spawn(|| input_job(tx_clone, &conn));
 
         let tx_clone = tx.clone();
-        let handle2 = r.spawn(|| traffic_job(tx_clone, &clash));
+        let handle2 = r.spawn(|| traffic_job(tx_clone, &conn));
 
         let tx_clone = tx.clone();
-        let handle3 = r.spawn(|| log_job(tx_clone, &clash));
+        let handle3 = r.spawn(|| log_job(tx_clone, &conn));
 
         let tx_clone = tx.clone();
-        let handle4 = r.spawn(|| req_job(&opt, &flags, tx_clone, &clash));
+        let handle4 = r.spawn(|| req_job(&opt, &flags, tx_clone, &conn, &paused));
 
-        let handle5 = r.spawn(|| action_job(&opt, &flags, tx, rx, &clash));
+        let handle5 = r.spawn(|| action_job(&opt, &flags, tx, rx, &conn, &paused));
 
         handle1.join().unwrap()?;
         handle2.join().unwrap()?;
@@ -49,10 +266,31 @@ pub fn servo(tx: Sender<Event>, rx: Receiver<Action>, opt: TuiOpt, flags: Flags)
     })
 }
 
-fn input_job(tx: Sender<Event>) -> TuiResult<()> {
+fn input_job(tx: Sender<Event>, conn: &Connection) -> TuiResult<()> {
     loop {
+        // Wait with timeout, so shutdown is not blocked until next key
+        match crossterm::event::poll(STREAM_POLL) {
+            Ok(true) => {}
+            Ok(false) if conn.is_shut_down() => break,
+            Ok(false) => continue,
+            Err(_) => {
+                tx.send(Event::Quit)?;
+                break;
+            }
+        }
         match crossterm::event::read() {
-            Ok(CrossTermEvent::Key(event)) => tx.send(Event::from(event))?,
+            Ok(CrossTermEvent::Key(event)) => tx.send(Event::Key(event))?,
+            // Ignore moving & dragging, which floods the channel
+            Ok(CrossTermEvent::Mouse(
+                event @ MouseEvent {
+                    kind:
+                        MouseEventKind::Down(_)
+                        | MouseEventKind::ScrollUp
+                        | MouseEventKind::ScrollDown,
+                    ..
+                },
+            )) => tx.send(Event::Mouse(event))?,
+            Ok(CrossTermEvent::Resize(columns, rows)) => tx.send(Event::Resize(columns, rows))?,
             Err(_) => {
                 tx.send(Event::Quit)?;
                 break;
@@ -63,54 +301,322 @@ fn input_job(tx: Sender<Event>) -> TuiResult<()> {
     Ok(())
 }
 
-fn req_job(_opt: &TuiOpt, _flags: &Flags, tx: Sender<Event>, clash: &Clash) -> TuiResult<()> {
-    let mut interval = Interval::every(Duration::from_millis(50));
-    let mut connection_pulse = Pulse::new(20); // Every 1 s
-    let mut proxies_pulse = Pulse::new(100); //   Every 5 s + 0 tick
-    let mut rules_pulse = Pulse::new(101); //     Every 5 s + 1 tick
-    let mut version_pulse = Pulse::new(102); //   Every 5 s + 2 tick
-    let mut config_pulse = Pulse::new(103); //    Every 5 s + 3 tick
+/// Time between polling states other than connections, from `--interval`
+/// or `poll_interval` in config
+pub fn poll_interval(opt: &TuiOpt) -> Duration {
+    let secs = opt
+        .interval
+        .or(get_config().tui.poll_interval)
+        .filter(|x| x.is_finite() && *x > 0.)
+        .unwrap_or(DEFAULT_POLL_INTERVAL);
+    Duration::from_secs_f32(secs)
+}
+
+/// Number of [`REQ_TICK`]s in [`poll_interval`]
+fn poll_ticks(opt: &TuiOpt) -> u64 {
+    let millis = poll_interval(opt).as_millis() as f32;
+    ((millis / REQ_TICK.as_millis() as f32).round() as u64).max(1)
+}
+
+fn req_job(
+    opt: &TuiOpt,
+    _flags: &Flags,
+    tx: Sender<Event>,
+    conn: &Connection,
+    paused: &AtomicBool,
+) -> TuiResult<()> {
+    let ticks = poll_ticks(opt);
+    let connections = get_config().tui.shows(Panel::Connections);
+    debug!("Polling every {} ticks", ticks);
+
+    let mut interval = Interval::every(REQ_TICK);
+    let new_pulses = || {
+        [
+            Pulse::new(20),        // Connection every 1 s
+            Pulse::new(ticks),     // Proxies
+            Pulse::new(ticks + 1), // Rules, 1 tick later
+            Pulse::new(ticks + 2), // Version, 2 ticks later
+            Pulse::new(ticks + 3), // Config, 3 ticks later
+        ]
+    };
+    let mut pulses = new_pulses();
+    let mut was_paused = false;
+    let mut loaded = false;
+    let mut generation = conn.generation();
+    let mut failing = false;
 
     loop {
-        if version_pulse.tick() {
-            tx.send(Event::Update(UpdateEvent::Version(clash.get_version()?)))?;
+        if conn.is_shut_down() {
+            return Ok(());
         }
-        if connection_pulse.tick() {
-            tx.send(Event::Update(UpdateEvent::Connection(
-                clash.get_connections()?.into(),
-            )))?;
+        if paused.load(Ordering::Acquire) {
+            was_paused = true;
+            interval.tick();
+            continue;
         }
-        if rules_pulse.tick() {
-            tx.send(Event::Update(UpdateEvent::Rules(clash.get_rules()?)))?;
+        if was_paused {
+            // Fresh pulses fire right away
+            was_paused = false;
+            pulses = new_pulses();
         }
-        if proxies_pulse.tick() {
-            tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
+        if conn.generation() != generation {
+            // Groups of the new server are loaded lazily again
+            generation = conn.generation();
+            loaded = false;
         }
-        if config_pulse.tick() {
-            tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+        // Failed requests are tried again on next pulses instead of stopping
+        // polling, so it picks up once server is back
+        let start = Instant::now();
+        match poll(&tx, &conn.clash(), &mut pulses, &mut loaded, connections) {
+            Err(TuiError::ClashCtl(e)) => {
+                if !failing {
+                    warn!("Polling server failed ({}), keep trying", e);
+                    failing = true;
+                }
+                tx.send(Event::Update(UpdateEvent::PollFailed(e.to_string())))?;
+            }
+            res => {
+                if res? {
+                    tx.send(Event::Update(UpdateEvent::PollOk(start.elapsed())))?;
+                }
+                if failing {
+                    info!("Polling server recovered");
+                    failing = false;
+                }
+            }
         }
         interval.tick();
     }
 }
 
-fn traffic_job(tx: Sender<Event>, clash: &Clash) -> TuiResult<()> {
-    let mut traffics = clash.get_traffic()?;
+/// Request states whose pulses are due and send them, returning whether any
+/// is requested. Connections are skipped unless `connections` is shown
+fn poll(
+    tx: &Sender<Event>,
+    clash: &Clash,
+    pulses: &mut [Pulse; 5],
+    loaded: &mut bool,
+    connections: bool,
+) -> TuiResult<bool> {
+    let [connection_pulse, proxies_pulse, rules_pulse, version_pulse, config_pulse] = pulses;
+    let due = [&proxies_pulse, &rules_pulse, &version_pulse, &config_pulse]
+        .iter()
+        .any(|x| x.is_pulse())
+        || (connections && connection_pulse.is_pulse());
+    if version_pulse.tick() {
+        tx.send(Event::Update(UpdateEvent::Version(clash.try_get_version()?)))?;
+    }
+    if connection_pulse.tick() && connections {
+        tx.send(Event::Update(UpdateEvent::Connection(
+            clash.get_connections()?.into(),
+        )))?;
+    }
+    if rules_pulse.tick() {
+        tx.send(Event::Update(UpdateEvent::Rules(clash.get_rules()?)))?;
+    }
+    if proxies_pulse.tick() {
+        let proxies = clash.get_proxies()?;
+        if *loaded {
+            tx.send(Event::Update(UpdateEvent::Proxies(proxies)))?;
+        } else {
+            send_proxies_lazily(tx, proxies)?;
+            *loaded = true;
+        }
+    }
+    if config_pulse.tick() {
+        tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+    }
+    Ok(due)
+}
+
+/// Build groups of `proxies` a few at a time and send them as they're built,
+/// so the tree is shown before all groups are ready. Full proxies are sent
+/// at last to be merged. Groups in a chunk are built on rayon's global pool
+/// with `parallel` feature
+fn send_proxies_lazily(tx: &Sender<Event>, proxies: Proxies) -> TuiResult<()> {
+    let groups = proxies.groups().collect::<Vec<_>>();
+    for chunk in groups.chunks(LAZY_GROUPS) {
+        #[cfg(feature = "parallel")]
+        let built = chunk
+            .par_iter()
+            .map(|(name, group)| ProxyGroup::new(name, group, &proxies))
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let built = chunk
+            .iter()
+            .map(|(name, group)| ProxyGroup::new(name, group, &proxies))
+            .collect();
+        tx.send(Event::Update(UpdateEvent::ProxyGroups(built)))?;
+    }
+    tx.send(Event::Update(UpdateEvent::Proxies(proxies)))?;
+    Ok(())
+}
+
+#[cfg(feature = "traffic")]
+fn traffic_job(tx: Sender<Event>, conn: &Connection) -> TuiResult<()> {
+    if !get_config().tui.shows(Panel::Traffic) {
+        return Ok(());
+    }
+    stream_job(&tx, conn, Stream::Traffic, Clash::get_traffic, |traffic| {
+        Ok(tx.send(Event::Update(UpdateEvent::Traffic(traffic)))?)
+    })
+}
+
+#[cfg(not(feature = "traffic"))]
+fn traffic_job(_tx: Sender<Event>, _conn: &Connection) -> TuiResult<()> {
+    Ok(())
+}
+
+#[cfg(feature = "logs")]
+fn log_job(tx: Sender<Event>, conn: &Connection) -> TuiResult<()> {
+    if !get_config().tui.shows(Panel::Logs) {
+        return Ok(());
+    }
+    let level = get_config().tui.log_level.clone().unwrap_or(Level::Info);
+    let open = move |clash: &Clash| clash.get_log_with_level(&level);
+    stream_job(&tx, conn, Stream::Log, open, |log| {
+        Ok(tx.send(Event::Update(UpdateEvent::Log(log)))?)
+    })
+}
+
+#[cfg(not(feature = "logs"))]
+fn log_job(_tx: Sender<Event>, _conn: &Connection) -> TuiResult<()> {
+    Ok(())
+}
+
+/// Keep `stream` connected and pass its items to `handle`, reconnecting with
+/// backoff. Gives up until server is switched if the stream is unavailable on
+/// server, or keeps failing to connect.
+#[cfg(any(feature = "traffic", feature = "logs"))]
+fn stream_job<T, O, H>(
+    tx: &Sender<Event>,
+    conn: &Connection,
+    stream: Stream,
+    open: O,
+    mut handle: H,
+) -> TuiResult<()>
+where
+    T: DeserializeOwned + Send + 'static,
+    O: Fn(&Clash) -> clashctl_core::Result<LongHaul<T>> + Clone + Send + 'static,
+    H: FnMut(T) -> TuiResult<()>,
+{
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+    let mut failures = 0;
     loop {
-        match traffics.next() {
-            Some(Ok(traffic)) => tx.send(Event::Update(UpdateEvent::Traffic(traffic)))?,
-            Some(Err(e)) => warn!("{:?}", e),
-            None => warn!("No more traffic"),
+        if conn.is_shut_down() {
+            return Ok(());
         }
+        let generation = conn.generation();
+        if !conn.is_available(stream) {
+            tx.send(stream.state_event(StreamState::Unsupported))?;
+            conn.wait_for_switch(generation);
+            continue;
+        }
+
+        let mut connected = false;
+        let switched = forward_stream(conn, stream.name(), open.clone(), |msg| match msg {
+            Some(item) => handle(item),
+            None => {
+                connected = true;
+                backoff.reset();
+                Ok(tx.send(stream.state_event(StreamState::Connected))?)
+            }
+        })?;
+        if switched {
+            backoff.reset();
+            failures = 0;
+            continue;
+        }
+
+        failures = if connected { 0 } else { failures + 1 };
+        if failures >= MAX_STREAM_FAILURES {
+            warn!(
+                "Unable to connect to {} stream after {} attempts, giving up",
+                stream.name(),
+                failures
+            );
+            conn.disable(stream, generation);
+            failures = 0;
+            continue;
+        }
+        warn!("{} stream disconnected", stream.name());
+        tx.send(stream.state_event(StreamState::Reconnecting))?;
+        sleep(backoff.next_backoff());
     }
 }
 
-fn log_job(tx: Sender<Event>, clash: &Clash) -> TuiResult<()> {
+/// Open a stream with `open` and pass its items to `handle`, or `None` once
+/// connected. Returns `true` if it's stopped because server was switched or
+/// shutting down, `false` if the stream ended.
+///
+/// Stream is read in another thread, so switching won't be blocked by an
+/// idle stream. It's stopped and waited for up to [`READER_JOIN_TIMEOUT`]
+/// when returning, see [`Clash::stop_on`].
+#[cfg(any(feature = "traffic", feature = "logs"))]
+fn forward_stream<T, O, H>(
+    conn: &Connection,
+    name: &'static str,
+    open: O,
+    mut handle: H,
+) -> TuiResult<bool>
+where
+    T: DeserializeOwned + Send + 'static,
+    O: FnOnce(&Clash) -> clashctl_core::Result<LongHaul<T>> + Send + 'static,
+    H: FnMut(Option<T>) -> TuiResult<()>,
+{
+    let generation = conn.generation();
+    let stop = Arc::new(AtomicBool::new(false));
+    let clash = Clash::clone(&conn.clash()).stop_on(stop.clone());
+    let (stream_tx, stream_rx) = channel();
+    spawn(move || match open(&clash) {
+        Ok(stream) => {
+            if stream_tx.send(None).is_err() {
+                return;
+            }
+            for item in stream {
+                match item {
+                    Ok(item) => {
+                        if stream_tx.send(Some(item)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("{:?}", e);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => warn!("Unable to connect to {} stream ({})", name, e),
+    });
     loop {
-        let mut logs = clash.get_log()?;
-        match logs.next() {
-            Some(Ok(log)) => tx.send(Event::Update(UpdateEvent::Log(log)))?,
-            Some(Err(e)) => warn!("{:?}", e),
-            None => warn!("No more traffic"),
+        if conn.generation() != generation || conn.is_shut_down() {
+            stop.store(true, Ordering::Release);
+            join_reader(&stream_rx);
+            return Ok(true);
+        }
+        match stream_rx.recv_timeout(STREAM_POLL) {
+            Ok(msg) => handle(msg)?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(false),
+        }
+    }
+}
+
+/// Wait for reader of [`forward_stream`] to quit, which drops its end of `rx`.
+/// Items still coming are dropped
+#[cfg(any(feature = "traffic", feature = "logs"))]
+fn join_reader<T>(rx: &Receiver<T>) {
+    let deadline = Instant::now() + READER_JOIN_TIMEOUT;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(left) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+            Err(RecvTimeoutError::Timeout) => {
+                warn!("Stream reader didn't quit in {:?}", READER_JOIN_TIMEOUT);
+                return;
+            }
         }
     }
 }
@@ -120,45 +626,396 @@ fn action_job(
     flags: &Flags,
     tx: Sender<Event>,
     rx: Receiver<Action>,
-    clash: &Clash,
+    conn: &Connection,
+    paused: &AtomicBool,
 ) -> TuiResult<()> {
-    while let Ok(action) = rx.recv() {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(MAX_DELAY_TESTS)
+        .build()
+        .map_err(|_| TuiError::TuiInternalErr)?;
+
+    loop {
+        let action = match rx.recv_timeout(STREAM_POLL) {
+            Ok(action) => action,
+            Err(RecvTimeoutError::Timeout) if !conn.is_shut_down() => continue,
+            _ => break,
+        };
         tx.send(Event::Action(action.clone()))?;
+        let clash = conn.clash();
         match action {
-            Action::TestLatency { proxies } => {
-                let result = proxies
-                    .par_iter()
-                    .filter_map(|proxy| {
-                        clash
-                            .get_proxy_delay(proxy, flags.test_url.as_str(), flags.timeout)
-                            .err()
-                    })
-                    .collect::<Vec<_>>();
-
-                let count = result.len();
-
-                if count != 0 {
-                    warn!(
-                        "   {}",
-                        result
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect::<Vec<_>>()
-                            .join(" ")
+            Action::SetPaused(value) => paused.store(value, Ordering::Release),
+            Action::TestLatency {
+                proxies,
+                url,
+                timeout,
+            } => {
+                let url = url.as_deref().unwrap_or_else(|| flags.test_url.as_str());
+                let clash = delay_client(flags, &clash, timeout);
+                let delays = test_latency(&pool, timeout, &clash, url, proxies);
+                tx.send(Event::Update(UpdateEvent::ProxyLatency(delays)))?;
+                tx.send(Event::Update(UpdateEvent::ProxyTestLatencyDone {
+                    groups: vec![],
+                }))?;
+            }
+            Action::TestGroupLatency {
+                group,
+                proxies,
+                url,
+                timeout,
+                select_fastest,
+            } => {
+                let url = url.as_deref().unwrap_or_else(|| flags.test_url.as_str());
+                let delay_clash = delay_client(flags, &clash, timeout);
+                let group_delay = conn.has_group_delay();
+                let delays = test_group_latency(
+                    &pool,
+                    timeout,
+                    &delay_clash,
+                    &group,
+                    url,
+                    proxies.clone(),
+                    group_delay,
+                );
+                let fastest = fastest(&proxies, &delays);
+                tx.send(Event::Update(UpdateEvent::ProxyLatency(delays)))?;
+                tx.send(Event::Update(UpdateEvent::ProxyTestLatencyDone {
+                    groups: vec![group.clone()],
+                }))?;
+                if select_fastest {
+                    match fastest {
+                        Some((proxy, delay)) => {
+                            let message = format!("Selected {} in {}, {}ms", proxy, group, delay);
+                            if select(&tx, &clash, group, proxy)? {
+                                tx.send(Event::Update(UpdateEvent::StatusInfo(message)))?;
+                            }
+                        }
+                        None => tx.send(Event::Update(UpdateEvent::StatusError(format!(
+                            "All members of {} failed, selection unchanged",
+                            group
+                        ))))?,
+                    }
+                }
+            }
+            Action::TestAllGroupsLatency {
+                groups,
+                cancel,
+                timeout,
+            } => {
+                let clash = delay_client(flags, &clash, timeout);
+                let total = groups.len();
+                let mut pending = groups.into_iter().enumerate();
+                while let Some((done, (group, proxies))) = pending.next() {
+                    if cancel.load(Ordering::Relaxed) || conn.is_shut_down() {
+                        debug!("Testing all groups canceled after {}/{}", done, total);
+                        let groups = std::iter::once(group)
+                            .chain(pending.map(|(_, (group, _))| group))
+                            .collect();
+                        tx.send(Event::Update(UpdateEvent::ProxyTestLatencyDone { groups }))?;
+                        break;
+                    }
+                    let url = {
+                        let config = get_config();
+                        let default = flags.test_url.as_str();
+                        resolve_test_url(&group, default, &config.test_urls)?.to_owned()
+                    };
+                    let group_delay = conn.has_group_delay();
+                    let delays = test_group_latency(
+                        &pool,
+                        timeout,
+                        &clash,
+                        &group,
+                        &url,
+                        proxies,
+                        group_delay,
                     );
-                    warn!("({}) error(s) during test proxy delay", count);
+                    tx.send(Event::Update(UpdateEvent::ProxyLatency(delays)))?;
+                    tx.send(Event::Update(UpdateEvent::ProxyTestProgress {
+                        done: done + 1,
+                        total,
+                    }))?;
+                    tx.send(Event::Update(UpdateEvent::ProxyTestLatencyDone {
+                        groups: vec![group],
+                    }))?;
                 }
-
-                tx.send(Event::Update(UpdateEvent::ProxyTestLatencyDone))?;
-                tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
             }
             Action::ApplySelection { group, proxy } => {
-                let _ = clash
-                    .set_proxygroup_selected(&group, &proxy)
-                    .map_err(|e| warn!("{:?}", e));
+                select(&tx, &clash, group, proxy)?;
+            }
+            Action::UpdateProviders { group, proxies } => {
+                let providers = match clash.get_providers() {
+                    Ok(providers) => providers
+                        .values()
+                        .filter(|x| x.is_updatable() && proxies.iter().any(|p| x.contains(p)))
+                        .map(|x| x.name.to_owned())
+                        .collect::<Vec<_>>(),
+                    Err(e) => {
+                        warn!("Unable to get providers ({})", e);
+                        vec![]
+                    }
+                };
+                for provider in providers.iter() {
+                    if let Err(e) = clash.update_provider(provider) {
+                        warn!("Failed to update provider {} ({})", provider, e)
+                    }
+                }
+                tx.send(Event::Update(UpdateEvent::ProviderUpdateDone {
+                    group,
+                    providers,
+                }))?;
+                tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
+            }
+            Action::ReloadConfig => {
+                if let Err(e) = clash.reload_configs(true, "") {
+                    warn!("Failed to reload config ({})", e)
+                }
+                tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+                tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
+                tx.send(Event::Update(UpdateEvent::Rules(clash.get_rules()?)))?;
+            }
+            Action::SetMode(mode) => {
+                if let Err(e) = clash.set_mode(mode) {
+                    warn!("Failed to switch mode to {} ({})", mode, e)
+                }
+                tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+            }
+            Action::Reconnect => {
+                let connected = flags
+                    .using_server(&get_config())
+                    .and_then(|server| Ok((flags.connect_server(server.clone())?, server)));
+                let (clash, server) = match connected {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        let error = format!("Unable to reconnect ({})", e);
+                        tx.send(Event::Update(UpdateEvent::ReconnectFailed(error)))?;
+                        continue;
+                    }
+                };
+                let version = match clash.try_get_version() {
+                    Ok(version) => version,
+                    Err(e) => {
+                        let error = format!("Unable to reconnect to {} ({})", server, e);
+                        tx.send(Event::Update(UpdateEvent::ReconnectFailed(error)))?;
+                        continue;
+                    }
+                };
+                // Streams of the old client see generation bumped and reopen
+                conn.replace(clash, version.as_ref());
+                let clash = conn.clash();
+                tx.send(Event::Update(UpdateEvent::Reconnected(server)))?;
+                tx.send(Event::Update(UpdateEvent::Version(version)))?;
+                tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+                tx.send(Event::Update(UpdateEvent::Rules(clash.get_rules()?)))?;
+                tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
+            }
+            Action::UseServer(url) => {
+                let server = get_config().servers.iter().find(|x| x.url == url).cloned();
+                let server = match server {
+                    Some(server) => server,
+                    None => {
+                        warn!("Server {} is not configured", url);
+                        continue;
+                    }
+                };
+                let clash = flags.connect_server(server.clone())?;
+                let version = match clash.try_get_version() {
+                    Ok(version) => version,
+                    Err(e) => {
+                        warn!("Unable to connect to {} ({}), server not switched", server, e);
+                        continue;
+                    }
+                };
+                get_config_mut().use_server(url)?;
+                conn.replace(clash, version.as_ref());
+                let clash = conn.clash();
+                tx.send(Event::Update(UpdateEvent::ServerSwitched(server)))?;
+                tx.send(Event::Update(UpdateEvent::Version(version)))?;
+                tx.send(Event::Update(UpdateEvent::Config(clash.get_configs()?)))?;
+                tx.send(Event::Update(UpdateEvent::Rules(clash.get_rules()?)))?;
                 tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
             }
         }
     }
     Ok(())
 }
+
+/// Run `on_select` hook in background, failures are shown in status line
+fn on_select(tx: &Sender<Event>, command: String) {
+    let tx = tx.clone();
+    spawn(move || {
+        let error = match run_hook(&command).and_then(|mut child| child.wait()) {
+            Ok(status) if status.success() => return,
+            Ok(status) => format!("`on_select` hook failed ({})", status),
+            Err(e) => format!("Unable to run `on_select` hook ({})", e),
+        };
+        warn!("{}", error);
+        let _ = tx.send(Event::Update(UpdateEvent::StatusError(error)));
+    });
+}
+
+/// Client for delay tests of `timeout` ms, see [`Flags::delay_request_timeout`]
+fn delay_client(flags: &Flags, clash: &Clash, timeout: u64) -> Clash {
+    let request_timeout = flags.delay_request_timeout(timeout);
+    clash
+        .clone()
+        .with_timeout(Some(Duration::from_millis(request_timeout)))
+}
+
+/// Select `proxy` of `group`, then refresh proxies. Returns whether it's
+/// selected
+fn select(tx: &Sender<Event>, clash: &Clash, group: String, proxy: String) -> TuiResult<bool> {
+    let selected = match clash.set_proxygroup_selected(&group, &proxy) {
+        Ok(_) => {
+            if let Some(ref template) = get_config().tui.on_select {
+                on_select(tx, fill_hook(template, &group, &proxy));
+            }
+            tx.send(Event::Update(UpdateEvent::ProxySelected { group, proxy }))?;
+            true
+        }
+        Err(e) => {
+            warn!("{:?}", e);
+            false
+        }
+    };
+    tx.send(Event::Update(UpdateEvent::Proxies(clash.get_proxies()?)))?;
+    Ok(selected)
+}
+
+/// The one of `proxies` with the lowest delay, earlier ones win ties. `None`
+/// if all failed, including ones with zero delay as Clash records failures so
+fn fastest(proxies: &[String], delays: &HashMap<String, Option<u64>>) -> Option<(String, u64)> {
+    proxies
+        .iter()
+        .filter_map(|x| Some((x, delays.get(x).copied().flatten()?)))
+        .filter(|(_, delay)| *delay > 0)
+        // First of equal ones is taken
+        .min_by_key(|(_, delay)| *delay)
+        .map(|(proxy, delay)| (proxy.to_owned(), delay))
+}
+
+/// Test delay of proxies in `group` against `url` with group delay API if
+/// server has it, fallback to testing one by one if it fails
+fn test_group_latency(
+    pool: &ThreadPool,
+    timeout: u64,
+    clash: &Clash,
+    group: &str,
+    url: &str,
+    proxies: Vec<String>,
+    group_delay: bool,
+) -> HashMap<String, Option<u64>> {
+    if !group_delay {
+        return test_latency(pool, timeout, clash, url, proxies);
+    }
+    match clash.get_group_delay(group, url, timeout) {
+        Ok(res) => proxies
+            .into_iter()
+            .map(|proxy| {
+                let delay = res.get(&proxy).copied();
+                (proxy, delay)
+            })
+            .collect(),
+        Err(e) => {
+            debug!("Group delay test unavailable ({}), test one by one", e);
+            test_latency(pool, timeout, clash, url, proxies)
+        }
+    }
+}
+
+/// Test delay of proxies against `url` concurrently within `pool`. Failed ones
+/// are `None`
+fn test_latency(
+    pool: &ThreadPool,
+    timeout: u64,
+    clash: &Clash,
+    url: &str,
+    proxies: Vec<String>,
+) -> HashMap<String, Option<u64>> {
+    let result = pool.install(|| {
+        proxies
+            .into_par_iter()
+            .map(|proxy| {
+                let res = clash.get_proxy_delay(&proxy, url, timeout);
+                (proxy, res)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let errors = result
+        .iter()
+        .filter_map(|(_, res)| res.as_ref().err().map(ToString::to_string))
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        warn!("   {}", errors.join(" "));
+        warn!("({}) error(s) during test proxy delay", errors.len());
+    }
+
+    result
+        .into_iter()
+        .map(|(proxy, res)| (proxy, res.ok().map(|x| x.delay)))
+        .collect()
+}
+
+#[test]
+fn test_delay_client() {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread::sleep,
+    };
+
+    use clap::Parser;
+    use clashctl_core::ErrorKind;
+
+    // Answer after 300ms, like Clash waiting on a slow proxy
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            sleep(Duration::from_millis(300));
+            let resp = concat!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\n",
+                r#"{"delay":287}"#
+            );
+            let _ = stream.write_all(resp.as_bytes());
+        }
+    });
+    let flags = Flags::parse_from(["clashctl", "--timeout", "100"]);
+    let clash = Clash::builder(url)
+        .unwrap()
+        .timeout(Some(Duration::from_millis(flags.timeout)))
+        .build()
+        .unwrap();
+    let test = |clash: &Clash| clash.get_proxy_delay("Proxy", "http://x.io", 100);
+
+    // Delay tests wait longer than the general timeout, which is kept for
+    // other requests
+    assert_eq!(test(&delay_client(&flags, &clash, 100)).unwrap().delay, 287);
+    assert!(matches!(
+        test(&clash).unwrap_err().kind(),
+        ErrorKind::Timeout
+    ));
+}
+
+#[test]
+fn test_fastest() {
+    let proxies = ["a", "b", "c"].map(String::from);
+    let delays = |list: [Option<u64>; 3]| {
+        proxies
+            .iter()
+            .cloned()
+            .zip(list)
+            .collect::<HashMap<_, _>>()
+    };
+
+    let fastest_of = |list| fastest(&proxies, &delays(list));
+    assert_eq!(fastest_of([Some(300), Some(100), None]), Some(("b".into(), 100)));
+    // Earlier one wins a tie
+    assert_eq!(fastest_of([None, Some(100), Some(100)]), Some(("b".into(), 100)));
+    assert_eq!(fastest_of([None, None, None]), None);
+    // Zero delay is a failure
+    assert_eq!(fastest_of([Some(0), Some(200), None]), Some(("b".into(), 200)));
+    assert_eq!(fastest_of([Some(0), None, Some(0)]), None);
+}
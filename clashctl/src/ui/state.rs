@@ -1,48 +1,186 @@
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::{HashMap, VecDeque},
+    iter::repeat_n,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use clashctl_core::model::{ConnectionWithSpeed, Log, Rule, Traffic, Version};
+#[cfg(feature = "connections")]
+use clashctl_core::model::ConnectionWithSpeed;
+#[cfg(feature = "logs")]
+use clashctl_core::model::Level;
+use clashctl_core::model::{Mode, Rule, RuleStats, Rules, Traffic, Version};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
 use smart_default::SmartDefault;
 
+#[cfg(feature = "logs")]
+use crate::ui::pages::TimedLog;
 use crate::{
-    interactive::{Noop, RuleSort},
+    interactive::{ColorLevel, KeyAction, Noop, Panel, RuleSort},
     ui::{
-        components::{MovableListManage, MovableListManager, MovableListState, ProxyTree},
-        TuiResult,
+        components::{
+            palette_commands, MovableListManage, MovableListManager, MovableListState,
+            PaletteState, ProxyTree, StatusQueue, TreeUpdate,
+        },
+        utils::{copy_to_clipboard, dashboard_url, open_in_browser, PollHealth},
+        export_path, LogRecorder, TuiResult, DEFAULT_EXPORT_PATH, DEFAULT_LOG_RECORD_PATH,
     },
-    Action, ConfigState, Event, InputEvent, UpdateEvent,
+    get_config, get_config_mut, Action, ConfigState, Event, InputEvent, ListEvent, StreamState,
+    UpdateEvent,
 };
 
-pub(crate) type LogListState<'a> = MovableListState<'a, Log, Noop>;
+#[cfg(feature = "logs")]
+pub(crate) type LogListState<'a> = MovableListState<'a, TimedLog, Noop>;
+#[cfg(feature = "connections")]
 pub(crate) type ConListState<'a> = MovableListState<'a, ConnectionWithSpeed, Noop>;
 pub(crate) type RuleListState<'a> = MovableListState<'a, Rule, RuleSort>;
 pub(crate) type DebugListState<'a> = MovableListState<'a, Event, Noop>;
 
+/// Max number of logs kept in memory if not configured, oldest ones are
+/// dropped first
+#[cfg(feature = "logs")]
+const DEFAULT_MAX_LOGS: usize = 2000;
+
+/// Logs received longer than this ago are dropped
+#[cfg(feature = "logs")]
+const LOG_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Max number of traffic samples kept, one sample per second
+const MAX_TRAFFICS: usize = 256;
+
+/// Traffic samples older than this are considered stale and displayed as zero
+const TRAFFIC_STALE: Duration = Duration::from_secs(3);
+
+/// Latency test timeout is adjusted by this much (in ms) with `+` & `-`,
+/// within `MIN_DELAY_TIMEOUT..=MAX_DELAY_TIMEOUT`
+const DELAY_TIMEOUT_STEP: u64 = 500;
+
+const MIN_DELAY_TIMEOUT: u64 = 500;
+
+const MAX_DELAY_TIMEOUT: u64 = 30_000;
+
+/// Keep latency test timeout `ms` within `MIN_DELAY_TIMEOUT..=MAX_DELAY_TIMEOUT`
+pub fn clamp_delay_timeout(ms: u64) -> u64 {
+    ms.clamp(MIN_DELAY_TIMEOUT, MAX_DELAY_TIMEOUT)
+}
+
+/// Latency test requests within this interval after the last one are ignored
+const TEST_DEBOUNCE: Duration = Duration::from_millis(300);
+
 #[derive(Debug, Clone, SmartDefault)]
 pub struct TuiStates<'a> {
     pub should_quit: bool,
     #[default(_code = "Instant::now()")]
     pub start_time: Instant,
     pub version: Option<Version>,
-    pub traffics: Vec<Traffic>,
+    pub traffics: VecDeque<Traffic>,
     pub max_traffic: Traffic,
+    // Sum of all traffic samples received, used for averages
+    pub total_traffic: Traffic,
+    pub last_traffic_time: Option<Instant>,
+    pub poll_health: PollHealth,
+    pub traffic_stream: StreamState,
     pub all_events_recv: usize,
     pub page_index: u8,
+    // Titles of pages shown, `TITLES` without ones of hidden panels
+    #[default(_code = "TuiStates::shown_titles()")]
+    pub titles: Vec<&'static str>,
     pub show_debug: bool,
+    // First key of a chord waiting for the next key
+    pub pending_chord: Option<char>,
+    // Whether polling the server is paused
+    pub paused: bool,
+    // Whether waiting for reconnecting to finish
+    pub reconnecting: bool,
+    // Whether actions changing the server are dropped, see
+    // [`Action::is_mutating`]
+    pub read_only: bool,
+    // Messages shown in status line
+    pub status: StatusQueue,
+    #[default(_code = "new_proxy_tree()")]
     pub proxy_tree: ProxyTree<'a>,
     pub rule_freq: HashMap<String, usize>,
     // (upload_size, download_size)
+    #[cfg(feature = "connections")]
     pub con_size: (u64, u64),
 
+    #[cfg(feature = "logs")]
     #[default(_code = "{
+        let max_logs = get_config().tui.max_logs.filter(|x| *x > 0);
         let mut ret = MovableListState::default();
-        ret.with_index().dsc_index();
+        ret.with_index()
+            .dsc_index()
+            .wrap_lines()
+            .set_capacity(Some(max_logs.unwrap_or(DEFAULT_MAX_LOGS)));
         ret
     }")]
     pub log_state: LogListState<'a>,
+    // Payload substring to filter logs with, if any
+    #[cfg(feature = "logs")]
+    pub log_filter: Option<String>,
+    // File logs are appended to, if recording
+    pub log_record: Option<LogRecorder>,
+    // Whether keys are being typed into `log_filter`
+    #[cfg(feature = "logs")]
+    pub log_searching: bool,
+    pub log_stream: StreamState,
+    // Minimum level of logs to be displayed
+    #[cfg(feature = "logs")]
+    #[default(_code = "Level::Debug")]
+    pub log_level: Level,
+    #[cfg(feature = "connections")]
     pub con_state: ConListState<'a>,
     pub rule_state: RuleListState<'a>,
+    // Payload substring to filter rules with, if any
+    pub rule_filter: Option<String>,
+    // Whether keys are being typed into `rule_filter`
+    pub rule_searching: bool,
+    // Rules in the order Clash matches them, `rule_state` may be sorted
+    pub rules: Rules,
+    // Counts of `rules` by type, shown in header of rules page
+    pub rule_stats: RuleStats,
+    // Host to find the matching rule of, see [`Rules::match_host`]
+    pub rule_host: Option<String>,
+    // Whether keys are being typed into `rule_host`
+    pub rule_host_typing: bool,
     pub debug_state: DebugListState<'a>,
     pub config_state: ConfigState,
+    // Set to cancel testing all groups
+    pub sweep_cancel: Option<Arc<AtomicBool>>,
+    // When the last latency test was requested, for debouncing
+    pub last_test_time: Option<Instant>,
+    // Index of server highlighted in server picker, `None` if it's closed
+    pub server_picker: Option<usize>,
+    // Whether latency bands of current group are shown in a popup
+    pub show_group_stats: bool,
+    // Whether current member of each group is shown in a popup
+    pub show_selections: bool,
+    // Timeout of latency tests in ms, adjusted with `+` & `-`
+    #[default(2000)]
+    pub delay_timeout: u64,
+    // Command palette, `None` if it's closed
+    pub palette: Option<PaletteState>,
+    // Size of terminal from the latest resize event, (columns, rows)
+    pub terminal_size: Option<(u16, u16)>,
+}
+
+fn new_proxy_tree<'a>() -> ProxyTree<'a> {
+    let tui = &get_config().tui;
+    ProxyTree::with_thresholds(tui.latency)
+        .with_theme(
+            tui.theme
+                .theme()
+                .downgrade(tui.color_level.unwrap_or_else(ColorLevel::detect)),
+        )
+        .with_compact(tui.compact)
+        .with_single_line(tui.single_line_groups)
+        .with_pinned(tui.pinned_groups.iter().cloned())
+        .with_aliases(tui.aliases.clone())
+        .with_global_group(tui.global_group)
+        .with_wrap_navigation(tui.wrap_navigation)
 }
 
 // TODO fix: drop_events not working
@@ -52,42 +190,395 @@ impl<'a> TuiStates<'a> {
     ];
 
     pub fn handle(&mut self, event: Event) -> TuiResult<Option<Action>> {
+        let event = match event {
+            Event::Key(key) if !self.is_typing() => match self.pending_chord.take() {
+                Some(prefix) => Event::from_chord(prefix, key),
+                None => match get_config().tui.keymap.action_of(&key) {
+                    Some(action) => Event::from_action(action, key),
+                    None => Event::from(key),
+                },
+            },
+            event => event,
+        };
+
         self.all_events_recv += 1;
         if self.debug_state.len() >= 300 {
             let _ = self.drop_events(100);
         }
         self.debug_state.push(event.to_owned());
 
-        match event {
+        let action = match event {
             Event::Quit => {
                 self.should_quit = true;
+                None
+            }
+            Event::Key(key) => self.handle_typing(key)?,
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            Event::Resize(columns, rows) => {
+                self.terminal_size = Some((columns, rows));
+                self.proxy_tree.resized();
+                None
+            }
+            Event::Input(event) => self.handle_input(event)?,
+            Event::Update(update) => self.handle_update(update)?,
+            _ => None,
+        };
+        match action {
+            // Keys stay bound, so tell why nothing happens
+            Some(action) if self.read_only && action.is_mutating() => {
+                self.status.info("Read-only mode, changes to the server are disabled");
                 Ok(None)
             }
-            Event::Input(event) => self.handle_input(event),
-            Event::Update(update) => self.handle_update(update),
-            _ => Ok(None),
+            action => Ok(action),
         }
     }
 
+    fn handle_mouse(&mut self, event: MouseEvent) -> Option<Action> {
+        if self.title() == "Proxies" && !self.is_typing() {
+            return self.proxy_tree.handle_mouse(event);
+        }
+        let code = match event.kind {
+            MouseEventKind::ScrollUp => KeyCode::Up,
+            MouseEventKind::ScrollDown => KeyCode::Down,
+            _ => return None,
+        };
+        self.active_list()?.handle(ListEvent { fast: false, code })
+    }
+
+    /// Whether a latency test was requested too recently. Otherwise this
+    /// request is recorded for the next check
+    fn test_debounced(&mut self) -> bool {
+        match self.last_test_time {
+            Some(time) if time.elapsed() < TEST_DEBOUNCE => true,
+            _ => {
+                self.last_test_time = Some(Instant::now());
+                false
+            }
+        }
+    }
+
+    /// Seconds passed since the last traffic sample, if it is stale
+    fn traffic_gap(&self) -> usize {
+        match self.last_traffic_time {
+            Some(time) if time.elapsed() >= TRAFFIC_STALE => time.elapsed().as_secs() as usize,
+            _ => 0,
+        }
+    }
+
+    /// Latest traffic sample, or zero if no sample arrived recently
+    pub fn current_traffic(&self) -> Traffic {
+        match self.traffic_gap() {
+            0 => self.traffics.back().copied().unwrap_or_default(),
+            _ => Traffic::default(),
+        }
+    }
+
+    /// Traffic samples from newest to oldest, with one zero sample filled in
+    /// for each second passed since the last sample went stale
+    pub fn recent_traffics(&self) -> impl Iterator<Item = Traffic> + '_ {
+        repeat_n(Traffic::default(), self.traffic_gap()).chain(self.traffics.iter().rev().copied())
+    }
+
     #[inline]
     pub fn page_len(&mut self) -> usize {
         if self.show_debug {
-            Self::TITLES.len()
+            self.titles.len()
         } else {
-            Self::TITLES.len() - 1
+            self.titles.len() - 1
         }
     }
 
     #[inline]
     pub fn title(&self) -> &str {
-        Self::TITLES[self.page_index as usize]
+        self.titles[self.page_index as usize]
+    }
+
+    /// [`Self::TITLES`] without pages of panels hidden in config or compiled
+    /// out, see
+    /// [`TuiConfig::shows`](crate::interactive::TuiConfig::shows)
+    pub fn shown_titles() -> Vec<&'static str> {
+        let tui = &get_config().tui;
+        Self::TITLES
+            .iter()
+            .copied()
+            .filter(|x| match *x {
+                "Conns" => tui.shows(Panel::Connections),
+                "Logs" => tui.shows(Panel::Logs),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Whether key events should be consumed as text input instead of
+    /// keybindings
+    #[inline]
+    pub fn is_typing(&self) -> bool {
+        self.server_picker.is_some()
+            || self.palette.is_some()
+            || self.show_group_stats
+            || self.show_selections
+            || match self.title() {
+                "Proxies" => self.proxy_tree.is_searching() || self.proxy_tree.is_typing_alias(),
+                "Rules" => self.rule_searching || self.rule_host_typing,
+                #[cfg(feature = "logs")]
+                "Logs" => self.log_searching,
+                _ => false,
+            }
+    }
+
+    fn handle_typing(&mut self, key: KeyEvent) -> TuiResult<Option<Action>> {
+        if let (KeyModifiers::CONTROL, KeyCode::Char('c')) = (key.modifiers, key.code) {
+            self.should_quit = true;
+            return Ok(None);
+        }
+        if self.server_picker.is_some() {
+            return Ok(self.handle_server_picker(key));
+        }
+        if self.palette.is_some() {
+            return self.handle_palette(key);
+        }
+        if self.show_group_stats || self.show_selections {
+            // Any key closes them
+            self.show_group_stats = false;
+            self.show_selections = false;
+            return Ok(None);
+        }
+        if self.title() == "Rules" && self.rule_host_typing {
+            self.handle_rule_host(key);
+            return Ok(None);
+        }
+        if self.title() == "Rules" {
+            return Ok(self.handle_rule_search(key));
+        }
+        #[cfg(feature = "logs")]
+        if self.title() == "Logs" {
+            return Ok(self.handle_log_search(key));
+        }
+        if self.proxy_tree.is_typing_alias() {
+            if let Some((name, alias)) = self.proxy_tree.handle_alias(key) {
+                self.save_alias(name, alias);
+            }
+            return Ok(None);
+        }
+        Ok(self.proxy_tree.handle_search(key))
+    }
+
+    fn save_alias(&mut self, name: String, alias: Option<String>) {
+        let mut config = get_config_mut();
+        let message = match alias {
+            Some(ref alias) => format!("Aliased {} as {}", name, alias),
+            None => format!("Cleared alias of {}", name),
+        };
+        match alias {
+            Some(alias) => config.tui.aliases.insert(name, alias),
+            None => config.tui.aliases.remove(&name),
+        };
+        match config.write() {
+            Ok(_) => self.status.info(message),
+            Err(e) => self.status.error(format!("Failed to save alias: {}", e)),
+        };
+    }
+
+    fn handle_rule_search(&mut self, key: KeyEvent) -> Option<Action> {
+        let filter = self.rule_filter.get_or_insert_with(String::new);
+        match key.code {
+            KeyCode::Char(c) => filter.push(c),
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Enter => {
+                self.rule_searching = false;
+                if filter.is_empty() {
+                    self.rule_filter = None
+                }
+            }
+            KeyCode::Esc => {
+                self.rule_searching = false;
+                self.rule_filter = None;
+            }
+            code @ (KeyCode::Up | KeyCode::Down) => {
+                return self.rule_state.handle(ListEvent {
+                    fast: matches!(key.modifiers, KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+                    code,
+                })
+            }
+            _ => {}
+        }
+        None
+    }
+
+    #[cfg(feature = "logs")]
+    fn handle_log_search(&mut self, key: KeyEvent) -> Option<Action> {
+        let filter = self.log_filter.get_or_insert_with(String::new);
+        match key.code {
+            KeyCode::Char(c) => filter.push(c),
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Enter => {
+                self.log_searching = false;
+                if filter.is_empty() {
+                    self.log_filter = None
+                }
+            }
+            KeyCode::Esc => {
+                self.log_searching = false;
+                self.log_filter = None;
+            }
+            code @ (KeyCode::Up | KeyCode::Down) => {
+                return self.log_state.handle(ListEvent {
+                    fast: matches!(key.modifiers, KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+                    code,
+                })
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_rule_host(&mut self, key: KeyEvent) {
+        let host = self.rule_host.get_or_insert_with(String::new);
+        match key.code {
+            KeyCode::Char(c) => host.push(c),
+            KeyCode::Backspace => {
+                host.pop();
+            }
+            KeyCode::Enter => {
+                self.rule_host_typing = false;
+                if host.is_empty() {
+                    self.rule_host = None
+                }
+            }
+            KeyCode::Esc => {
+                self.rule_host_typing = false;
+                self.rule_host = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_palette(&mut self, key: KeyEvent) -> TuiResult<Option<Action>> {
+        let commands = palette_commands(&get_config().tui.keymap);
+        let palette = self.palette.get_or_insert_with(Default::default);
+        // Keys of opening it close it too, unless they can be typed
+        let toggled = key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            && get_config().tui.keymap.action_of(&key) == Some(KeyAction::OpenPalette);
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => self.palette = None,
+            _ if toggled => self.palette = None,
+            (_, KeyCode::Enter) => {
+                let picked = palette.picked(&commands);
+                self.palette = None;
+                if let Some(event) = picked {
+                    return self.handle(event);
+                }
+            }
+            _ => {
+                let num_matched = palette.filter(&commands).len();
+                palette.handle(key, num_matched);
+            }
+        }
+        Ok(None)
+    }
+
+    fn handle_server_picker(&mut self, key: KeyEvent) -> Option<Action> {
+        let servers = self.config_state.servers();
+        let index = self.server_picker.get_or_insert(0);
+        match (get_config().tui.keymap.action_of(&key), key.code) {
+            (Some(KeyAction::MoveUp), _) => *index = index.saturating_sub(1),
+            (Some(KeyAction::MoveDown), _) => {
+                *index = (*index + 1).min(servers.len().saturating_sub(1))
+            }
+            (Some(KeyAction::Select), _) => {
+                let picked = servers.get(*index).map(|x| x.url.clone());
+                let using = self.config_state.server().map(|x| &x.url);
+                self.server_picker = None;
+                return picked.filter(|x| Some(x) != using).map(Action::UseServer);
+            }
+            (Some(KeyAction::Quit | KeyAction::PickServer), _) | (_, KeyCode::Esc) => {
+                self.server_picker = None
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Apply updates of proxies queued since last frame, so they're drawn
+    /// together instead of one frame each
+    pub fn flush_updates(&mut self) -> bool {
+        self.proxy_tree.flush(&self.rule_freq)
+    }
+
+    /// Flush logs recorded if it's been a while, so they show up in file
+    /// even when no more logs come
+    pub fn flush_log_record(&mut self) {
+        if let Some(Err(e)) = self.log_record.as_ref().map(LogRecorder::flush_if_due) {
+            self.log_record = None;
+            self.status.error(format!("Failed to record logs: {}", e));
+        }
+    }
+
+    fn toggle_log_record(&mut self) {
+        if let Some(recorder) = self.log_record.take() {
+            match recorder.flush() {
+                Ok(_) => self.status.info(format!(
+                    "Stopped recording logs to {}",
+                    recorder.path().display()
+                )),
+                Err(e) => self.status.error(format!("Failed to record logs: {}", e)),
+            };
+            return;
+        }
+        let path = get_config()
+            .tui
+            .log_record_path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_LOG_RECORD_PATH.into());
+        match LogRecorder::open(path) {
+            Ok(recorder) => {
+                self.status
+                    .info(format!("Recording logs to {}", recorder.path().display()));
+                self.log_record = Some(recorder);
+            }
+            Err(e) => {
+                self.status.error(format!("Failed to record logs: {}", e));
+            }
+        }
+    }
+
+    /// Drop states of the previous server
+    fn reset_server(&mut self) {
+        self.version = None;
+        self.traffics.clear();
+        self.max_traffic = Traffic::default();
+        self.total_traffic = Traffic::default();
+        self.last_traffic_time = None;
+        self.proxy_tree = new_proxy_tree();
+        self.rule_freq.clear();
+        #[cfg(feature = "connections")]
+        {
+            self.con_size = (0, 0);
+            self.con_state.sorted_merge(vec![]);
+        }
+        #[cfg(feature = "logs")]
+        self.log_state.clear();
+        self.rule_state.sorted_merge(vec![]);
+        self.rules = Rules::default();
+        self.rule_stats = RuleStats::default();
+        self.config_state = ConfigState::default();
+        self.poll_health.reset();
+        if let Some(cancel) = self.sweep_cancel.take() {
+            cancel.store(true, atomic::Ordering::Relaxed);
+        }
     }
 
     fn active_list<'own>(&'own mut self) -> Option<MovableListManager<'a, 'own>> {
         match self.title() {
             "Rules" => Some(MovableListManager::Rule(&mut self.rule_state)),
             "Debug" => Some(MovableListManager::Event(&mut self.debug_state)),
+            #[cfg(feature = "logs")]
             "Logs" => Some(MovableListManager::Log(&mut self.log_state)),
+            #[cfg(feature = "connections")]
             "Conns" => Some(MovableListManager::Connection(&mut self.con_state)),
             "Proxies" => Some(MovableListManager::Proxy(&mut self.proxy_tree)),
             _ => None,
@@ -95,32 +586,109 @@ impl<'a> TuiStates<'a> {
     }
 
     fn handle_update(&mut self, update: UpdateEvent) -> TuiResult<Option<Action>> {
+        #[cfg(feature = "logs")]
+        let expired = self
+            .log_state
+            .iter()
+            .take_while(|x| x.received.elapsed() > LOG_RETENTION)
+            .count();
+        #[cfg(feature = "logs")]
+        self.log_state.drop_oldest(expired);
+
         match update {
             UpdateEvent::Config(config) => self.config_state.update_clash(config),
+            #[cfg(feature = "connections")]
             UpdateEvent::Connection(connection) => {
                 self.con_size = (connection.upload_total, connection.download_total);
                 self.con_state.sorted_merge(connection.connections);
                 self.con_state.with_index();
             }
-            UpdateEvent::Version(version) => self.version = Some(version),
+            // Not polled without the page
+            #[cfg(not(feature = "connections"))]
+            UpdateEvent::Connection(_) => {}
+            UpdateEvent::Version(version) => self.version = version,
             UpdateEvent::Traffic(traffic) => {
                 let Traffic { up, down } = traffic;
                 self.max_traffic.up = self.max_traffic.up.max(up);
                 self.max_traffic.down = self.max_traffic.down.max(down);
-                self.traffics.push(traffic)
+                self.total_traffic.up += up;
+                self.total_traffic.down += down;
+                self.last_traffic_time = Some(Instant::now());
+                if self.traffics.len() >= MAX_TRAFFICS {
+                    self.traffics.pop_front();
+                }
+                self.traffics.push_back(traffic)
             }
             UpdateEvent::Proxies(proxies) => {
-                let mut new_tree = Into::<ProxyTree>::into(proxies);
-                new_tree.sort_groups_with_frequency(&self.rule_freq);
-                self.proxy_tree.replace_with(new_tree);
+                self.proxy_tree.queue(TreeUpdate::Proxies(proxies));
+            }
+            UpdateEvent::ProxyGroups(groups) => {
+                self.proxy_tree.queue(TreeUpdate::Groups(groups));
+            }
+            UpdateEvent::Log(log) => {
+                if let Some(Err(e)) = self.log_record.as_ref().map(|x| x.record(&log)) {
+                    // Stop recording so it's reported once, the stream goes on
+                    self.log_record = None;
+                    self.status.error(format!("Failed to record logs: {}", e));
+                }
+                #[cfg(feature = "logs")]
+                {
+                    let style = self.proxy_tree.theme().log_style(&log.log_type);
+                    self.log_state.push(TimedLog::new(log, style));
+                }
             }
-            UpdateEvent::Log(log) => self.log_state.push(log),
+            UpdateEvent::LogStream(stream) => self.log_stream = stream,
+            UpdateEvent::TrafficStream(stream) => self.traffic_stream = stream,
             UpdateEvent::Rules(rules) => {
                 self.rule_freq = rules.owned_frequency();
-                self.rule_state.sorted_merge(rules.rules);
+                self.proxy_tree.sort_groups_with_frequency(&self.rule_freq);
+                self.rule_state.sorted_merge(rules.rules.clone());
+                self.rule_stats = rules.stats();
+                self.rules = rules;
+            }
+            UpdateEvent::ProxyLatency(delays) => {
+                self.proxy_tree.queue(TreeUpdate::Delays(delays));
+            }
+            UpdateEvent::ProxySelected { group, proxy } => {
+                self.proxy_tree.queue(TreeUpdate::Selected { group, proxy });
+            }
+            UpdateEvent::ProxyTestProgress { done, total } => {
+                self.proxy_tree.sweep_progress(done, total);
+            }
+            UpdateEvent::ProxyTestLatencyDone { groups } => {
+                self.proxy_tree.end_testing(&groups);
+                if !self.proxy_tree.is_testing() {
+                    self.sweep_cancel = None;
+                    // Tell why some may have failed
+                    self.status.info(format!(
+                        "Latency test done, proxies slower than {}ms are marked failed",
+                        self.delay_timeout
+                    ));
+                }
             }
-            UpdateEvent::ProxyTestLatencyDone => {
-                self.proxy_tree.end_testing();
+            UpdateEvent::ProviderUpdateDone { group, providers } => {
+                self.proxy_tree.end_updating(&group, &providers);
+            }
+            UpdateEvent::ServerSwitched(_) => self.reset_server(),
+            UpdateEvent::Reconnected(server) => {
+                // Same server, so stay where we were once proxies are back
+                let snapshot = self.proxy_tree.snapshot();
+                self.reset_server();
+                self.proxy_tree.restore_later(snapshot);
+                self.reconnecting = false;
+                self.status.info(format!("Reconnected to {}", server));
+            }
+            UpdateEvent::ReconnectFailed(error) => {
+                self.reconnecting = false;
+                self.status.error(error);
+            }
+            UpdateEvent::PollOk(elapsed) => self.poll_health.succeed(Instant::now(), elapsed),
+            UpdateEvent::PollFailed(error) => self.poll_health.fail(error),
+            UpdateEvent::StatusError(error) => {
+                self.status.error(error);
+            }
+            UpdateEvent::StatusInfo(message) => {
+                self.status.info(message);
             }
         }
         Ok(None)
@@ -136,12 +704,30 @@ impl<'a> TuiStates<'a> {
             InputEvent::ToggleDebug => {
                 self.show_debug = !self.show_debug;
                 // On the debug page
-                if self.page_index == Self::TITLES.len() as u8 - 1 {
+                if self.page_index == self.debug_page_index() {
                     self.page_index -= 1;
                 } else if self.show_debug {
                     self.page_index = self.debug_page_index()
                 }
             }
+            InputEvent::Esc if self.status.has_error() => {
+                self.status.dismiss();
+            }
+            InputEvent::Esc if self.title() == "Proxies" && self.sweep_cancel.is_some() => {
+                if let Some(cancel) = self.sweep_cancel.take() {
+                    cancel.store(true, atomic::Ordering::Relaxed);
+                }
+            }
+            InputEvent::Esc if self.title() == "Rules" && self.rule_host.is_some() => {
+                self.rule_host = None;
+            }
+            InputEvent::Esc if self.title() == "Rules" && self.rule_filter.is_some() => {
+                self.rule_filter = None;
+            }
+            #[cfg(feature = "logs")]
+            InputEvent::Esc if self.title() == "Logs" && self.log_filter.is_some() => {
+                self.log_filter = None;
+            }
             InputEvent::Esc => {
                 if let Some(mut list) = self.active_list() {
                     list.end();
@@ -157,17 +743,356 @@ impl<'a> TuiStates<'a> {
                     return Ok(list.handle(list_event));
                 }
             }
-            InputEvent::TestLatency => {
-                if self.title() == "Proxies" && !self.proxy_tree.is_testing() {
-                    self.proxy_tree.start_testing();
+            InputEvent::TestLatency | InputEvent::TestAndSelectFastest => {
+                let idle = match self.proxy_tree.get_current_group() {
+                    Some(group) => !self.proxy_tree.is_testing_group(group.name()),
+                    None => false,
+                };
+                if self.title() == "Proxies" && idle && !self.test_debounced() {
+                    let select_fastest = event == InputEvent::TestAndSelectFastest;
                     let group = self.proxy_tree.current_group();
+                    if select_fastest && !group.proxy_type().is_selector() {
+                        let hint = format!("{} is not selectable", group.proxy_type());
+                        self.status.info(hint);
+                        return Ok(None);
+                    }
                     let proxies = group
                         .members()
                         .iter()
                         .filter(|x| x.proxy_type().is_normal())
                         .map(|x| x.name().into())
                         .collect();
-                    return Ok(Some(Action::TestLatency { proxies }));
+                    let name = group.name().to_owned();
+                    let url = get_config().test_urls.get(&name).cloned();
+                    let timeout = self.delay_timeout;
+                    let action = Action::TestGroupLatency {
+                        group: name.clone(),
+                        proxies,
+                        url,
+                        timeout,
+                        select_fastest,
+                    };
+                    // Dropped with the read-only hint, don't wait for it
+                    if self.read_only && action.is_mutating() {
+                        return Ok(Some(action));
+                    }
+                    self.proxy_tree.start_testing(&name);
+                    let then = if select_fastest { " to select the fastest" } else { "" };
+                    self.status
+                        .info(format!("Testing {}{}, timeout {}ms", name, then, timeout));
+                    return Ok(Some(action));
+                }
+            }
+            // Not debounced, a single proxy is cheap to test again
+            InputEvent::TestMemberLatency => {
+                if self.title() == "Proxies" {
+                    if let Some(name) = self.proxy_tree.start_testing_member() {
+                        let group = self.proxy_tree.current_group().name();
+                        let url = get_config().test_urls.get(group).cloned();
+                        let timeout = self.delay_timeout;
+                        self.status
+                            .info(format!("Testing {}, timeout {}ms", name, timeout));
+                        return Ok(Some(Action::TestLatency {
+                            proxies: vec![name],
+                            url,
+                            timeout,
+                        }));
+                    }
+                }
+            }
+            InputEvent::TestAllLatency => {
+                if self.title() == "Proxies"
+                    && !self.proxy_tree.is_testing()
+                    && !self.test_debounced()
+                {
+                    let groups = self.proxy_tree.testable_groups();
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    self.proxy_tree.start_sweep(&groups);
+                    self.sweep_cancel = Some(cancel.clone());
+                    let timeout = self.delay_timeout;
+                    self.status
+                        .info(format!("Testing all groups, timeout {}ms", timeout));
+                    return Ok(Some(Action::TestAllGroupsLatency {
+                        groups,
+                        cancel,
+                        timeout,
+                    }));
+                }
+            }
+            InputEvent::Search => match self.title() {
+                "Proxies" => {
+                    self.proxy_tree.start_search();
+                }
+                "Rules" => {
+                    self.rule_filter.get_or_insert_with(String::new);
+                    self.rule_searching = true;
+                }
+                #[cfg(feature = "logs")]
+                "Logs" => {
+                    self.log_filter.get_or_insert_with(String::new);
+                    self.log_searching = true;
+                }
+                _ => {}
+            },
+            InputEvent::MatchRule => {
+                if self.title() == "Rules" {
+                    self.rule_host = Some(String::new());
+                    self.rule_host_typing = true;
+                    // Only the matching rule is listed, drop where all rules
+                    // were scrolled to
+                    self.rule_state.end();
+                }
+            }
+            InputEvent::GlobalSearch => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.start_global_search();
+                }
+            }
+            InputEvent::NextMatch => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.next_match();
+                }
+            }
+            InputEvent::ToggleDelaySort => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.toggle_delay_sort();
+                }
+            }
+            #[cfg(feature = "logs")]
+            InputEvent::NextLogLevel => {
+                if self.title() == "Logs" {
+                    self.log_level = match self.log_level {
+                        Level::Debug => Level::Info,
+                        Level::Info => Level::Warning,
+                        Level::Warning => Level::Error,
+                        Level::Error => Level::Debug,
+                    }
+                }
+            }
+            #[cfg(not(feature = "logs"))]
+            InputEvent::NextLogLevel => {}
+            InputEvent::Chord(prefix) => {
+                if self.title() == "Proxies" {
+                    self.pending_chord = Some(prefix);
+                }
+            }
+            InputEvent::JumpToCurrent => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.jump_to_current();
+                }
+            }
+            InputEvent::UpdateProvider => {
+                if self.title() == "Proxies" && !self.proxy_tree.is_updating() {
+                    // Action will be dropped, don't wait for it
+                    if !self.read_only {
+                        self.proxy_tree.start_updating();
+                    }
+                    let group = self.proxy_tree.current_group();
+                    let proxies = group.members().iter().map(|x| x.name().into()).collect();
+                    return Ok(Some(Action::UpdateProviders {
+                        group: group.name().to_owned(),
+                        proxies,
+                    }));
+                }
+            }
+            InputEvent::ToggleHideDead => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.toggle_hide_dead();
+                }
+            }
+            InputEvent::NextGroupFilter => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.next_group_filter();
+                }
+            }
+            InputEvent::EditAlias => {
+                if self.title() == "Proxies" && !self.proxy_tree.start_alias() {
+                    self.status.info("Point to a proxy to alias it");
+                }
+            }
+            InputEvent::NextGroupSort => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.next_group_sort();
+                    let sort = self.proxy_tree.group_sort();
+                    self.status.info(format!("Groups sorted by {}", sort.label()));
+                }
+            }
+            InputEvent::ToggleCompact => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.toggle_compact();
+                }
+            }
+            InputEvent::ToggleSingleLine => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.toggle_single_line();
+                }
+            }
+            InputEvent::CopyName => {
+                if self.title() == "Proxies" {
+                    if let Some(name) = self.proxy_tree.focused_name().map(ToOwned::to_owned) {
+                        match copy_to_clipboard(&name) {
+                            Ok(_) => self.status.info(format!("Copied {}", name)),
+                            Err(e) => self.status.error(format!("Failed to copy: {}", e)),
+                        };
+                    }
+                }
+            }
+            InputEvent::TogglePin => {
+                if self.title() == "Proxies" {
+                    if let Some((group, pinned)) = self.proxy_tree.toggle_pin() {
+                        self.proxy_tree.sort_groups_with_frequency(&self.rule_freq);
+                        let mut config = get_config_mut();
+                        let pins = &mut config.tui.pinned_groups;
+                        pins.retain(|x| x != &group);
+                        if pinned {
+                            pins.push(group);
+                        }
+                        if let Err(e) = config.write() {
+                            self.status.error(format!("Failed to save pinned groups: {}", e));
+                        }
+                    }
+                }
+            }
+            InputEvent::ShowGroupStats => {
+                if self.title() == "Proxies" && self.proxy_tree.get_current_group().is_some() {
+                    self.show_group_stats = true;
+                }
+            }
+            InputEvent::ShowSelections => {
+                if self.title() == "Proxies" && !self.proxy_tree.is_loading() {
+                    self.show_selections = true;
+                }
+            }
+            InputEvent::ToggleLogRecord => self.toggle_log_record(),
+            InputEvent::ExportProxies => {
+                if self.title() == "Proxies" && !self.proxy_tree.is_loading() {
+                    let export = self.proxy_tree.to_export();
+                    let base = get_config()
+                        .tui
+                        .export_path
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_EXPORT_PATH.into());
+                    let path = export_path(&base, export.time);
+                    match export.write(&path) {
+                        Ok(_) => self.status.info(format!("Exported to {}", path.display())),
+                        Err(e) => self.status.error(format!("Failed to export proxies: {}", e)),
+                    };
+                }
+            }
+            InputEvent::FlipGlobalDirect => {
+                if self.title() == "Proxies" {
+                    match self.proxy_tree.flip_global_direct() {
+                        Ok(proxy) => {
+                            return Ok(Some(Action::ApplySelection {
+                                group: "GLOBAL".to_owned(),
+                                proxy,
+                            }))
+                        }
+                        Err(hint) => {
+                            self.status.info(hint);
+                        }
+                    }
+                }
+            }
+            InputEvent::NextSelection | InputEvent::PrevSelection => {
+                if self.title() == "Proxies" {
+                    let back = event == InputEvent::PrevSelection;
+                    match self.proxy_tree.cycle_selection(back) {
+                        Ok((group, proxy)) => {
+                            if !self.read_only {
+                                self.status.info(format!("{} → {}", group, proxy));
+                            }
+                            return Ok(Some(Action::ApplySelection { group, proxy }));
+                        }
+                        Err(hint) => {
+                            self.status.info(hint);
+                        }
+                    }
+                }
+            }
+            InputEvent::OpenDashboard => {
+                if self.title() == "Proxies" {
+                    let dashboard = get_config().tui.dashboard.clone();
+                    match (dashboard, self.proxy_tree.focused()) {
+                        (None, _) => {
+                            self.status.info("Set `dashboard` under `tui` in config first");
+                        }
+                        (Some(_), None) => {}
+                        (Some(base), Some((group, proxy))) => {
+                            let url = dashboard_url(&base, group, proxy);
+                            match open_in_browser(&url) {
+                                Ok(_) => self.status.info(format!("Opened {}", url)),
+                                Err(e) => {
+                                    self.status.error(format!("Failed to open dashboard: {}", e))
+                                }
+                            };
+                        }
+                    }
+                }
+            }
+            InputEvent::ToggleFold => {
+                self.proxy_tree.toggle_fold();
+            }
+            InputEvent::CollapseAll => {
+                self.proxy_tree.collapse_all();
+            }
+            InputEvent::ExpandAll => {
+                self.proxy_tree.expand_all();
+            }
+            InputEvent::ReloadConfig => {
+                if self.title() == "Configs" {
+                    return Ok(Some(Action::ReloadConfig));
+                }
+            }
+            InputEvent::NextMode => {
+                if self.title() == "Configs" {
+                    if let Some(config) = self.config_state.clash() {
+                        let mode = match config.mode {
+                            Mode::Rule => Mode::Global,
+                            Mode::Global => Mode::Direct,
+                            Mode::Direct => Mode::Rule,
+                        };
+                        return Ok(Some(Action::SetMode(mode)));
+                    }
+                }
+            }
+            InputEvent::Reconnect => {
+                if self.reconnecting {
+                    self.status.info("Already reconnecting");
+                } else {
+                    self.reconnecting = true;
+                    self.status.info("Reconnecting…");
+                    return Ok(Some(Action::Reconnect));
+                }
+            }
+            InputEvent::TogglePause => {
+                self.paused = !self.paused;
+                return Ok(Some(Action::SetPaused(self.paused)));
+            }
+            InputEvent::OlderSample | InputEvent::NewerSample => {
+                if self.title() == "Proxies" {
+                    self.proxy_tree.step_history(event == InputEvent::OlderSample);
+                }
+            }
+            InputEvent::IncreaseDelayTimeout | InputEvent::DecreaseDelayTimeout => {
+                if self.title() == "Proxies" {
+                    let timeout = if event == InputEvent::IncreaseDelayTimeout {
+                        self.delay_timeout.saturating_add(DELAY_TIMEOUT_STEP)
+                    } else {
+                        self.delay_timeout.saturating_sub(DELAY_TIMEOUT_STEP)
+                    };
+                    self.delay_timeout = clamp_delay_timeout(timeout);
+                    self.status
+                        .info(format!("Latency test timeout {}ms", self.delay_timeout));
+                }
+            }
+            InputEvent::OpenPalette => self.palette = Some(PaletteState::default()),
+            InputEvent::PickServer => {
+                let servers = self.config_state.servers();
+                if !servers.is_empty() {
+                    let using = self.config_state.server();
+                    let index = servers.iter().position(|x| Some(x) == using);
+                    self.server_picker = Some(index.unwrap_or_default());
                 }
             }
             InputEvent::NextSort => {
@@ -185,8 +1110,8 @@ impl<'a> TuiStates<'a> {
         Ok(None)
     }
 
-    pub const fn debug_page_index(&self) -> u8 {
-        Self::TITLES.len() as u8 - 1
+    pub fn debug_page_index(&self) -> u8 {
+        self.titles.len() as u8 - 1
     }
 
     fn drop_events(&mut self, num: usize) -> impl Iterator<Item = Event> + '_ {
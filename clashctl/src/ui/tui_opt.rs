@@ -2,8 +2,15 @@ use smart_default::SmartDefault;
 
 #[derive(Debug, SmartDefault, clap::Parser)]
 pub struct TuiOpt {
-    #[clap(default_value = "5")]
-    #[default = 5.0]
-    /// Interval between requests
-    pub interval: f32,
+    /// Interval between requests in seconds, overriding `poll_interval` in
+    /// config [default: 5]
+    pub interval: Option<f32>,
+    /// Disable switching proxies, updating providers, reloading config and
+    /// changing mode, for shared or monitoring setups
+    #[clap(long)]
+    pub read_only: bool,
+    /// Serve cached proxies & selections as JSON on localhost at this port,
+    /// read-only
+    #[clap(long, value_name = "PORT")]
+    pub serve: Option<u16>,
 }
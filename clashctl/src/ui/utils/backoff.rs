@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+/// Exponential backoff, doubling from `base` up to `max`
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            current: base,
+        }
+    }
+
+    /// Duration to wait before next retry
+    pub fn next_backoff(&mut self) -> Duration {
+        let ret = self.current;
+        self.current = (self.current * 2).min(self.max);
+        ret
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.base
+    }
+}
+
+#[test]
+fn test_backoff() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+    assert_eq!(backoff.next_backoff(), Duration::from_secs(1));
+    assert_eq!(backoff.next_backoff(), Duration::from_secs(2));
+    assert_eq!(backoff.next_backoff(), Duration::from_secs(4));
+    assert_eq!(backoff.next_backoff(), Duration::from_secs(5));
+    backoff.reset();
+    assert_eq!(backoff.next_backoff(), Duration::from_secs(1));
+}
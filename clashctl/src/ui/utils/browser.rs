@@ -0,0 +1,63 @@
+use std::io;
+
+use url::Url;
+
+/// Url of `group` in dashboard at `base`, with `proxy` pointed in it if any,
+/// like `http://127.0.0.1:9090/ui?group=Proxy&proxy=HK`
+pub fn dashboard_url(base: &Url, group: &str, proxy: Option<&str>) -> Url {
+    let mut url = base.clone();
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("group", group);
+        if let Some(proxy) = proxy {
+            query.append_pair("proxy", proxy);
+        }
+    }
+    url
+}
+
+/// Open `url` with default browser of the OS, without waiting for it
+#[cfg(feature = "browser")]
+pub fn open_in_browser(url: &Url) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        // Empty title, or `start` takes quoted url as title
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url.as_str())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(not(feature = "browser"))]
+pub fn open_in_browser(_: &Url) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "built without `browser` feature",
+    ))
+}
+
+#[test]
+fn test_dashboard_url() {
+    let base = Url::parse("http://127.0.0.1:9090/ui").unwrap();
+
+    assert_eq!(
+        dashboard_url(&base, "Proxy", None).as_str(),
+        "http://127.0.0.1:9090/ui?group=Proxy"
+    );
+    assert_eq!(
+        dashboard_url(&base, "Proxy", Some("HK 01")).as_str(),
+        "http://127.0.0.1:9090/ui?group=Proxy&proxy=HK+01"
+    );
+}
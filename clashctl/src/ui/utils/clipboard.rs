@@ -0,0 +1,21 @@
+use std::io;
+
+/// Copy `text` to system clipboard with an OSC 52 escape sequence. It's
+/// handled by the terminal emulator, so this works over SSH and without a
+/// display server
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "built without `clipboard` feature",
+    ))
+}
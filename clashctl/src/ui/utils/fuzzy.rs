@@ -0,0 +1,35 @@
+/// Match `pattern` against `target` as a case-insensitive subsequence.
+///
+/// Returns the char indices of `target` that matched, or `None` if not all
+/// chars of `pattern` can be found in order. An empty pattern matches
+/// everything with no highlighted chars.
+pub fn fuzzy_match(pattern: &str, target: &str) -> Option<Vec<usize>> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut ret = Vec::new();
+
+    for (i, c) in target.chars().enumerate() {
+        match pattern.peek() {
+            Some(p) if c.to_lowercase().any(|c| c == *p) => {
+                ret.push(i);
+                pattern.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    if pattern.peek().is_none() {
+        Some(ret)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert_eq!(fuzzy_match("", "HK 01"), Some(vec![]));
+    assert_eq!(fuzzy_match("hk1", "HK 01"), Some(vec![0, 1, 4]));
+    assert_eq!(fuzzy_match("香港", "🇭🇰 香港 02"), Some(vec![3, 4]));
+    assert_eq!(fuzzy_match("jp", "HK 01"), None);
+    assert_eq!(fuzzy_match("10", "HK 01"), None);
+}
@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+/// Polls taking longer than this are taken as slow
+const SLOW_POLL: Duration = Duration::from_secs(1);
+
+/// Without success for this many poll intervals, states are taken as stale
+const STALE_POLLS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    // Polls are slow or haven't succeeded for a while
+    Stale,
+    // Last poll failed
+    Failing,
+}
+
+/// Outcomes of polling server, telling whether states shown are in sync
+#[derive(Debug, Clone, Default)]
+pub struct PollHealth {
+    // Expected time between polls
+    interval: Duration,
+    last_ok: Option<Instant>,
+    last_elapsed: Duration,
+    last_error: Option<String>,
+}
+
+impl PollHealth {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    /// Poll finished at `at` after `elapsed`
+    pub fn succeed(&mut self, at: Instant, elapsed: Duration) {
+        self.last_ok = Some(at);
+        self.last_elapsed = elapsed;
+        self.last_error = None;
+    }
+
+    pub fn fail(&mut self, error: String) {
+        self.last_error = Some(error);
+    }
+
+    /// Forget outcomes of previous polls, like when switched to another server
+    pub fn reset(&mut self) {
+        *self = Self::new(self.interval)
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Health as of `now`, `None` before any poll is done
+    pub fn health(&self, now: Instant) -> Option<Health> {
+        if self.last_error.is_some() {
+            return Some(Health::Failing);
+        }
+        let last_ok = self.last_ok?;
+        let stale = now.saturating_duration_since(last_ok) > self.interval * STALE_POLLS;
+        if stale || self.last_elapsed > SLOW_POLL {
+            Some(Health::Stale)
+        } else {
+            Some(Health::Ok)
+        }
+    }
+}
+
+#[test]
+fn test_poll_health() {
+    let start = Instant::now();
+    let mut health = PollHealth::new(Duration::from_secs(5));
+    assert_eq!(health.health(start), None);
+
+    health.succeed(start, Duration::from_millis(20));
+    assert_eq!(health.health(start + Duration::from_secs(10)), Some(Health::Ok));
+    assert_eq!(health.health(start + Duration::from_secs(16)), Some(Health::Stale));
+
+    health.succeed(start, Duration::from_secs(2));
+    assert_eq!(health.health(start), Some(Health::Stale));
+
+    health.fail("connection refused".to_owned());
+    assert_eq!(health.health(start), Some(Health::Failing));
+    assert_eq!(health.last_error(), Some("connection refused"));
+
+    health.succeed(start, Duration::from_millis(20));
+    assert_eq!(health.health(start), Some(Health::Ok));
+    assert_eq!(health.last_error(), None);
+
+    health.reset();
+    assert_eq!(health.health(start), None);
+}
@@ -1,4 +1,4 @@
-use std::{borrow::Cow, ops::Range};
+use std::{borrow::Cow, collections::VecDeque, mem, ops::Range};
 
 use tui::{
     style::{Color, Modifier, Style},
@@ -6,6 +6,8 @@ use tui::{
     widgets::{Block, Borders},
 };
 
+use unicode_width::UnicodeWidthChar;
+
 use crate::{IntoSpans, Wrap};
 
 pub fn help_footer(content: &str, normal: Style, highlight: Style) -> Spans {
@@ -98,6 +100,97 @@ pub fn spans_window_owned<'a>(mut spans: Spans<'a>, range: &Range<usize>) -> Spa
     }
 }
 
+/// Rest of `string` after scrolling `cols` columns to the right, with the
+/// number of chars skipped. Wide chars are skipped as a whole rather than cut
+/// in half, so the rest may start a column later
+pub fn skip_columns(string: &str, cols: usize) -> (usize, &str) {
+    let mut skipped = 0;
+    for (count, (index, c)) in string.char_indices().enumerate() {
+        if skipped >= cols {
+            return (count, &string[index..]);
+        }
+        skipped += c.width().unwrap_or_default();
+    }
+    (string.chars().count(), "")
+}
+
+/// Rows of a scrollbar of `height` occupied by its thumb, when `pos` out of
+/// `total` items is selected. Thumb is at least one row tall and touches both
+/// ends when the first or last item is selected
+pub fn scrollbar_thumb(pos: usize, total: usize, height: usize) -> Range<usize> {
+    if total <= 1 || height == 0 {
+        return 0..height;
+    }
+    let size = (height / total).max(1);
+    let start = pos.min(total - 1) * (height - size) / (total - 1);
+    start..start + size
+}
+
+/// Break `spans` into lines no wider than `width`, at spaces where possible.
+/// Words longer than a line are cut. Lines after the first are indented by
+/// `indent` columns, and styles are kept across breaks
+pub fn wrap_spans(spans: &Spans, width: usize, indent: usize) -> Vec<Spans<'static>> {
+    let width = width.max(1);
+    // Indent is dropped if it leaves no room for text
+    let indent = if indent < width { indent } else { 0 };
+
+    let mut lines = vec![];
+    let mut line: Vec<(char, Style)> = vec![];
+    let mut line_width = 0;
+    let mut chars: VecDeque<_> = spans
+        .0
+        .iter()
+        .flat_map(|x| x.content.chars().map(move |c| (c, x.style)))
+        .collect();
+    while let Some((c, style)) = chars.pop_front() {
+        let char_width = c.width().unwrap_or_default();
+        let room = if lines.is_empty() { width } else { width - indent };
+        if line_width + char_width > room && !line.is_empty() {
+            // Move the unfinished word to next line, unless it's all there is
+            let rest = match line.iter().rposition(|(c, _)| *c == ' ') {
+                Some(pos) if pos > 0 => line.split_off(pos + 1),
+                _ => vec![],
+            };
+            while line.last().is_some_and(|(c, _)| *c == ' ') {
+                line.pop();
+            }
+            lines.push(mem::take(&mut line));
+            line_width = 0;
+            // Wrap the moved word again, as next line may have less room
+            chars.push_front((c, style));
+            for x in rest.into_iter().rev() {
+                chars.push_front(x);
+            }
+            continue;
+        }
+        if c == ' ' && line.is_empty() && !lines.is_empty() {
+            continue;
+        }
+        line.push((c, style));
+        line_width += char_width;
+    }
+    lines.push(line);
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut ret: Vec<Span> = vec![];
+            if i > 0 && indent > 0 {
+                ret.push(Span::raw(" ".repeat(indent)));
+            }
+            let start = ret.len();
+            for (c, style) in line {
+                match ret[start..].last_mut() {
+                    Some(last) if last.style == style => last.content.to_mut().push(c),
+                    _ => ret.push(Span::styled(c.to_string(), style)),
+                }
+            }
+            Spans(ret)
+        })
+        .collect()
+}
+
 pub fn get_block(title: &str) -> Block {
     Block::default()
         .borders(Borders::ALL)
@@ -125,3 +218,69 @@ fn test_string_window() {
     assert_eq!("代理", &string_window(&test, &(2..4)));
     assert_eq!("理相关的 API", &string_window(&test, &(3..114)));
 }
+
+#[test]
+fn test_skip_columns() {
+    assert_eq!(skip_columns("hong-kong", 0), (0, "hong-kong"));
+    assert_eq!(skip_columns("hong-kong", 5), (5, "kong"));
+    assert_eq!(skip_columns("hong", 10), (4, ""));
+    // Each of them takes two columns
+    assert_eq!(skip_columns("香港节点", 2), (1, "港节点"));
+    assert_eq!(skip_columns("香港节点", 3), (2, "节点"));
+    assert_eq!(skip_columns("🇭🇰 HK", 1), (1, "🇰 HK"));
+}
+
+#[test]
+fn test_scrollbar_thumb() {
+    assert_eq!(scrollbar_thumb(0, 10, 5), 0..1);
+    assert_eq!(scrollbar_thumb(5, 10, 5), 2..3);
+    assert_eq!(scrollbar_thumb(9, 10, 5), 4..5);
+    assert_eq!(scrollbar_thumb(0, 2, 6), 0..3);
+    assert_eq!(scrollbar_thumb(1, 2, 6), 3..6);
+    assert_eq!(scrollbar_thumb(0, 1, 6), 0..6);
+    assert_eq!(scrollbar_thumb(0, 3, 0), 0..0);
+}
+
+#[test]
+fn test_wrap_spans() {
+    use unicode_width::UnicodeWidthStr;
+
+    let text = |lines: Vec<Spans>| {
+        lines
+            .iter()
+            .map(|x| x.0.iter().map(|x| x.content.as_ref()).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    let red = Style::default().fg(Color::Red);
+    let spans = Spans(vec![
+        Span::styled("INFO ", red),
+        Span::raw("dial tcp 1.2.3.4:443 timeout"),
+    ]);
+    assert_eq!(
+        text(wrap_spans(&spans, 12, 5)),
+        [
+            "INFO dial",
+            "     tcp",
+            "     1.2.3.4",
+            "     :443",
+            "     timeout"
+        ]
+    );
+    assert_eq!(text(wrap_spans(&spans, 80, 5)), ["INFO dial tcp 1.2.3.4:443 timeout"]);
+    // Long words are cut
+    assert_eq!(text(wrap_spans(&Spans::from("abcdefgh"), 3, 0)), ["abc", "def", "gh"]);
+    assert_eq!(text(wrap_spans(&Spans::from("香港节点"), 5, 0)), ["香港", "节点"]);
+
+    // Word moved to next line is cut again if it's longer than what's left
+    // after indent
+    let spans = Spans::from(format!("INFO {} ok", "x".repeat(45)));
+    let lines = text(wrap_spans(&spans, 20, 5));
+    assert!(lines.iter().all(|x| x.width() <= 20), "{:?}", lines);
+    assert_eq!(lines.concat().replace(' ', ""), format!("INFO{}ok", "x".repeat(45)));
+    assert_eq!(lines[0], "INFO");
+    assert_eq!(lines[1], format!("     {}", "x".repeat(15)));
+
+    // Style is kept on the part moved to next line
+    let lines = wrap_spans(&Spans(vec![Span::styled("ab cd", red)]), 3, 0);
+    assert_eq!(lines[1].0[0].style, red);
+}
@@ -27,6 +27,15 @@ pub trait HMS {
         write!(ret, "{}s", s).expect("Cannot write to buf");
         ret
     }
+
+    /// Only the largest unit, like `3s`, `1m` or `2h`
+    fn coarse(&self) -> String {
+        match self.as_second() {
+            s if s.abs() < 60 => format!("{}s", s),
+            s if s.abs() < 3600 => format!("{}m", s / 60),
+            s => format!("{}h", s / 3600),
+        }
+    }
 }
 
 impl HMS for chrono::Duration {
@@ -40,3 +49,12 @@ impl HMS for std::time::Duration {
         self.as_secs().try_into().expect("Seconds to big")
     }
 }
+
+#[test]
+fn test_coarse() {
+    use std::time::Duration;
+
+    assert_eq!(Duration::from_secs(3).coarse(), "3s");
+    assert_eq!(Duration::from_secs(119).coarse(), "1m");
+    assert_eq!(Duration::from_secs(7200).coarse(), "2h");
+}
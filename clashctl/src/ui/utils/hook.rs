@@ -0,0 +1,60 @@
+use std::{io, process::Child};
+
+/// Fill `{group}` & `{proxy}` in `template` with quoted `group` & `proxy`, so
+/// names are passed to shell as is
+pub fn fill_hook(template: &str, group: &str, proxy: &str) -> String {
+    template
+        .replace("{group}", &quote(group))
+        .replace("{proxy}", &quote(proxy))
+}
+
+fn quote(value: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", value.replace('"', ""))
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// Run `command` with system shell, without waiting for it
+#[cfg(feature = "hooks")]
+pub fn run_hook(command: &str) -> io::Result<Child> {
+    use std::process::{Command, Stdio};
+
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+#[cfg(not(feature = "hooks"))]
+pub fn run_hook(_: &str) -> io::Result<Child> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "built without `hooks` feature",
+    ))
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_fill_hook() {
+    assert_eq!(
+        fill_hook("notify-send {group} {proxy}", "Proxy", "HK 01"),
+        "notify-send 'Proxy' 'HK 01'"
+    );
+    assert_eq!(
+        fill_hook("echo {proxy}", "Proxy", "it's $(rm -rf ~)"),
+        r"echo 'it'\''s $(rm -rf ~)'"
+    );
+}
@@ -1,12 +1,23 @@
 mod_use::mod_use![
     hms,
+    backoff,
+    health,
     ext,
+    fuzzy,
     wrap,
     coord,
     pulse,
     helper,
     interval,
     as_color,
+    clipboard,
+    browser,
+    hook,
     tui_logger
     ticks_counter
 ];
+
+#[cfg(test)]
+mod testutil;
+#[cfg(test)]
+pub use testutil::*;
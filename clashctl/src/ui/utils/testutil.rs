@@ -0,0 +1,44 @@
+use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use unicode_width::UnicodeWidthStr;
+
+/// Render `widget` into an empty `width` x `height` buffer, see
+/// [`buffer_to_string`]
+pub fn render_to_string(widget: impl Widget, width: u16, height: u16) -> String {
+    let area = Rect::new(0, 0, width, height);
+    let mut buffer = Buffer::empty(area);
+    widget.render(area, &mut buffer);
+    buffer_to_string(&buffer)
+}
+
+/// Symbols of `buffer` without styles, one line per row with trailing
+/// spaces trimmed. Cells hidden behind wide chars are skipped, so text
+/// reads as it does on screen
+pub fn buffer_to_string(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    buffer
+        .content
+        .chunks(width.max(1))
+        .map(|row| {
+            let mut line = String::new();
+            let mut hidden = 0;
+            for cell in row {
+                if hidden > 0 {
+                    hidden -= 1;
+                    continue;
+                }
+                line += &cell.symbol;
+                hidden = cell.symbol.width().saturating_sub(1);
+            }
+            line.trim_end().to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_render_to_string() {
+    use tui::widgets::Paragraph;
+
+    let text = render_to_string(Paragraph::new("香港 01\nab  "), 8, 3);
+    assert_eq!(text, "香港 01\nab\n");
+}